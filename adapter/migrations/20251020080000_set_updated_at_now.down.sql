@@ -0,0 +1,7 @@
+-- Add down migration script here
+CREATE OR REPLACE FUNCTION set_updated_at() RETURNS trigger AS '
+       BEGIN
+        new.updated_at := "now";
+        RETURN NEW;
+       END;
+' LANGUAGE 'plpgsql';
@@ -0,0 +1,8 @@
+-- Add up migration script here
+-- "now" が識別子として解釈され UPDATE が失敗していたため、関数呼び出しに置き換える
+CREATE OR REPLACE FUNCTION set_updated_at() RETURNS trigger AS '
+       BEGIN
+        new.updated_at := now();
+        RETURN NEW;
+       END;
+' LANGUAGE 'plpgsql';
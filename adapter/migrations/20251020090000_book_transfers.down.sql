@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS book_ownership_histories;
+DROP TABLE IF EXISTS book_transfers;
@@ -0,0 +1,10 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP(3) WITH TIME ZONE;
+ALTER TABLE books ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP(3) WITH TIME ZONE;
+
+-- 論理削除済みユーザーのメールアドレスは再登録できるようにする
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_email_key;
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_active_key ON users(email) WHERE deleted_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS users_deleted_at_idx ON users(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX IF NOT EXISTS books_deleted_at_idx ON books(deleted_at) WHERE deleted_at IS NOT NULL;
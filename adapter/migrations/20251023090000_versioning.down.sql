@@ -0,0 +1,6 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS users_version_trigger ON users;
+DROP TRIGGER IF EXISTS books_version_trigger ON books;
+ALTER TABLE users DROP COLUMN IF EXISTS version;
+ALTER TABLE books DROP COLUMN IF EXISTS version;
+DROP FUNCTION IF EXISTS increment_version();
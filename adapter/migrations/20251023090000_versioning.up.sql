@@ -0,0 +1,18 @@
+-- Add up migration script here
+CREATE OR REPLACE FUNCTION increment_version() RETURNS trigger AS '
+       BEGIN
+        new.version := old.version + 1;
+        RETURN NEW;
+       END;
+' LANGUAGE 'plpgsql';
+
+ALTER TABLE books ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 1;
+
+CREATE TRIGGER books_version_trigger
+    BEFORE UPDATE ON books FOR EACH ROW
+    EXECUTE PROCEDURE increment_version();
+
+CREATE TRIGGER users_version_trigger
+    BEFORE UPDATE ON users FOR EACH ROW
+    EXECUTE PROCEDURE increment_version();
@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified_at TIMESTAMP(3) WITH TIME ZONE;
+
+-- 既存のユーザーは管理者が登録したものなので確認済みとして扱う
+UPDATE users SET email_verified_at = created_at WHERE email_verified_at IS NULL;
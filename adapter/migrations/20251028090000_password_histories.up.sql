@@ -0,0 +1,12 @@
+-- Add up migration script here
+-- 再利用を禁止するため、設定したパスワードのハッシュを新しい順に保持する
+CREATE TABLE IF NOT EXISTS password_histories (
+    password_history_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL,
+    password_hash VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP(3) WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS password_histories_user_id_idx ON password_histories(user_id, created_at DESC);
@@ -0,0 +1,6 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS role_permissions;
+UPDATE users
+SET role_id = (SELECT role_id FROM roles WHERE name = 'User')
+WHERE role_id = (SELECT role_id FROM roles WHERE name = 'Librarian');
+DELETE FROM roles WHERE name = 'Librarian';
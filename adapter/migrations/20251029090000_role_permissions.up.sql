@@ -0,0 +1,25 @@
+-- Add up migration script here
+-- ロールごとに付与する権限。権限は "book:write:any" のような文字列で保持する
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role_id UUID NOT NULL,
+    permission VARCHAR(64) NOT NULL,
+
+    PRIMARY KEY (role_id, permission),
+    FOREIGN KEY (role_id) REFERENCES roles(role_id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+INSERT INTO roles(name) VALUES ('Admin'), ('Librarian'), ('User') ON CONFLICT DO NOTHING;
+
+INSERT INTO role_permissions(role_id, permission)
+SELECT r.role_id, p.permission
+FROM roles AS r
+INNER JOIN (
+    VALUES
+        ('Admin', 'book:write:any'),
+        ('Admin', 'user:manage'),
+        ('Admin', 'checkout:force-return'),
+        ('Admin', 'audit:read'),
+        ('Librarian', 'book:write:any'),
+        ('Librarian', 'checkout:force-return')
+) AS p(role_name, permission) ON p.role_name = r.name
+ON CONFLICT DO NOTHING;
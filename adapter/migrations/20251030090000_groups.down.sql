@@ -0,0 +1,5 @@
+-- Add down migration script here
+ALTER TABLE books DROP COLUMN IF EXISTS group_id;
+DROP TABLE IF EXISTS group_members;
+DROP TRIGGER IF EXISTS groups_updated_at_trigger ON groups;
+DROP TABLE IF EXISTS groups;
@@ -0,0 +1,32 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS groups (
+    group_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE,
+    -- TRUE の場合、グループが所有する蔵書はメンバーにしか貸し出さない
+    members_only_lending BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP(3) WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    updated_at TIMESTAMP(3) WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP(3)
+);
+
+CREATE TRIGGER groups_updated_at_trigger
+    BEFORE UPDATE ON groups FOR EACH ROW
+    EXECUTE PROCEDURE set_updated_at();
+
+CREATE TABLE IF NOT EXISTS group_members (
+    group_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    role VARCHAR(32) NOT NULL,
+    joined_at TIMESTAMP(3) WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+
+    PRIMARY KEY (group_id, user_id),
+    FOREIGN KEY (group_id) REFERENCES groups(group_id) ON DELETE CASCADE ON UPDATE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users(user_id) ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS group_members_user_id_idx ON group_members(user_id);
+
+-- グループが削除された蔵書は登録した利用者の個人所有に戻る
+ALTER TABLE books
+    ADD COLUMN IF NOT EXISTS group_id UUID REFERENCES groups(group_id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS books_group_id_idx ON books(group_id);
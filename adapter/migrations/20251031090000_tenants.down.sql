@@ -0,0 +1,25 @@
+-- Add down migration script here
+UPDATE users
+SET role_id = (SELECT role_id FROM roles WHERE name = 'Admin')
+WHERE role_id = (SELECT role_id FROM roles WHERE name = 'SuperAdmin');
+DELETE FROM roles WHERE name = 'SuperAdmin';
+
+ALTER TABLE user_identities DROP CONSTRAINT IF EXISTS user_identities_pkey;
+ALTER TABLE user_identities ADD PRIMARY KEY (provider, subject);
+
+ALTER TABLE groups DROP CONSTRAINT IF EXISTS groups_tenant_id_name_key;
+ALTER TABLE groups ADD CONSTRAINT groups_name_key UNIQUE (name);
+
+DROP INDEX IF EXISTS users_email_active_key;
+CREATE UNIQUE INDEX users_email_active_key ON users(email) WHERE deleted_at IS NULL;
+
+ALTER TABLE audit_logs DROP COLUMN IF EXISTS tenant_id;
+ALTER TABLE user_identities DROP COLUMN IF EXISTS tenant_id;
+ALTER TABLE groups DROP COLUMN IF EXISTS tenant_id;
+ALTER TABLE returned_checkouts DROP COLUMN IF EXISTS tenant_id;
+ALTER TABLE checkouts DROP COLUMN IF EXISTS tenant_id;
+ALTER TABLE books DROP COLUMN IF EXISTS tenant_id;
+ALTER TABLE users DROP COLUMN IF EXISTS tenant_id;
+
+DROP TRIGGER IF EXISTS tenants_updated_at_trigger ON tenants;
+DROP TABLE IF EXISTS tenants;
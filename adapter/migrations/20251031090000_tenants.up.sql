@@ -0,0 +1,73 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS tenants (
+    tenant_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- サブドメインやヘッダーで指定される識別子
+    slug VARCHAR(63) NOT NULL UNIQUE,
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP(3) WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP(3),
+    updated_at TIMESTAMP(3) WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP(3)
+);
+
+CREATE TRIGGER tenants_updated_at_trigger
+    BEFORE UPDATE ON tenants FOR EACH ROW
+    EXECUTE PROCEDURE set_updated_at();
+
+-- 既存のデータはすべて default テナントに所属させる
+INSERT INTO tenants(slug, name) VALUES ('default', 'Default') ON CONFLICT DO NOTHING;
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS tenant_id UUID REFERENCES tenants(tenant_id) ON DELETE CASCADE;
+ALTER TABLE books ADD COLUMN IF NOT EXISTS tenant_id UUID REFERENCES tenants(tenant_id) ON DELETE CASCADE;
+ALTER TABLE checkouts ADD COLUMN IF NOT EXISTS tenant_id UUID REFERENCES tenants(tenant_id) ON DELETE CASCADE;
+ALTER TABLE returned_checkouts ADD COLUMN IF NOT EXISTS tenant_id UUID REFERENCES tenants(tenant_id) ON DELETE CASCADE;
+ALTER TABLE groups ADD COLUMN IF NOT EXISTS tenant_id UUID REFERENCES tenants(tenant_id) ON DELETE CASCADE;
+ALTER TABLE user_identities ADD COLUMN IF NOT EXISTS tenant_id UUID REFERENCES tenants(tenant_id) ON DELETE CASCADE;
+-- 操作者のいない監査ログはテナントが特定できないことがある
+ALTER TABLE audit_logs ADD COLUMN IF NOT EXISTS tenant_id UUID REFERENCES tenants(tenant_id) ON DELETE CASCADE;
+
+UPDATE users SET tenant_id = (SELECT tenant_id FROM tenants WHERE slug = 'default');
+UPDATE books SET tenant_id = (SELECT tenant_id FROM tenants WHERE slug = 'default');
+UPDATE checkouts SET tenant_id = (SELECT tenant_id FROM tenants WHERE slug = 'default');
+UPDATE returned_checkouts SET tenant_id = (SELECT tenant_id FROM tenants WHERE slug = 'default');
+UPDATE groups SET tenant_id = (SELECT tenant_id FROM tenants WHERE slug = 'default');
+UPDATE user_identities SET tenant_id = (SELECT tenant_id FROM tenants WHERE slug = 'default');
+UPDATE audit_logs SET tenant_id = (SELECT tenant_id FROM tenants WHERE slug = 'default');
+
+ALTER TABLE users ALTER COLUMN tenant_id SET NOT NULL;
+ALTER TABLE books ALTER COLUMN tenant_id SET NOT NULL;
+ALTER TABLE checkouts ALTER COLUMN tenant_id SET NOT NULL;
+ALTER TABLE returned_checkouts ALTER COLUMN tenant_id SET NOT NULL;
+ALTER TABLE groups ALTER COLUMN tenant_id SET NOT NULL;
+ALTER TABLE user_identities ALTER COLUMN tenant_id SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS users_tenant_id_idx ON users(tenant_id);
+CREATE INDEX IF NOT EXISTS books_tenant_id_idx ON books(tenant_id);
+CREATE INDEX IF NOT EXISTS checkouts_tenant_id_idx ON checkouts(tenant_id);
+CREATE INDEX IF NOT EXISTS returned_checkouts_tenant_id_idx ON returned_checkouts(tenant_id);
+CREATE INDEX IF NOT EXISTS audit_logs_tenant_id_idx ON audit_logs(tenant_id);
+
+-- メールアドレスやグループ名、外部 ID はテナントごとに一意とする
+DROP INDEX IF EXISTS users_email_active_key;
+CREATE UNIQUE INDEX users_email_active_key ON users(tenant_id, email) WHERE deleted_at IS NULL;
+
+ALTER TABLE groups DROP CONSTRAINT IF EXISTS groups_name_key;
+ALTER TABLE groups ADD CONSTRAINT groups_tenant_id_name_key UNIQUE (tenant_id, name);
+
+ALTER TABLE user_identities DROP CONSTRAINT IF EXISTS user_identities_pkey;
+ALTER TABLE user_identities ADD PRIMARY KEY (tenant_id, provider, subject);
+
+-- SuperAdmin はテナントの作成を含むすべての権限を持つ
+INSERT INTO roles(name) VALUES ('SuperAdmin') ON CONFLICT DO NOTHING;
+
+INSERT INTO role_permissions(role_id, permission)
+SELECT r.role_id, p.permission
+FROM roles AS r
+CROSS JOIN (
+    VALUES
+        ('book:write:any'),
+        ('user:manage'),
+        ('checkout:force-return'),
+        ('audit:read'),
+        ('tenant:manage')
+) AS p(permission)
+WHERE r.name = 'SuperAdmin'
+ON CONFLICT DO NOTHING;
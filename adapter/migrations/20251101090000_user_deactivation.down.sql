@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE users
+    DROP COLUMN IF EXISTS deactivated_at,
+    DROP COLUMN IF EXISTS active;
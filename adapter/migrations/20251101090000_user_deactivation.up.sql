@@ -0,0 +1,5 @@
+-- Add up migration script here
+-- FALSE の場合はログインできない。削除と異なり、蔵書や貸出の記録はそのまま残す
+ALTER TABLE users
+    ADD COLUMN IF NOT EXISTS active BOOLEAN NOT NULL DEFAULT TRUE,
+    ADD COLUMN IF NOT EXISTS deactivated_at TIMESTAMP(3) WITH TIME ZONE;
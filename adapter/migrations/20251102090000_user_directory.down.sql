@@ -0,0 +1,2 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS users_tenant_id_name_idx;
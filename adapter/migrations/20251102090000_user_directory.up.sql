@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- 利用者の一覧は名前順に返す
+CREATE INDEX IF NOT EXISTS users_tenant_id_name_idx ON users(tenant_id, name);
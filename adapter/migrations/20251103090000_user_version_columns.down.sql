@@ -0,0 +1,6 @@
+-- Add down migration script here
+DROP TRIGGER IF EXISTS users_version_trigger ON users;
+
+CREATE TRIGGER users_version_trigger
+    BEFORE UPDATE ON users FOR EACH ROW
+    EXECUTE PROCEDURE increment_version();
@@ -0,0 +1,9 @@
+-- Add up migration script here
+-- バージョンはクライアントが If-Match 付きで変更できる列が変わった場合だけ上げる。
+-- メールアドレスの確認日時や無効化などの記録では上げず、不要な 412 を返さないようにする
+DROP TRIGGER IF EXISTS users_version_trigger ON users;
+
+CREATE TRIGGER users_version_trigger
+    BEFORE UPDATE ON users FOR EACH ROW
+    WHEN ((OLD.name, OLD.email, OLD.role_id) IS DISTINCT FROM (NEW.name, NEW.email, NEW.role_id))
+    EXECUTE PROCEDURE increment_version();
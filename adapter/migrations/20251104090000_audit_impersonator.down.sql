@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS audit_logs_impersonator_id_idx;
+
+ALTER TABLE audit_logs DROP COLUMN IF EXISTS impersonator_id;
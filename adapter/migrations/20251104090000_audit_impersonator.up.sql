@@ -0,0 +1,5 @@
+-- Add up migration script here
+-- 代理ログイン中の操作では、操作者（actor_id）は代理された利用者で、実際に操作した管理者をここに残す
+ALTER TABLE audit_logs ADD COLUMN IF NOT EXISTS impersonator_id UUID;
+
+CREATE INDEX IF NOT EXISTS audit_logs_impersonator_id_idx ON audit_logs(impersonator_id);
@@ -0,0 +1,67 @@
+use kernel::model::{
+    api_key::{ApiKey, ApiKeyScope, AuthorizedApiKey},
+    id::{ApiKeyId, UserId},
+};
+use shared::error::{AppError, AppResult};
+use sqlx::types::chrono::{DateTime, Utc};
+use std::str::FromStr;
+
+pub struct ApiKeyRow {
+    pub api_key_id: ApiKeyId,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<ApiKeyRow> for ApiKey {
+    type Error = AppError;
+    fn try_from(value: ApiKeyRow) -> Result<Self, Self::Error> {
+        let ApiKeyRow {
+            api_key_id,
+            name,
+            prefix,
+            scopes,
+            expires_at,
+            last_used_at,
+            created_at,
+        } = value;
+        Ok(ApiKey {
+            api_key_id,
+            name,
+            prefix,
+            scopes: parse_scopes(scopes)?,
+            expires_at,
+            last_used_at,
+            created_at,
+        })
+    }
+}
+
+pub struct AuthorizedApiKeyRow {
+    pub api_key_id: ApiKeyId,
+    pub user_id: UserId,
+    pub scopes: Vec<String>,
+}
+
+impl TryFrom<AuthorizedApiKeyRow> for AuthorizedApiKey {
+    type Error = AppError;
+    fn try_from(value: AuthorizedApiKeyRow) -> Result<Self, Self::Error> {
+        Ok(AuthorizedApiKey {
+            api_key_id: value.api_key_id,
+            user_id: value.user_id,
+            scopes: parse_scopes(value.scopes)?,
+        })
+    }
+}
+
+fn parse_scopes(scopes: Vec<String>) -> AppResult<Vec<ApiKeyScope>> {
+    scopes
+        .iter()
+        .map(|s| {
+            ApiKeyScope::from_str(s).map_err(|e| AppError::ConversionEntityError(e.to_string()))
+        })
+        .collect()
+}
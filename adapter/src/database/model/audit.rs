@@ -0,0 +1,41 @@
+use kernel::model::{
+    audit::{AuditAction, AuditLog, AuditTarget},
+    id::{AuditLogId, UserId},
+};
+use serde_json::Value;
+use shared::error::AppError;
+use sqlx::types::chrono::{DateTime, Utc};
+use std::str::FromStr;
+use uuid::Uuid;
+
+pub struct AuditLogRow {
+    pub total: i64,
+    pub audit_log_id: AuditLogId,
+    pub actor_id: Option<UserId>,
+    pub impersonator_id: Option<UserId>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Uuid,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<AuditLogRow> for AuditLog {
+    type Error = AppError;
+    fn try_from(value: AuditLogRow) -> Result<Self, Self::Error> {
+        Ok(AuditLog {
+            audit_log_id: value.audit_log_id,
+            actor_id: value.actor_id,
+            impersonator_id: value.impersonator_id,
+            action: AuditAction::from_str(&value.action)
+                .map_err(|e| AppError::ConversionEntityError(e.to_string()))?,
+            target_type: AuditTarget::from_str(&value.target_type)
+                .map_err(|e| AppError::ConversionEntityError(e.to_string()))?,
+            target_id: value.target_id,
+            before: value.before,
+            after: value.after,
+            created_at: value.created_at,
+        })
+    }
+}
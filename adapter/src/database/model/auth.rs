@@ -1,25 +1,43 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use shared::error::{AppError, AppResult};
 use std::str::FromStr;
 
 use kernel::model::{
-    auth::{event::CreateToken, AccessToken},
-    id::UserId,
+    auth::{
+        event::{CreateEmailVerification, CreateImpersonationToken, CreateToken},
+        AccessToken, ClientInfo, EmailVerificationToken, PasswordResetToken, RefreshToken, Session,
+    },
+    id::{SessionId, UserId},
 };
+use serde_json::json;
 
 use crate::redis::model::{RedisKey, RedisValue};
 
 pub struct UserItem {
     pub user_id: UserId,
     pub password_hash: String,
+    pub email_verified: bool,
+    pub active: bool,
 }
 
 pub struct AuthorizationKey(String);
 pub struct AuthorizedUserId(UserId);
 
-pub fn from(event: CreateToken) -> (AuthorizationKey, AuthorizedUserId) {
+// アクセストークンの値。どのセッションで発行されたかも保持する
+#[derive(Serialize, Deserialize)]
+pub struct AccessTokenValue {
+    pub user_id: UserId,
+    pub session_id: Option<SessionId>,
+}
+
+pub fn from(event: &CreateToken, session_id: SessionId) -> (AuthorizationKey, AccessTokenValue) {
     (
-        AuthorizationKey(event.access_token),
-        AuthorizedUserId(event.id),
+        AuthorizationKey(event.access_token.clone()),
+        AccessTokenValue {
+            user_id: event.id,
+            session_id: Some(session_id),
+        },
     )
 }
 
@@ -42,13 +60,273 @@ impl From<&AccessToken> for AuthorizationKey {
 }
 
 impl RedisKey for AuthorizationKey {
-    type Value = AuthorizedUserId;
+    type Value = AccessTokenValue;
 
     fn inner(&self) -> String {
         self.0.clone()
     }
 }
 
+impl RedisValue for AccessTokenValue {
+    fn inner(&self) -> String {
+        json!(self).to_string()
+    }
+}
+
+impl TryFrom<String> for AccessTokenValue {
+    type Error = AppError;
+
+    fn try_from(s: String) -> AppResult<Self> {
+        // セッション管理の導入前に発行されたトークンはユーザー ID のみを保持している
+        if let Ok(user_id) = UserId::from_str(&s) {
+            return Ok(Self {
+                user_id,
+                session_id: None,
+            });
+        }
+        serde_json::from_str(&s).map_err(|e| AppError::ConversionEntityError(e.to_string()))
+    }
+}
+
+// 代理ログインのアクセストークン。通常のトークンと区別できるようプレフィックスを付ける
+pub const IMPERSONATION_TOKEN_PREFIX: &str = "imp_";
+
+pub struct ImpersonationKey(String);
+
+#[derive(Serialize, Deserialize)]
+pub struct ImpersonationValue {
+    pub impersonator: UserId,
+    pub user_id: UserId,
+}
+
+impl ImpersonationKey {
+    pub fn is_impersonation(token: &AccessToken) -> bool {
+        token.0.starts_with(IMPERSONATION_TOKEN_PREFIX)
+    }
+}
+
+impl From<&CreateImpersonationToken> for ImpersonationKey {
+    fn from(event: &CreateImpersonationToken) -> Self {
+        Self(format!("{IMPERSONATION_TOKEN_PREFIX}{}", event.access_token))
+    }
+}
+
+impl From<&AccessToken> for ImpersonationKey {
+    fn from(token: &AccessToken) -> Self {
+        Self(token.0.clone())
+    }
+}
+
+impl From<ImpersonationKey> for AccessToken {
+    fn from(key: ImpersonationKey) -> Self {
+        Self(key.0)
+    }
+}
+
+impl From<&ImpersonationTokenValue> for ImpersonationKey {
+    fn from(value: &ImpersonationTokenValue) -> Self {
+        Self(value.0.clone())
+    }
+}
+
+impl RedisKey for ImpersonationKey {
+    type Value = ImpersonationValue;
+
+    fn inner(&self) -> String {
+        format!("impersonation:{}", self.0)
+    }
+}
+
+impl RedisValue for ImpersonationValue {
+    fn inner(&self) -> String {
+        json!(self).to_string()
+    }
+}
+
+impl TryFrom<String> for ImpersonationValue {
+    type Error = AppError;
+
+    fn try_from(s: String) -> AppResult<Self> {
+        serde_json::from_str(&s).map_err(|e| AppError::ConversionEntityError(e.to_string()))
+    }
+}
+
+// ログインごとのセッション情報
+pub struct SessionKey(SessionId);
+
+#[derive(Serialize, Deserialize)]
+pub struct SessionValue {
+    pub user_id: UserId,
+    // Redis に保存するアクセストークンを使う場合のみ保持する
+    pub access_token: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl SessionValue {
+    pub fn new(
+        user_id: UserId,
+        access_token: Option<String>,
+        client: ClientInfo,
+        ttl: u64,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            user_id,
+            access_token,
+            created_at: now,
+            last_used_at: now,
+            expires_at: now + chrono::Duration::seconds(ttl as i64),
+            user_agent: client.user_agent,
+            ip_address: client.ip_address,
+        }
+    }
+
+    // 有効期限までの残り秒数。Redis の有効期限に使う
+    pub fn remaining_ttl(&self) -> u64 {
+        (self.expires_at - Utc::now()).num_seconds().max(1) as u64
+    }
+
+    pub fn into_session(self, session_id: SessionId) -> Session {
+        Session {
+            session_id,
+            created_at: self.created_at,
+            last_used_at: self.last_used_at,
+            expires_at: self.expires_at,
+            client: ClientInfo {
+                user_agent: self.user_agent,
+                ip_address: self.ip_address,
+            },
+        }
+    }
+}
+
+impl From<SessionId> for SessionKey {
+    fn from(session_id: SessionId) -> Self {
+        Self(session_id)
+    }
+}
+
+impl RedisKey for SessionKey {
+    type Value = SessionValue;
+
+    fn inner(&self) -> String {
+        format!("session:{}", self.0)
+    }
+}
+
+impl RedisValue for SessionValue {
+    fn inner(&self) -> String {
+        json!(self).to_string()
+    }
+}
+
+impl TryFrom<String> for SessionValue {
+    type Error = AppError;
+
+    fn try_from(s: String) -> AppResult<Self> {
+        serde_json::from_str(&s).map_err(|e| AppError::ConversionEntityError(e.to_string()))
+    }
+}
+
+pub struct SessionIdValue(pub SessionId);
+
+impl RedisValue for SessionIdValue {
+    fn inner(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+impl TryFrom<String> for SessionIdValue {
+    type Error = AppError;
+
+    fn try_from(s: String) -> AppResult<Self> {
+        SessionId::from_str(&s).map(Self)
+    }
+}
+
+// 代理ログインのトークンを、代理された利用者と管理者のそれぞれについて保持するセット。
+// どちらかのセッションをすべて失効させるときに、代理ログインのトークンも合わせて削除する
+pub struct UserImpersonationsKey(UserId);
+
+pub struct ImpersonationTokenValue(pub String);
+
+impl From<UserId> for UserImpersonationsKey {
+    fn from(user_id: UserId) -> Self {
+        Self(user_id)
+    }
+}
+
+impl From<&ImpersonationKey> for ImpersonationTokenValue {
+    fn from(key: &ImpersonationKey) -> Self {
+        Self(key.0.clone())
+    }
+}
+
+impl RedisKey for UserImpersonationsKey {
+    type Value = ImpersonationTokenValue;
+
+    fn inner(&self) -> String {
+        format!("user-impersonations:{}", self.0)
+    }
+}
+
+impl RedisValue for ImpersonationTokenValue {
+    fn inner(&self) -> String {
+        self.0.clone()
+    }
+}
+
+impl TryFrom<String> for ImpersonationTokenValue {
+    type Error = AppError;
+
+    fn try_from(s: String) -> AppResult<Self> {
+        Ok(Self(s))
+    }
+}
+
+// ユーザーごとに有効なセッションを保持するセット
+pub struct UserSessionsKey(UserId);
+
+impl From<UserId> for UserSessionsKey {
+    fn from(user_id: UserId) -> Self {
+        Self(user_id)
+    }
+}
+
+impl RedisKey for UserSessionsKey {
+    type Value = SessionIdValue;
+
+    fn inner(&self) -> String {
+        format!("user-sessions:{}", self.0)
+    }
+}
+
+pub struct PasswordResetKey(String);
+
+impl From<&PasswordResetToken> for PasswordResetKey {
+    fn from(token: &PasswordResetToken) -> Self {
+        Self(token.0.clone())
+    }
+}
+
+impl From<PasswordResetKey> for PasswordResetToken {
+    fn from(key: PasswordResetKey) -> Self {
+        Self(key.0)
+    }
+}
+
+impl RedisKey for PasswordResetKey {
+    type Value = AuthorizedUserId;
+
+    fn inner(&self) -> String {
+        format!("password-reset:{}", self.0)
+    }
+}
+
 impl RedisValue for AuthorizedUserId {
     fn inner(&self) -> String {
         self.0.to_string()
@@ -66,7 +344,141 @@ impl TryFrom<String> for AuthorizedUserId {
 }
 
 impl AuthorizedUserId {
+    pub fn new(user_id: UserId) -> Self {
+        Self(user_id)
+    }
+
     pub fn into_inner(self) -> UserId {
         self.0
     }
-}
\ No newline at end of file
+}
+
+// アクセストークンと衝突しないよう、確認用トークンはプレフィックスを付けて保存する
+pub struct EmailVerificationKey(String);
+pub struct EmailVerificationValue {
+    pub user_id: UserId,
+    pub email: String,
+}
+
+pub fn from_verification(
+    event: CreateEmailVerification,
+) -> (EmailVerificationKey, EmailVerificationValue) {
+    (
+        EmailVerificationKey(event.token),
+        EmailVerificationValue {
+            user_id: event.user_id,
+            email: event.email,
+        },
+    )
+}
+
+impl From<&EmailVerificationToken> for EmailVerificationKey {
+    fn from(token: &EmailVerificationToken) -> Self {
+        Self(token.0.clone())
+    }
+}
+
+impl From<EmailVerificationKey> for EmailVerificationToken {
+    fn from(key: EmailVerificationKey) -> Self {
+        Self(key.0)
+    }
+}
+
+impl RedisKey for EmailVerificationKey {
+    type Value = EmailVerificationValue;
+
+    fn inner(&self) -> String {
+        format!("email-verification:{}", self.0)
+    }
+}
+
+impl RedisValue for EmailVerificationValue {
+    fn inner(&self) -> String {
+        json!({ "user_id": self.user_id, "email": self.email }).to_string()
+    }
+}
+
+impl TryFrom<String> for EmailVerificationValue {
+    type Error = AppError;
+
+    fn try_from(s: String) -> AppResult<Self> {
+        let value: serde_json::Value = serde_json::from_str(&s)
+            .map_err(|e| AppError::ConversionEntityError(e.to_string()))?;
+        let field = |name: &str| {
+            value[name].as_str().map(str::to_string).ok_or_else(|| {
+                AppError::ConversionEntityError(format!("missing field: {name}"))
+            })
+        };
+        Ok(Self {
+            user_id: UserId::from_str(&field("user_id")?)
+                .map_err(|e| AppError::ConversionEntityError(e.to_string()))?,
+            email: field("email")?,
+        })
+    }
+}
+
+// リフレッシュトークン。使用済みになると削除し、UsedRefreshTokenKey に移す
+pub struct RefreshTokenKey(String);
+
+#[derive(Serialize, Deserialize)]
+pub struct RefreshTokenValue {
+    pub user_id: UserId,
+    pub session_id: SessionId,
+}
+
+impl RefreshTokenKey {
+    pub fn generate() -> Self {
+        Self(uuid::Uuid::new_v4().simple().to_string())
+    }
+}
+
+impl From<&RefreshToken> for RefreshTokenKey {
+    fn from(token: &RefreshToken) -> Self {
+        Self(token.0.clone())
+    }
+}
+
+impl From<RefreshTokenKey> for RefreshToken {
+    fn from(key: RefreshTokenKey) -> Self {
+        Self(key.0)
+    }
+}
+
+impl RedisKey for RefreshTokenKey {
+    type Value = RefreshTokenValue;
+
+    fn inner(&self) -> String {
+        format!("refresh-token:{}", self.0)
+    }
+}
+
+impl RedisValue for RefreshTokenValue {
+    fn inner(&self) -> String {
+        json!(self).to_string()
+    }
+}
+
+impl TryFrom<String> for RefreshTokenValue {
+    type Error = AppError;
+
+    fn try_from(s: String) -> AppResult<Self> {
+        serde_json::from_str(&s).map_err(|e| AppError::ConversionEntityError(e.to_string()))
+    }
+}
+
+// 使用済みのリフレッシュトークン。再利用を検知したらセッションごと無効にする
+pub struct UsedRefreshTokenKey(String);
+
+impl From<&RefreshToken> for UsedRefreshTokenKey {
+    fn from(token: &RefreshToken) -> Self {
+        Self(token.0.clone())
+    }
+}
+
+impl RedisKey for UsedRefreshTokenKey {
+    type Value = SessionIdValue;
+
+    fn inner(&self) -> String {
+        format!("refresh-token-used:{}", self.0)
+    }
+}
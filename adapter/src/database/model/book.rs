@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
-use kernel::model::book::{Book, Checkout};
-use kernel::model::id::{BookId, CheckoutId, UserId};
+use kernel::model::book::{Book, BookOwnershipHistory, BookTransfer, Checkout, DeletedBook};
+use kernel::model::group::BookGroup;
+use kernel::model::id::{BookId, BookTransferId, CheckoutId, GroupId, UserId};
 use kernel::model::user::{BookOwner, CheckoutUser};
 
 pub struct BookRow {
@@ -11,6 +12,9 @@ pub struct BookRow {
     pub description: String,
     pub owned_by: UserId,
     pub owner_name: String,
+    pub group_id: Option<GroupId>,
+    pub group_name: Option<String>,
+    pub version: i64,
 }
 
 impl BookRow {
@@ -24,12 +28,49 @@ impl BookRow {
             owner: BookOwner {
                 user_id: self.owned_by,
                 name: self.owner_name,
+                group: book_group(self.group_id, self.group_name),
             },
             checkout,
+            version: self.version,
         }
     }
 }
 
+pub struct DeletedBookRow {
+    pub book_id: BookId,
+    pub title: String,
+    pub author: String,
+    pub isbn: String,
+    pub owned_by: UserId,
+    pub owner_name: String,
+    pub group_id: Option<GroupId>,
+    pub group_name: Option<String>,
+    pub deleted_at: DateTime<Utc>,
+}
+
+impl From<DeletedBookRow> for DeletedBook {
+    fn from(value: DeletedBookRow) -> Self {
+        DeletedBook {
+            book_id: value.book_id,
+            title: value.title,
+            author: value.author,
+            isbn: value.isbn,
+            owner: BookOwner {
+                user_id: value.owned_by,
+                name: value.owner_name,
+                group: book_group(value.group_id, value.group_name),
+            },
+            deleted_at: value.deleted_at,
+        }
+    }
+}
+
+fn book_group(group_id: Option<GroupId>, name: Option<String>) -> Option<BookGroup> {
+    group_id
+        .zip(name)
+        .map(|(group_id, name)| BookGroup { group_id, name })
+}
+
 pub struct PaginatedBookRow {
     pub total: i64,
     pub book_id: BookId,
@@ -55,4 +96,54 @@ impl From<BookCheckoutRow> for Checkout {
             checked_out_at: value.checked_out_at,
         }
     }
-}
\ No newline at end of file
+}
+
+pub struct BookOwnershipStateRow {
+    pub book_id: BookId,
+    pub owned_by: UserId,
+    pub checked_out_by: Option<UserId>,
+}
+
+pub struct BookTransferRow {
+    pub transfer_id: BookTransferId,
+    pub book_id: BookId,
+    pub title: String,
+    pub from_user_id: UserId,
+    pub to_user_id: UserId,
+    pub requested_at: DateTime<Utc>,
+}
+
+impl From<BookTransferRow> for BookTransfer {
+    fn from(value: BookTransferRow) -> Self {
+        BookTransfer {
+            transfer_id: value.transfer_id,
+            book_id: value.book_id,
+            title: value.title,
+            from_user_id: value.from_user_id,
+            to_user_id: value.to_user_id,
+            requested_at: value.requested_at,
+        }
+    }
+}
+
+pub struct BookOwnershipHistoryRow {
+    pub book_id: BookId,
+    pub from_user_id: UserId,
+    pub to_user_id: UserId,
+    pub transferred_by: UserId,
+    pub forced: bool,
+    pub transferred_at: DateTime<Utc>,
+}
+
+impl From<BookOwnershipHistoryRow> for BookOwnershipHistory {
+    fn from(value: BookOwnershipHistoryRow) -> Self {
+        BookOwnershipHistory {
+            book_id: value.book_id,
+            from_user_id: value.from_user_id,
+            to_user_id: value.to_user_id,
+            transferred_by: value.transferred_by,
+            forced: value.forced,
+            transferred_at: value.transferred_at,
+        }
+    }
+}
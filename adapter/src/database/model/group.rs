@@ -0,0 +1,47 @@
+use kernel::model::{
+    group::{Group, GroupMember, GroupRole},
+    id::{GroupId, UserId},
+};
+use shared::error::AppError;
+use sqlx::types::chrono::{DateTime, Utc};
+use std::str::FromStr;
+
+pub struct GroupRow {
+    pub group_id: GroupId,
+    pub name: String,
+    pub members_only_lending: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl GroupRow {
+    pub fn into_group(self, members: Vec<GroupMember>) -> Group {
+        Group {
+            group_id: self.group_id,
+            name: self.name,
+            members_only_lending: self.members_only_lending,
+            members,
+            created_at: self.created_at,
+        }
+    }
+}
+
+pub struct GroupMemberRow {
+    pub group_id: GroupId,
+    pub user_id: UserId,
+    pub name: String,
+    pub role: String,
+    pub joined_at: DateTime<Utc>,
+}
+
+impl TryFrom<GroupMemberRow> for GroupMember {
+    type Error = AppError;
+    fn try_from(value: GroupMemberRow) -> Result<Self, Self::Error> {
+        Ok(GroupMember {
+            user_id: value.user_id,
+            name: value.name,
+            role: GroupRole::from_str(&value.role)
+                .map_err(|e| AppError::ConversionEntityError(e.to_string()))?,
+            joined_at: value.joined_at,
+        })
+    }
+}
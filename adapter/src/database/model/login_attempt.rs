@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use kernel::model::id::TenantId;
+use shared::error::{AppError, AppResult};
+
+use crate::redis::model::{RedisKey, RedisValue};
+
+// メールアドレス単位と IP アドレス単位のキーを同じ形式で扱う。メールアドレスはテナントごとに区別する
+fn subject(kind: &str, value: &str) -> String {
+    format!("{kind}:{value}")
+}
+
+// 一定時間内のログイン失敗回数
+pub struct LoginFailureKey(String);
+
+impl LoginFailureKey {
+    pub fn email(tenant_id: TenantId, email: &str) -> Self {
+        Self(subject("email", &format!("{tenant_id}:{email}")))
+    }
+
+    pub fn ip(ip_address: &str) -> Self {
+        Self(subject("ip", ip_address))
+    }
+}
+
+impl RedisKey for LoginFailureKey {
+    type Value = AttemptCount;
+
+    fn inner(&self) -> String {
+        format!("login-failures:{}", self.0)
+    }
+}
+
+pub struct AttemptCount(pub u64);
+
+impl RedisValue for AttemptCount {
+    fn inner(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+impl TryFrom<String> for AttemptCount {
+    type Error = AppError;
+
+    fn try_from(s: String) -> AppResult<Self> {
+        s.parse()
+            .map(Self)
+            .map_err(|e: std::num::ParseIntError| AppError::ConversionEntityError(e.to_string()))
+    }
+}
+
+// しきい値を超えたときのロック
+pub struct LoginLockoutKey(String);
+
+impl LoginLockoutKey {
+    pub fn email(tenant_id: TenantId, email: &str) -> Self {
+        Self(subject("email", &format!("{tenant_id}:{email}")))
+    }
+
+    pub fn ip(ip_address: &str) -> Self {
+        Self(subject("ip", ip_address))
+    }
+}
+
+impl RedisKey for LoginLockoutKey {
+    type Value = BlockedUntil;
+
+    fn inner(&self) -> String {
+        format!("login-lockout:{}", self.0)
+    }
+}
+
+// 失敗のたびに課す待ち時間
+pub struct LoginDelayKey(String);
+
+impl LoginDelayKey {
+    pub fn email(tenant_id: TenantId, email: &str) -> Self {
+        Self(subject("email", &format!("{tenant_id}:{email}")))
+    }
+}
+
+impl RedisKey for LoginDelayKey {
+    type Value = BlockedUntil;
+
+    fn inner(&self) -> String {
+        format!("login-delay:{}", self.0)
+    }
+}
+
+pub struct BlockedUntil(pub DateTime<Utc>);
+
+impl BlockedUntil {
+    pub fn after(secs: u64) -> Self {
+        Self(Utc::now() + chrono::Duration::seconds(secs as i64))
+    }
+
+    // 再試行できるまでの秒数（切り上げ）
+    pub fn remaining_secs(&self) -> u64 {
+        let millis = (self.0 - Utc::now()).num_milliseconds().max(0) as u64;
+        millis.div_ceil(1000).max(1)
+    }
+}
+
+impl RedisValue for BlockedUntil {
+    fn inner(&self) -> String {
+        self.0.to_rfc3339()
+    }
+}
+
+impl TryFrom<String> for BlockedUntil {
+    type Error = AppError;
+
+    fn try_from(s: String) -> AppResult<Self> {
+        Ok(Self(DateTime::parse_from_rfc3339(&s)?.with_timezone(&Utc)))
+    }
+}
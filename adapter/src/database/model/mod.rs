@@ -1,4 +1,11 @@
+pub mod api_key;
+pub mod audit;
 pub mod book;
+pub mod group;
 pub mod auth;
 pub mod user;
 pub mod checkout;
+pub mod login_attempt;
+pub mod oidc;
+pub mod tenant;
+pub mod two_factor;
@@ -0,0 +1,55 @@
+use kernel::model::id::TenantId;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shared::error::{AppError, AppResult};
+
+use crate::redis::model::{RedisKey, RedisValue};
+
+// 認可リクエストの state。コールバックで一度だけ取り出す
+pub struct OidcStateKey(String);
+
+#[derive(Serialize, Deserialize)]
+pub struct OidcStateValue {
+    // ログインを開始したテナント。別のテナントのコールバックでは使えない
+    pub tenant_id: TenantId,
+    pub code_verifier: String,
+    pub nonce: String,
+}
+
+impl OidcStateKey {
+    pub fn generate() -> Self {
+        Self(uuid::Uuid::new_v4().simple().to_string())
+    }
+
+    pub fn state(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for OidcStateKey {
+    fn from(state: String) -> Self {
+        Self(state)
+    }
+}
+
+impl RedisKey for OidcStateKey {
+    type Value = OidcStateValue;
+
+    fn inner(&self) -> String {
+        format!("oidc-state:{}", self.0)
+    }
+}
+
+impl RedisValue for OidcStateValue {
+    fn inner(&self) -> String {
+        json!(self).to_string()
+    }
+}
+
+impl TryFrom<String> for OidcStateValue {
+    type Error = AppError;
+
+    fn try_from(s: String) -> AppResult<Self> {
+        serde_json::from_str(&s).map_err(|e| AppError::ConversionEntityError(e.to_string()))
+    }
+}
@@ -0,0 +1,20 @@
+use kernel::model::{id::TenantId, tenant::Tenant};
+use sqlx::types::chrono::{DateTime, Utc};
+
+pub struct TenantRow {
+    pub tenant_id: TenantId,
+    pub slug: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<TenantRow> for Tenant {
+    fn from(value: TenantRow) -> Self {
+        Tenant {
+            tenant_id: value.tenant_id,
+            slug: value.slug,
+            name: value.name,
+            created_at: value.created_at,
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use kernel::model::{
+    id::{TenantId, UserId},
+    two_factor::{event::CreateLoginChallenge, LoginChallenge, LoginChallengeToken},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use shared::error::{AppError, AppResult};
+use sqlx::types::chrono::{DateTime, Utc};
+
+use crate::redis::model::{RedisKey, RedisValue};
+
+pub struct UserTotpRow {
+    pub secret: String,
+    pub last_used_step: Option<i64>,
+    pub enabled_at: Option<DateTime<Utc>>,
+}
+
+pub struct LoginChallengeKey(String);
+
+impl LoginChallengeKey {
+    pub fn generate() -> Self {
+        Self(uuid::Uuid::new_v4().simple().to_string())
+    }
+}
+
+impl From<&LoginChallengeToken> for LoginChallengeKey {
+    fn from(token: &LoginChallengeToken) -> Self {
+        Self(token.0.clone())
+    }
+}
+
+impl From<LoginChallengeKey> for LoginChallengeToken {
+    fn from(key: LoginChallengeKey) -> Self {
+        Self(key.0)
+    }
+}
+
+impl RedisKey for LoginChallengeKey {
+    type Value = LoginChallengeValue;
+
+    fn inner(&self) -> String {
+        format!("login-challenge:{}", self.0)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct LoginChallengeValue {
+    pub user_id: UserId,
+    pub tenant_id: TenantId,
+    pub email: String,
+}
+
+impl From<CreateLoginChallenge> for LoginChallengeValue {
+    fn from(event: CreateLoginChallenge) -> Self {
+        Self {
+            user_id: event.user_id,
+            tenant_id: event.tenant_id,
+            email: event.email,
+        }
+    }
+}
+
+impl From<LoginChallengeValue> for LoginChallenge {
+    fn from(value: LoginChallengeValue) -> Self {
+        Self {
+            user_id: value.user_id,
+            tenant_id: value.tenant_id,
+            email: value.email,
+        }
+    }
+}
+
+impl RedisValue for LoginChallengeValue {
+    fn inner(&self) -> String {
+        json!(self).to_string()
+    }
+}
+
+impl TryFrom<String> for LoginChallengeValue {
+    type Error = AppError;
+
+    fn try_from(s: String) -> AppResult<Self> {
+        serde_json::from_str(&s).map_err(|e| AppError::ConversionEntityError(e.to_string()))
+    }
+}
@@ -1,4 +1,4 @@
-use kernel::model::{id::UserId, role::Role, user::User};
+use kernel::model::{id::UserId, role::Role, user::{DeletedUser, User}};
 use shared::error::AppError;
 use sqlx::types::chrono::{DateTime, Utc};
 use std::str::FromStr;
@@ -8,6 +8,8 @@ pub struct UserRow {
     pub name: String,
     pub email: String,
     pub role_name: String,
+    pub active: bool,
+    pub version: i64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -20,6 +22,8 @@ impl TryFrom<UserRow> for User {
             name,
             email,
             role_name,
+            active,
+            version,
             ..
         } = value;
         Ok(User {
@@ -28,6 +32,60 @@ impl TryFrom<UserRow> for User {
             email,
             role: Role::from_str(role_name.as_str())
                 .map_err(|e| AppError::ConversionEntityError(e.to_string()))?,
+            active,
+            version,
         })
     }
 }
+
+pub struct PaginatedUserRow {
+    pub total: i64,
+    pub user_id: UserId,
+    pub name: String,
+    pub email: String,
+    pub role_name: String,
+    pub active: bool,
+    pub version: i64,
+}
+
+impl TryFrom<PaginatedUserRow> for User {
+    type Error = AppError;
+    fn try_from(value: PaginatedUserRow) -> Result<Self, Self::Error> {
+        let PaginatedUserRow {
+            user_id,
+            name,
+            email,
+            role_name,
+            active,
+            version,
+            ..
+        } = value;
+        Ok(User {
+            user_id,
+            name,
+            email,
+            role: Role::from_str(role_name.as_str())
+                .map_err(|e| AppError::ConversionEntityError(e.to_string()))?,
+            active,
+            version,
+        })
+    }
+}
+
+pub struct DeletedUserRow {
+    pub user_id: UserId,
+    pub name: String,
+    pub email: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+impl From<DeletedUserRow> for DeletedUser {
+    fn from(value: DeletedUserRow) -> Self {
+        DeletedUser {
+            user_id: value.user_id,
+            name: value.name,
+            email: value.email,
+            deleted_at: value.deleted_at,
+        }
+    }
+}
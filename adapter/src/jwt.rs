@@ -0,0 +1,85 @@
+use chrono::Utc;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use kernel::model::id::{SessionId, UserId};
+use serde::{Deserialize, Serialize};
+use shared::{
+    config::JwtConfig,
+    error::{AppError, AppResult},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: UserId,
+    pub jti: String,
+    // 発行元のセッション
+    pub sid: SessionId,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+// HS256 で署名した JWT を発行・検証する
+pub struct JwtCodec {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    validation: Validation,
+    ttl: u64,
+}
+
+impl JwtCodec {
+    pub fn new(config: &JwtConfig) -> Self {
+        Self {
+            encoding_key: EncodingKey::from_secret(config.secret.as_bytes()),
+            decoding_key: DecodingKey::from_secret(config.secret.as_bytes()),
+            validation: Validation::new(Algorithm::HS256),
+            ttl: config.ttl,
+        }
+    }
+
+    pub fn encode(&self, user_id: UserId, jti: String, session_id: SessionId) -> AppResult<String> {
+        let iat = Utc::now().timestamp();
+        let claims = Claims {
+            sub: user_id,
+            jti,
+            sid: session_id,
+            iat,
+            exp: iat + self.ttl as i64,
+        };
+        encode(&Header::new(Algorithm::HS256), &claims, &self.encoding_key)
+            .map_err(|e| AppError::ConversionEntityError(e.to_string()))
+    }
+
+    // 署名や有効期限が不正な場合は None を返す
+    pub fn decode(&self, token: &str) -> Option<Claims> {
+        decode::<Claims>(token, &self.decoding_key, &self.validation)
+            .ok()
+            .map(|data| data.claims)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codec(secret: &str, ttl: u64) -> JwtCodec {
+        JwtCodec::new(&JwtConfig {
+            secret: secret.into(),
+            ttl,
+            refresh_ttl: 3600,
+        })
+    }
+
+    #[test]
+    fn test_encode_and_decode() -> anyhow::Result<()> {
+        let user_id = UserId::new();
+        let session_id = SessionId::new();
+        let token = codec("secret", 60).encode(user_id, "jti".into(), session_id)?;
+
+        let claims = codec("secret", 60).decode(&token).unwrap();
+        assert_eq!(claims.sub, user_id);
+        assert_eq!(claims.sid, session_id);
+
+        assert!(codec("another", 60).decode(&token).is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use async_trait::async_trait;
+use kernel::{directory::Directory, model::identity::ExternalIdentity};
+use ldap3::{dn_escape, ldap_escape, Ldap, LdapConnAsync, LdapConnSettings, Scope, SearchEntry};
+use shared::{
+    config::LdapConfig,
+    error::{AppError, AppResult},
+};
+use std::time::Duration;
+
+// LDAP の結果コード invalidCredentials
+const INVALID_CREDENTIALS: u32 = 49;
+
+// 利用者の DN とパスワードでシンプルバインドし、成功したらエントリから名前とメールアドレスを読む
+pub struct LdapDirectory {
+    config: LdapConfig,
+}
+
+impl LdapDirectory {
+    pub fn new(config: LdapConfig) -> Self {
+        Self { config }
+    }
+
+    async fn bind_and_search(
+        &self,
+        ldap: &mut Ldap,
+        login: &str,
+        password: &str,
+    ) -> AppResult<Option<ExternalIdentity>> {
+        // タイムアウトは操作ごとに指定する必要がある
+        let timeout = Duration::from_secs(self.config.timeout);
+        let bind_dn = fill_template(&self.config.bind_dn_template, &dn_escape(login));
+        let res = ldap
+            .with_timeout(timeout)
+            .simple_bind(&bind_dn, password)
+            .await
+            .map_err(directory_error)?;
+        if res.rc == INVALID_CREDENTIALS {
+            return Ok(None);
+        }
+        res.success().map_err(directory_error)?;
+
+        let filter = fill_template(&self.config.search_filter, &ldap_escape(login));
+        let attrs = vec![
+            self.config.name_attribute.as_str(),
+            self.config.email_attribute.as_str(),
+        ];
+        let (entries, _) = ldap
+            .with_timeout(timeout)
+            .search(&self.config.search_base, Scope::Subtree, &filter, attrs)
+            .await
+            .and_then(|res| res.success())
+            .map_err(directory_error)?;
+        let entry = entries
+            .into_iter()
+            .next()
+            .map(SearchEntry::construct)
+            .ok_or_else(|| {
+                AppError::ExternalServiceError(format!("No directory entry matches {filter}"))
+            })?;
+
+        let first = |attr: &str| entry.attrs.get(attr).and_then(|v| v.first()).cloned();
+        let email = first(&self.config.email_attribute).ok_or_else(|| {
+            AppError::ExternalServiceError(format!("Directory entry {} has no email", entry.dn))
+        })?;
+
+        Ok(Some(ExternalIdentity {
+            subject: entry.dn.clone(),
+            email,
+            // ディレクトリのメールアドレスは管理者が登録したものとして確認済みとみなす
+            email_verified: true,
+            name: first(&self.config.name_attribute),
+            groups: Vec::new(),
+        }))
+    }
+}
+
+#[async_trait]
+impl Directory for LdapDirectory {
+    fn provider(&self) -> &str {
+        &self.config.url
+    }
+
+    async fn authenticate(
+        &self,
+        login: &str,
+        password: &str,
+    ) -> AppResult<Option<ExternalIdentity>> {
+        // 空のパスワードでのバインドは匿名バインドとして成功してしまうため、問い合わせる前に拒否する
+        if login.is_empty() || password.is_empty() {
+            return Ok(None);
+        }
+
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(Duration::from_secs(self.config.timeout))
+            .set_starttls(self.config.starttls);
+        let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &self.config.url)
+            .await
+            .map_err(directory_error)?;
+        ldap3::drive!(conn);
+
+        let res = self.bind_and_search(&mut ldap, login, password).await;
+        let _ = ldap.unbind().await;
+        res
+    }
+}
+
+fn fill_template(template: &str, username: &str) -> String {
+    template.replace("{username}", username)
+}
+
+fn directory_error(e: ldap3::LdapError) -> AppError {
+    AppError::ExternalServiceError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_template_escapes_login() {
+        let dn = fill_template("uid={username},ou=people,dc=example,dc=com", &dn_escape("a,b=c"));
+        assert_eq!(dn, r"uid=a\2cb\3dc,ou=people,dc=example,dc=com");
+
+        let filter = fill_template("(mail={username})", &ldap_escape("*)(uid=*"));
+        assert_eq!(filter, r"(mail=\2a\29\28uid=\2a)");
+    }
+}
@@ -1,3 +1,8 @@
 pub mod database;
+pub mod jwt;
+pub mod ldap;
+pub mod mailer;
+pub mod oidc;
+pub mod password;
 pub mod repository;
 pub mod redis;
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use kernel::mailer::{Mail, Mailer};
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use shared::{
+    config::{MailConfig, SmtpConfig, SmtpSecurity},
+    error::{AppError, AppResult},
+};
+
+// 設定に応じて SMTP で送るメーラーか、ログに出すだけのメーラーを作る
+pub fn build_mailer(config: MailConfig) -> AppResult<Arc<dyn Mailer>> {
+    match config.smtp {
+        Some(smtp) => Ok(Arc::new(SmtpMailer::new(smtp)?)),
+        None => Ok(Arc::new(LogMailer::new(config.log_body))),
+    }
+}
+
+// 送信せずにログへ出力するだけのメーラー。開発環境や SMTP 未設定時に使う。
+// 本文には確認用やパスワード再設定用のトークンが含まれるため、log_body が有効な場合だけ debug で出す
+pub struct LogMailer {
+    log_body: bool,
+}
+
+impl LogMailer {
+    pub fn new(log_body: bool) -> Self {
+        Self { log_body }
+    }
+}
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, mail: Mail) -> AppResult<()> {
+        tracing::info!(to = %mail.to, subject = %mail.subject, "Mail not sent: SMTP is not configured");
+        if self.log_body {
+            tracing::debug!(to = %mail.to, body = %mail.body, "Mail body");
+        }
+        Ok(())
+    }
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(config: SmtpConfig) -> AppResult<Self> {
+        let builder = match config.security {
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host),
+            SmtpSecurity::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+            }
+            // ローカルのメールサーバー向け。認証情報を平文で送るため本番では使わない
+            SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &config.host,
+            )),
+        }
+        .map_err(mail_error)?
+        .port(config.port);
+        let builder = match (config.username, config.password) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username, password))
+            }
+            _ => builder,
+        };
+        let from = config.from.parse().map_err(mail_error)?;
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, mail: Mail) -> AppResult<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(mail.to.parse().map_err(mail_error)?)
+            .subject(mail.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(mail.body)
+            .map_err(mail_error)?;
+        self.transport.send(message).await.map_err(mail_error)?;
+        Ok(())
+    }
+}
+
+fn mail_error(e: impl std::fmt::Display) -> AppError {
+    AppError::ExternalServiceError(e.to_string())
+}
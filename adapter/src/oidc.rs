@@ -0,0 +1,407 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header, jwk::JwkSet};
+use std::str::FromStr;
+use kernel::model::identity::ExternalIdentity;
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use shared::{
+    config::OidcConfig,
+    error::{AppError, AppResult},
+};
+use tokio::sync::OnceCell;
+
+// `.well-known/openid-configuration` のうち利用する項目
+#[derive(Debug, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    email: Option<String>,
+    email_verified: Option<bool>,
+    name: Option<String>,
+    nonce: Option<String>,
+    // グループのクレーム名は設定で変えられるため、残りのクレームはまとめて受け取る
+    #[serde(flatten)]
+    extra: serde_json::Map<String, Value>,
+}
+
+// PKCE (RFC 7636) の検証値と、それを S256 で変換したチャレンジ
+pub struct Pkce {
+    pub verifier: String,
+    pub challenge: String,
+}
+
+impl Pkce {
+    pub fn generate() -> Self {
+        let verifier = format!(
+            "{}{}",
+            uuid::Uuid::new_v4().simple(),
+            uuid::Uuid::new_v4().simple()
+        );
+        let challenge = code_challenge(&verifier);
+        Self {
+            verifier,
+            challenge,
+        }
+    }
+}
+
+pub fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+// 認可コードフローで ID プロバイダとやり取りする。ディスカバリの結果は初回に取得して使い回す
+pub struct OidcClient {
+    config: OidcConfig,
+    algorithms: Vec<Algorithm>,
+    http: reqwest::Client,
+    metadata: OnceCell<ProviderMetadata>,
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig) -> Self {
+        let algorithms = config
+            .signing_algorithms
+            .iter()
+            .filter_map(|alg| match Algorithm::from_str(alg) {
+                Ok(alg) => Some(alg),
+                Err(_) => {
+                    tracing::warn!(alg, "Ignoring unknown OIDC signing algorithm");
+                    None
+                }
+            })
+            .collect();
+        Self {
+            config,
+            algorithms,
+            http: reqwest::Client::new(),
+            metadata: OnceCell::new(),
+        }
+    }
+
+    pub fn config(&self) -> &OidcConfig {
+        &self.config
+    }
+
+    pub async fn authorization_url(
+        &self,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> AppResult<String> {
+        let metadata = self.metadata().await?;
+        let mut url = reqwest::Url::parse(&metadata.authorization_endpoint)
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", &self.config.redirect_uri)
+            .append_pair("scope", &self.config.scopes)
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", "S256");
+        Ok(url.into())
+    }
+
+    // 認可コードを ID トークンと交換し、署名と発行者・受信者・nonce を検証する
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> AppResult<ExternalIdentity> {
+        let metadata = self.metadata().await?;
+        let mut params = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+            ("client_id", self.config.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(secret) = &self.config.client_secret {
+            params.push(("client_secret", secret.as_str()));
+        }
+
+        let res = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+        // 認可コードや検証値が誤っている場合、プロバイダは 400 を返す
+        if res.status().is_client_error() {
+            tracing::info!(status = %res.status(), "OIDC token request was rejected");
+            return Err(AppError::UnauthenticatedError);
+        }
+        let token: TokenResponse = res
+            .error_for_status()
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?;
+
+        self.verify_id_token(metadata, &token.id_token, nonce).await
+    }
+
+    async fn verify_id_token(
+        &self,
+        metadata: &ProviderMetadata,
+        id_token: &str,
+        nonce: &str,
+    ) -> AppResult<ExternalIdentity> {
+        let header = decode_header(id_token).map_err(|_| AppError::UnauthenticatedError)?;
+        // 署名方式はヘッダーの指定ではなく設定で決める。設定にない方式で署名されたトークンは受け付けない
+        let Some(alg) = self.algorithms.iter().copied().find(|alg| *alg == header.alg) else {
+            tracing::info!(alg = ?header.alg, "OIDC ID token is signed with an unexpected algorithm");
+            return Err(AppError::UnauthenticatedError);
+        };
+        let key = match alg {
+            // HMAC で署名する場合の鍵はクライアントシークレット
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                let secret = self
+                    .config
+                    .client_secret
+                    .as_ref()
+                    .ok_or(AppError::UnauthenticatedError)?;
+                DecodingKey::from_secret(secret.as_bytes())
+            }
+            _ => self.find_signing_key(metadata, header.kid.as_deref(), alg).await?,
+        };
+
+        let mut validation = Validation::new(alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| {
+                tracing::info!(error.message = %e, "Invalid OIDC ID token");
+                AppError::UnauthenticatedError
+            })?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(AppError::UnauthenticatedError);
+        }
+        let email = claims.email.ok_or_else(|| {
+            AppError::UnprocessableEntity("ID token does not contain an email claim".into())
+        })?;
+        let groups = match claims.extra.get(&self.config.groups_claim) {
+            Some(Value::Array(values)) => values
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect(),
+            Some(Value::String(group)) => vec![group.clone()],
+            _ => Vec::new(),
+        };
+
+        Ok(ExternalIdentity {
+            subject: claims.sub,
+            email,
+            email_verified: claims.email_verified.unwrap_or(false),
+            name: claims.name,
+            groups,
+        })
+    }
+
+    // 鍵のローテーションに追従するため、JWKS は検証のたびに取得する
+    async fn find_signing_key(
+        &self,
+        metadata: &ProviderMetadata,
+        kid: Option<&str>,
+        alg: Algorithm,
+    ) -> AppResult<DecodingKey> {
+        let jwks_uri = metadata.jwks_uri.as_ref().ok_or_else(|| {
+            AppError::ExternalServiceError("Provider does not publish jwks_uri".into())
+        })?;
+        let jwks: JwkSet = self.get_json(jwks_uri).await?;
+        // 鍵に方式が宣言されている場合は、それと一致しない鍵を使わない
+        let alg_matches = |jwk: &&jsonwebtoken::jwk::Jwk| {
+            jwk.common
+                .key_algorithm
+                .is_none_or(|key_alg| key_alg.to_string() == format!("{alg:?}"))
+        };
+        let jwk = match kid {
+            Some(kid) => jwks.find(kid).filter(alg_matches),
+            None => jwks.keys.iter().find(alg_matches),
+        }
+        .ok_or(AppError::UnauthenticatedError)?;
+        DecodingKey::from_jwk(jwk).map_err(|_| AppError::UnauthenticatedError)
+    }
+
+    async fn metadata(&self) -> AppResult<&ProviderMetadata> {
+        self.metadata.get_or_try_init(|| self.discover()).await
+    }
+
+    async fn discover(&self) -> AppResult<ProviderMetadata> {
+        let url = format!("{}/.well-known/openid-configuration", self.config.issuer);
+        let metadata: ProviderMetadata = self.get_json(&url).await?;
+        // 別のプロバイダの設定を返された場合は使わない
+        if metadata.issuer.trim_end_matches('/') != self.config.issuer {
+            return Err(AppError::ExternalServiceError(format!(
+                "Issuer mismatch: expected {}, got {}",
+                self.config.issuer, metadata.issuer
+            )));
+        }
+        Ok(metadata)
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> AppResult<T> {
+        self.http
+            .get(url)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| AppError::ExternalServiceError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Form, Json, Router, extract::State, http::StatusCode, routing::{get, post}};
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use serde_json::json;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    const CLIENT_ID: &str = "book-api";
+    const CLIENT_SECRET: &str = "mock-secret";
+    const NONCE: &str = "mock-nonce";
+
+    #[derive(Clone)]
+    struct MockIdp {
+        issuer: String,
+        // 認可リクエストで受け取ったはずのチャレンジ
+        challenge: Arc<Mutex<String>>,
+    }
+
+    async fn discovery(State(idp): State<MockIdp>) -> Json<Value> {
+        Json(json!({
+            "issuer": idp.issuer,
+            "authorization_endpoint": format!("{}/authorize", idp.issuer),
+            "token_endpoint": format!("{}/token", idp.issuer),
+        }))
+    }
+
+    async fn token(
+        State(idp): State<MockIdp>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Result<Json<Value>, StatusCode> {
+        let verifier = form.get("code_verifier").ok_or(StatusCode::BAD_REQUEST)?;
+        let pkce_ok = code_challenge(verifier) == *idp.challenge.lock().unwrap();
+        if form.get("code").map(String::as_str) != Some("valid-code") || !pkce_ok {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let now = chrono::Utc::now().timestamp();
+        let claims = json!({
+            "iss": idp.issuer,
+            "aud": CLIENT_ID,
+            "sub": "idp-user-1",
+            "email": "sso@example.com",
+            "email_verified": true,
+            "name": "SSO User",
+            "groups": ["library-admins", "staff"],
+            "nonce": NONCE,
+            "iat": now,
+            "exp": now + 300,
+        });
+        let id_token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(CLIENT_SECRET.as_bytes()),
+        )
+        .unwrap();
+        Ok(Json(json!({ "access_token": "at", "token_type": "Bearer", "id_token": id_token })))
+    }
+
+    async fn start_mock_idp() -> MockIdp {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let idp = MockIdp {
+            issuer: format!("http://{}", listener.local_addr().unwrap()),
+            challenge: Arc::default(),
+        };
+        let app = Router::new()
+            .route("/.well-known/openid-configuration", get(discovery))
+            .route("/token", post(token))
+            .with_state(idp.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        idp
+    }
+
+    fn client_for(idp: &MockIdp, signing_algorithms: &[&str]) -> OidcClient {
+        OidcClient::new(OidcConfig {
+            issuer: idp.issuer.clone(),
+            client_id: CLIENT_ID.into(),
+            client_secret: Some(CLIENT_SECRET.into()),
+            redirect_uri: "http://localhost:8080/auth/oidc/callback".into(),
+            scopes: "openid email profile".into(),
+            groups_claim: "groups".into(),
+            admin_groups: vec!["library-admins".into()],
+            state_ttl: 600,
+            signing_algorithms: signing_algorithms.iter().map(|a| a.to_string()).collect(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_authorization_code_flow_with_mock_idp() -> anyhow::Result<()> {
+        let idp = start_mock_idp().await;
+        let client = client_for(&idp, &["HS256"]);
+        let pkce = Pkce::generate();
+
+        let url = reqwest::Url::parse(
+            &client
+                .authorization_url("mock-state", NONCE, &pkce.challenge)
+                .await?,
+        )?;
+        assert!(url.as_str().starts_with(&format!("{}/authorize", idp.issuer)));
+        let query: HashMap<_, _> = url.query_pairs().into_owned().collect();
+        assert_eq!(query["state"], "mock-state");
+        assert_eq!(query["code_challenge_method"], "S256");
+        *idp.challenge.lock().unwrap() = query["code_challenge"].clone();
+
+        let identity = client
+            .exchange_code("valid-code", &pkce.verifier, NONCE)
+            .await?;
+        assert_eq!(identity.subject, "idp-user-1");
+        assert_eq!(identity.email, "sso@example.com");
+        assert!(identity.email_verified);
+        assert_eq!(identity.groups, vec!["library-admins", "staff"]);
+
+        // nonce が一致しない ID トークンは受け付けない
+        let res = client
+            .exchange_code("valid-code", &pkce.verifier, "other-nonce")
+            .await;
+        assert!(matches!(res, Err(AppError::UnauthenticatedError)));
+
+        // 検証値が誤っている場合はプロバイダに拒否される
+        let res = client
+            .exchange_code("valid-code", "wrong-verifier", NONCE)
+            .await;
+        assert!(matches!(res, Err(AppError::UnauthenticatedError)));
+
+        // 設定にない方式で署名された ID トークンは、ヘッダーで指定されていても受け付けない
+        let client = client_for(&idp, &["RS256"]);
+        let res = client.exchange_code("valid-code", &pkce.verifier, NONCE).await;
+        assert!(matches!(res, Err(AppError::UnauthenticatedError)));
+
+        Ok(())
+    }
+}
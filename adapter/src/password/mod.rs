@@ -0,0 +1,127 @@
+pub mod policy;
+
+use argon2::{
+    password_hash::{
+        rand_core::OsRng, PasswordHash, PasswordHasher as _, PasswordVerifier as _, SaltString,
+    },
+    Algorithm, Argon2, Params, Version,
+};
+use shared::{
+    config::{PasswordHashAlgorithm, PasswordHashConfig},
+    error::{AppError, AppResult},
+};
+
+// 新しいパスワードは設定された方式でハッシュ化し、照合は保存済みのハッシュの形式に合わせて行う
+#[derive(Clone)]
+pub struct PasswordHasher {
+    config: PasswordHashConfig,
+}
+
+impl PasswordHasher {
+    pub fn new(config: PasswordHashConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn hash(&self, password: &str) -> AppResult<String> {
+        match self.config.algorithm {
+            PasswordHashAlgorithm::Argon2id => {
+                let salt = SaltString::generate(&mut OsRng);
+                self.argon2()?
+                    .hash_password(password.as_bytes(), &salt)
+                    .map(|hash| hash.to_string())
+                    .map_err(|e| AppError::PasswordHashError(e.to_string()))
+            }
+            PasswordHashAlgorithm::Bcrypt => {
+                bcrypt::hash(password, self.config.bcrypt_cost).map_err(AppError::from)
+            }
+        }
+    }
+
+    pub fn verify(&self, password: &str, hash: &str) -> AppResult<bool> {
+        if !hash.starts_with("$argon2") {
+            return bcrypt::verify(password, hash).map_err(AppError::from);
+        }
+        // パラメータは設定ではなくハッシュに含まれているものを使う
+        let parsed =
+            PasswordHash::new(hash).map_err(|e| AppError::PasswordHashError(e.to_string()))?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok())
+    }
+
+    // 保存済みのハッシュの方式やパラメータが現在の設定と異なる場合は true を返す
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        match self.config.algorithm {
+            PasswordHashAlgorithm::Argon2id => {
+                let Ok(parsed) = PasswordHash::new(hash) else {
+                    return true;
+                };
+                let Ok(params) = Params::try_from(&parsed) else {
+                    return true;
+                };
+                parsed.algorithm != argon2::ARGON2ID_IDENT
+                    || parsed.version != Some(Version::V0x13.into())
+                    || params.m_cost() != self.config.argon2_memory_kib
+                    || params.t_cost() != self.config.argon2_iterations
+                    || params.p_cost() != self.config.argon2_parallelism
+            }
+            PasswordHashAlgorithm::Bcrypt => hash
+                .parse::<bcrypt::HashParts>()
+                .map(|parts| parts.get_cost() != self.config.bcrypt_cost)
+                .unwrap_or(true),
+        }
+    }
+
+    fn argon2(&self) -> AppResult<Argon2<'static>> {
+        let params = Params::new(
+            self.config.argon2_memory_kib,
+            self.config.argon2_iterations,
+            self.config.argon2_parallelism,
+            None,
+        )
+        .map_err(|e| AppError::PasswordHashError(e.to_string()))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+#[cfg(test)]
+impl PasswordHasher {
+    // テスト用。Argon2id の既定のパラメータを使う
+    pub(crate) fn local() -> Self {
+        Self::new(PasswordHashConfig {
+            algorithm: PasswordHashAlgorithm::Argon2id,
+            argon2_memory_kib: Params::DEFAULT_M_COST,
+            argon2_iterations: Params::DEFAULT_T_COST,
+            argon2_parallelism: Params::DEFAULT_P_COST,
+            bcrypt_cost: bcrypt::DEFAULT_COST,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_and_rehash() -> anyhow::Result<()> {
+        let hasher = PasswordHasher::local();
+
+        let hash = hasher.hash("password")?;
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(hasher.verify("password", &hash)?);
+        assert!(!hasher.verify("wrong", &hash)?);
+        assert!(!hasher.needs_rehash(&hash));
+
+        // 以前の bcrypt のハッシュも照合でき、作り直しの対象になる
+        let legacy = bcrypt::hash("password", 4)?;
+        assert!(hasher.verify("password", &legacy)?);
+        assert!(hasher.needs_rehash(&legacy));
+
+        // パラメータを変えた場合も作り直す
+        let mut config = hasher.config.clone();
+        config.argon2_iterations += 1;
+        assert!(PasswordHasher::new(config).needs_rehash(&hash));
+
+        Ok(())
+    }
+}
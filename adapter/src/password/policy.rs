@@ -0,0 +1,253 @@
+use kernel::model::id::UserId;
+use shared::{
+    config::PasswordPolicyConfig,
+    error::{AppError, AppResult, PasswordRule, PasswordRuleViolation},
+};
+use sqlx::PgConnection;
+use std::{collections::HashSet, sync::LazyLock};
+
+use crate::password::PasswordHasher;
+
+// よく使われるパスワードの一覧。小文字で比較する
+static COMMON_PASSWORDS: LazyLock<HashSet<&'static str>> =
+    LazyLock::new(|| include_str!("common_passwords.txt").lines().collect());
+
+// 名前やメールアドレスの一部とみなす最小の文字数
+const MIN_PERSONAL_INFO_LENGTH: usize = 3;
+
+// パスワードを設定する利用者。新規作成時は user_id が None になる
+pub struct PasswordOwner<'a> {
+    pub user_id: Option<UserId>,
+    pub email: &'a str,
+    pub name: &'a str,
+}
+
+#[derive(Clone)]
+pub struct PasswordPolicy {
+    config: PasswordPolicyConfig,
+}
+
+impl PasswordPolicy {
+    pub fn new(config: PasswordPolicyConfig) -> Self {
+        Self { config }
+    }
+
+    // すべての規則を確認し、満たしていない規則をまとめてエラーとして返す
+    pub async fn validate(
+        &self,
+        conn: &mut PgConnection,
+        hasher: &PasswordHasher,
+        owner: PasswordOwner<'_>,
+        password: &str,
+    ) -> AppResult<()> {
+        let mut violations = self.check(password, owner.email, owner.name);
+        if let Some(user_id) = owner.user_id
+            && self.is_reused(conn, hasher, user_id, password).await?
+        {
+            violations.push(PasswordRuleViolation {
+                rule: PasswordRule::Reuse,
+                message: format!(
+                    "Password must differ from the last {} passwords",
+                    self.config.history_size
+                ),
+            });
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::PasswordPolicyViolation(violations))
+        }
+    }
+
+    // 設定したパスワードのハッシュを履歴に加え、保持数を超えた古いものを削除する
+    pub async fn remember(
+        &self,
+        conn: &mut PgConnection,
+        user_id: UserId,
+        password_hash: &str,
+    ) -> AppResult<()> {
+        if self.config.history_size <= 0 {
+            return Ok(());
+        }
+        sqlx::query!(
+            r#"
+                INSERT INTO password_histories(user_id, password_hash)
+                VALUES ($1, $2)
+            "#,
+            user_id as _,
+            password_hash
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM password_histories
+                WHERE user_id = $1
+                AND password_history_id NOT IN (
+                    SELECT password_history_id FROM password_histories
+                    WHERE user_id = $1
+                    ORDER BY created_at DESC
+                    LIMIT $2
+                )
+            "#,
+            user_id as _,
+            self.config.history_size
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+        Ok(())
+    }
+
+    // 履歴を使わない規則だけを確認する
+    pub fn check(&self, password: &str, email: &str, name: &str) -> Vec<PasswordRuleViolation> {
+        let mut violations = Vec::new();
+
+        if password.chars().count() < self.config.min_length {
+            violations.push(PasswordRuleViolation {
+                rule: PasswordRule::MinLength,
+                message: format!(
+                    "Password must be at least {} characters long",
+                    self.config.min_length
+                ),
+            });
+        }
+
+        if character_classes(password) < self.config.min_character_classes {
+            violations.push(PasswordRuleViolation {
+                rule: PasswordRule::CharacterClasses,
+                message: format!(
+                    "Password must contain at least {} of lowercase letters, uppercase letters, digits and symbols",
+                    self.config.min_character_classes
+                ),
+            });
+        }
+
+        if self.config.disallow_personal_info && contains_personal_info(password, email, name) {
+            violations.push(PasswordRuleViolation {
+                rule: PasswordRule::PersonalInfo,
+                message: "Password must not contain your name or email address".into(),
+            });
+        }
+
+        if self.config.reject_common && COMMON_PASSWORDS.contains(password.to_lowercase().as_str())
+        {
+            violations.push(PasswordRuleViolation {
+                rule: PasswordRule::CommonPassword,
+                message: "Password is too common".into(),
+            });
+        }
+
+        violations
+    }
+
+    // 現在のパスワードと履歴に残っている直近のパスワードのいずれかと一致すれば true を返す
+    async fn is_reused(
+        &self,
+        conn: &mut PgConnection,
+        hasher: &PasswordHasher,
+        user_id: UserId,
+        password: &str,
+    ) -> AppResult<bool> {
+        if self.config.history_size <= 0 {
+            return Ok(false);
+        }
+        let hashes = sqlx::query_scalar!(
+            r#"
+                SELECT password_hash AS "password_hash!" FROM users WHERE user_id = $1
+                UNION ALL
+                (
+                    SELECT password_hash FROM password_histories
+                    WHERE user_id = $1
+                    ORDER BY created_at DESC
+                    LIMIT $2
+                )
+            "#,
+            user_id as _,
+            self.config.history_size
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        for hash in hashes {
+            if hasher.verify(password, &hash)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+fn character_classes(password: &str) -> usize {
+    let has = |f: fn(&char) -> bool| password.chars().any(|c| f(&c));
+    [
+        has(|c| c.is_lowercase()),
+        has(|c| c.is_uppercase()),
+        has(|c| c.is_numeric()),
+        has(|c| !c.is_alphanumeric()),
+    ]
+    .into_iter()
+    .filter(|&x| x)
+    .count()
+}
+
+// メールアドレスのローカル部や名前の各語を、大文字小文字を区別せずに含むかどうか
+fn contains_personal_info(password: &str, email: &str, name: &str) -> bool {
+    let password = password.to_lowercase();
+    let local_part = email.split('@').next().unwrap_or_default();
+    std::iter::once(local_part)
+        .chain(name.split_whitespace())
+        .filter(|part| part.chars().count() >= MIN_PERSONAL_INFO_LENGTH)
+        .any(|part| password.contains(&part.to_lowercase()))
+}
+
+#[cfg(test)]
+impl PasswordPolicy {
+    // テスト用。規則を課さない
+    pub(crate) fn local() -> Self {
+        Self::new(PasswordPolicyConfig {
+            min_length: 1,
+            min_character_classes: 0,
+            disallow_personal_info: false,
+            history_size: 0,
+            reject_common: false,
+        })
+    }
+
+    pub(crate) fn strict() -> Self {
+        Self::new(PasswordPolicyConfig {
+            min_length: 10,
+            min_character_classes: 3,
+            disallow_personal_info: true,
+            history_size: 2,
+            reject_common: true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(violations: Vec<PasswordRuleViolation>) -> Vec<PasswordRule> {
+        violations.into_iter().map(|v| v.rule).collect()
+    }
+
+    #[test]
+    fn test_check_rules() {
+        let policy = PasswordPolicy::strict();
+        let check = |password| rules(policy.check(password, "alice@example.com", "Alice Smith"));
+
+        assert!(check("Correct-Horse-42").is_empty());
+        assert_eq!(
+            check("Password"),
+            vec![PasswordRule::MinLength, PasswordRule::CharacterClasses, PasswordRule::CommonPassword]
+        );
+        assert_eq!(check("Smith-Family-42"), vec![PasswordRule::PersonalInfo]);
+        assert_eq!(check("xALICEx-2024"), vec![PasswordRule::PersonalInfo]);
+        assert!(PasswordPolicy::local().check("a", "alice@example.com", "Alice").is_empty());
+    }
+}
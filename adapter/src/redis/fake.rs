@@ -0,0 +1,196 @@
+// テスト用に RESP を話す最小限のインメモリサーバー。RedisClient が使うコマンドだけを扱う
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+enum Value {
+    Str(Vec<u8>),
+    Set(BTreeSet<Vec<u8>>),
+}
+
+struct Entry {
+    value: Value,
+    expires_at: Option<Instant>,
+}
+
+type Store = Arc<Mutex<HashMap<Vec<u8>, Entry>>>;
+
+enum Reply {
+    Status(&'static str),
+    Int(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Vec<Reply>),
+    Error(String),
+}
+
+// サーバーを起動してポート番号を返す。状態は呼び出しごとに独立している
+pub(super) fn spawn() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let store = Store::default();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let store = store.clone();
+            std::thread::spawn(move || serve(stream, store));
+        }
+    });
+    port
+}
+
+fn serve(stream: TcpStream, store: Store) {
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    // MULTI の後は EXEC までコマンドを溜めておく
+    let mut queued: Option<Vec<Vec<Vec<u8>>>> = None;
+    while let Some(args) = read_command(&mut reader) {
+        let name = String::from_utf8_lossy(&args[0]).to_uppercase();
+        let reply = match (name.as_str(), queued.as_mut()) {
+            ("MULTI", _) => {
+                queued = Some(Vec::new());
+                Reply::Status("OK")
+            }
+            ("EXEC", Some(_)) => {
+                let commands = queued.take().unwrap();
+                let mut store = store.lock().unwrap();
+                Reply::Array(commands.iter().map(|c| execute(&mut store, c)).collect())
+            }
+            (_, Some(commands)) => {
+                commands.push(args);
+                Reply::Status("QUEUED")
+            }
+            (_, None) => execute(&mut store.lock().unwrap(), &args),
+        };
+        let mut buf = Vec::new();
+        encode(&reply, &mut buf);
+        if writer.write_all(&buf).is_err() {
+            break;
+        }
+    }
+}
+
+fn read_command(reader: &mut impl BufRead) -> Option<Vec<Vec<u8>>> {
+    let len = read_header(reader, b'*')?;
+    (0..len)
+        .map(|_| {
+            let len = read_header(reader, b'$')?;
+            let mut buf = vec![0; len + 2];
+            reader.read_exact(&mut buf).ok()?;
+            buf.truncate(len);
+            Some(buf)
+        })
+        .collect()
+}
+
+fn read_header(reader: &mut impl BufRead, prefix: u8) -> Option<usize> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).ok()? == 0 || line.as_bytes().first() != Some(&prefix) {
+        return None;
+    }
+    line[1..].trim_end().parse().ok()
+}
+
+fn encode(reply: &Reply, buf: &mut Vec<u8>) {
+    match reply {
+        Reply::Status(s) => buf.extend_from_slice(format!("+{s}\r\n").as_bytes()),
+        Reply::Int(n) => buf.extend_from_slice(format!(":{n}\r\n").as_bytes()),
+        Reply::Bulk(None) => buf.extend_from_slice(b"$-1\r\n"),
+        Reply::Bulk(Some(v)) => {
+            buf.extend_from_slice(format!("${}\r\n", v.len()).as_bytes());
+            buf.extend_from_slice(v);
+            buf.extend_from_slice(b"\r\n");
+        }
+        Reply::Array(items) => {
+            buf.extend_from_slice(format!("*{}\r\n", items.len()).as_bytes());
+            items.iter().for_each(|item| encode(item, buf));
+        }
+        Reply::Error(e) => buf.extend_from_slice(format!("-ERR {e}\r\n").as_bytes()),
+    }
+}
+
+fn execute(store: &mut HashMap<Vec<u8>, Entry>, args: &[Vec<u8>]) -> Reply {
+    let now = Instant::now();
+    store.retain(|_, entry| entry.expires_at.is_none_or(|at| at > now));
+    let seconds = |arg: &[u8]| -> i64 { String::from_utf8_lossy(arg).parse().unwrap_or(0) };
+    let expires_at = |secs: i64| now + Duration::from_secs(secs.max(0) as u64);
+    let name = String::from_utf8_lossy(&args[0]).to_uppercase();
+    match (name.as_str(), &args[1..]) {
+        ("PING", _) => Reply::Status("PONG"),
+        ("CLIENT", _) => Reply::Status("OK"),
+        ("SETEX", [key, ttl, value]) => {
+            store.insert(key.clone(), Entry {
+                value: Value::Str(value.clone()),
+                expires_at: Some(expires_at(seconds(ttl))),
+            });
+            Reply::Status("OK")
+        }
+        ("GET", [key]) => match store.get(key) {
+            Some(Entry { value: Value::Str(v), .. }) => Reply::Bulk(Some(v.clone())),
+            _ => Reply::Bulk(None),
+        },
+        ("GETDEL", [key]) => match store.remove(key) {
+            Some(Entry { value: Value::Str(v), .. }) => Reply::Bulk(Some(v)),
+            _ => Reply::Bulk(None),
+        },
+        ("INCR" | "INCRBY", [key, by @ ..]) => {
+            let by = by.first().map_or(1, |by| seconds(by));
+            let entry = store.entry(key.clone()).or_insert(Entry {
+                value: Value::Str(b"0".to_vec()),
+                expires_at: None,
+            });
+            let Value::Str(v) = &mut entry.value else {
+                return Reply::Error("wrong type".into());
+            };
+            let n = seconds(v) + by;
+            *v = n.to_string().into_bytes();
+            Reply::Int(n)
+        }
+        ("SADD", [key, members @ ..]) => {
+            let entry = store.entry(key.clone()).or_insert(Entry {
+                value: Value::Set(BTreeSet::new()),
+                expires_at: None,
+            });
+            let Value::Set(set) = &mut entry.value else {
+                return Reply::Error("wrong type".into());
+            };
+            Reply::Int(members.iter().filter(|m| set.insert(m.to_vec())).count() as i64)
+        }
+        ("SREM", [key, members @ ..]) => {
+            let Some(Entry { value: Value::Set(set), .. }) = store.get_mut(key) else {
+                return Reply::Int(0);
+            };
+            let removed = members.iter().filter(|m| set.remove(*m)).count();
+            if set.is_empty() {
+                store.remove(key);
+            }
+            Reply::Int(removed as i64)
+        }
+        ("SMEMBERS", [key]) => match store.get(key) {
+            Some(Entry { value: Value::Set(set), .. }) => Reply::Array(
+                set.iter().map(|m| Reply::Bulk(Some(m.clone()))).collect(),
+            ),
+            _ => Reply::Array(Vec::new()),
+        },
+        ("DEL", keys) => {
+            Reply::Int(keys.iter().filter(|k| store.remove(*k).is_some()).count() as i64)
+        }
+        ("EXPIRE", [key, ttl]) => {
+            let ttl = seconds(ttl);
+            match store.get_mut(key) {
+                Some(_) if ttl <= 0 => {
+                    store.remove(key);
+                    Reply::Int(1)
+                }
+                Some(entry) => {
+                    entry.expires_at = Some(expires_at(ttl));
+                    Reply::Int(1)
+                }
+                None => Reply::Int(0),
+            }
+        }
+        (name, _) => Reply::Error(format!("unsupported command '{name}'")),
+    }
+}
@@ -1,4 +1,6 @@
 pub mod model;
+#[cfg(any(test, feature = "test-util"))]
+mod fake;
 
 use redis::{AsyncCommands, Client};
 use shared::{config::RedisConfig, error::AppResult};
@@ -37,6 +39,52 @@ impl RedisClient {
         result.map(T::Value::try_from).transpose()
     }
 
+    // 取得と同時に削除する（一度しか使えないトークン向け）
+    pub async fn take<T: RedisKey> (
+        &self,
+        key: &T
+    ) -> AppResult<Option<T::Value>> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let result: Option<String> = conn.get_del(key.inner()).await?;
+        result.map(T::Value::try_from).transpose()
+    }
+
+    // セットに要素を追加し、セット自体の有効期限を延ばす
+    pub async fn add_to_set<T: RedisKey> (
+        &self,
+        key: &T,
+        member: &T::Value,
+        ttl: u64
+    ) -> AppResult<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let _: () = redis::pipe()
+            .atomic()
+            .sadd(key.inner(), member.inner())
+            .expire(key.inner(), ttl as i64)
+            .query_async(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove_from_set<T: RedisKey> (
+        &self,
+        key: &T,
+        member: &T::Value
+    ) -> AppResult<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let _: () = conn.srem(key.inner(), member.inner()).await?;
+        Ok(())
+    }
+
+    pub async fn members<T: RedisKey> (
+        &self,
+        key: &T
+    ) -> AppResult<Vec<T::Value>> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let result: Vec<String> = conn.smembers(key.inner()).await?;
+        result.into_iter().map(T::Value::try_from).collect()
+    }
+
     pub async fn delete<T: RedisKey> (
         &self,
         key: &T
@@ -46,8 +94,49 @@ impl RedisClient {
         Ok(())
     }
 
+    // カウンタを 1 増やして増加後の値を返す。有効期限は最後に増やした時点から数え直す
+    pub async fn increment<T: RedisKey> (
+        &self,
+        key: &T,
+        ttl: u64
+    ) -> AppResult<u64> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let (count, ()): (u64, ()) = redis::pipe()
+            .atomic()
+            .incr(key.inner(), 1)
+            .expire(key.inner(), ttl as i64)
+            .query_async(&mut conn)
+            .await?;
+        Ok(count)
+    }
+
+    // 値はそのままに有効期限だけを更新する
+    pub async fn expire<T: RedisKey> (
+        &self,
+        key: &T,
+        ttl: u64
+    ) -> AppResult<()> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let _: () = conn.expire(key.inner(), ttl as i64).await?;
+        Ok(())
+    }
+
     pub async fn try_connect(&self) -> AppResult<()> {
         let _ = self.client.get_multiplexed_async_connection().await?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl RedisClient {
+    // テスト用のクライアント。テストごとに空のインメモリサーバーを起動して接続する
+    pub fn local() -> std::sync::Arc<Self> {
+        std::sync::Arc::new(
+            Self::new(&RedisConfig {
+                host: "127.0.0.1".into(),
+                port: fake::spawn(),
+            })
+            .unwrap(),
+        )
+    }
+}
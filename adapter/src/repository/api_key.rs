@@ -0,0 +1,274 @@
+use async_trait::async_trait;
+use derive_new::new;
+use kernel::model::{
+    api_key::{
+        event::{CreateApiKey, RevokeApiKey},
+        ApiKey, AuthorizedApiKey, CreatedApiKey,
+    },
+    audit::{event::CreateAuditLog, AuditAction, AuditTarget},
+    id::UserId,
+};
+use kernel::repository::api_key::ApiKeyRepository;
+use sha2::{Digest, Sha256};
+use shared::error::{AppError, AppResult};
+use uuid::Uuid;
+
+use crate::database::{
+    model::api_key::{ApiKeyRow, AuthorizedApiKeyRow},
+    ConnectionPool,
+};
+use crate::repository::audit;
+
+// 発行したキーの先頭に付ける識別子。漏洩したキーを検出しやすくする
+const API_KEY_PREFIX: &str = "lib";
+
+#[derive(new)]
+pub struct ApiKeyRepositoryImpl {
+    db: ConnectionPool,
+}
+
+#[async_trait]
+impl ApiKeyRepository for ApiKeyRepositoryImpl {
+    async fn create(&self, event: CreateApiKey) -> AppResult<CreatedApiKey> {
+        let (prefix, key) = generate_api_key();
+        let scopes: Vec<String> = event.scopes.iter().map(|s| s.as_ref().to_string()).collect();
+
+        let mut tx = self.db.begin().await?;
+
+        let row = sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+                INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes, expires_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING
+                    api_key_id,
+                    name,
+                    prefix,
+                    scopes,
+                    expires_at,
+                    last_used_at,
+                    created_at
+            "#,
+            event.user_id as _,
+            event.name,
+            prefix,
+            hash_api_key(&key),
+            &scopes,
+            event.expires_at
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::ApiKey, row.api_key_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.user_id),
+                None,
+                AuditAction::Create,
+                AuditTarget::ApiKey,
+                row.api_key_id.raw(),
+                None,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(CreatedApiKey {
+            api_key: row.try_into()?,
+            key,
+        })
+    }
+
+    async fn find_by_user_id(&self, user_id: UserId) -> AppResult<Vec<ApiKey>> {
+        sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+                SELECT
+                    api_key_id,
+                    name,
+                    prefix,
+                    scopes,
+                    expires_at,
+                    last_used_at,
+                    created_at
+                FROM api_keys
+                WHERE user_id = $1 AND revoked_at IS NULL
+                ORDER BY created_at DESC
+            "#,
+            user_id as _
+        )
+        .fetch_all(self.db.inner_ref())
+        .await
+        .map_err(AppError::SpecificOperationError)?
+        .into_iter()
+        .map(ApiKey::try_from)
+        .collect()
+    }
+
+    async fn revoke(&self, event: RevokeApiKey) -> AppResult<()> {
+        let mut tx = self.db.begin().await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::ApiKey, event.api_key_id.raw()).await?;
+
+        // 他のユーザーのキーは存在しないものとして扱う
+        let res = sqlx::query!(
+            r#"
+                UPDATE api_keys SET revoked_at = CURRENT_TIMESTAMP(3)
+                WHERE api_key_id = $1 AND user_id = $2 AND revoked_at IS NULL
+            "#,
+            event.api_key_id as _,
+            event.user_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::EntityNotFound(
+                "Specified API key not found".into(),
+            ));
+        }
+
+        let after = audit::snapshot(&mut tx, AuditTarget::ApiKey, event.api_key_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.user_id),
+                event.impersonator,
+                AuditAction::Delete,
+                AuditTarget::ApiKey,
+                event.api_key_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn authenticate(&self, key: &str) -> AppResult<Option<AuthorizedApiKey>> {
+        sqlx::query_as!(
+            AuthorizedApiKeyRow,
+            r#"
+                UPDATE api_keys SET last_used_at = CURRENT_TIMESTAMP(3)
+                WHERE key_hash = $1
+                AND revoked_at IS NULL
+                AND (expires_at IS NULL OR expires_at > CURRENT_TIMESTAMP(3))
+                RETURNING api_key_id, user_id, scopes
+            "#,
+            hash_api_key(key)
+        )
+        .fetch_optional(self.db.inner_ref())
+        .await
+        .map_err(AppError::SpecificOperationError)?
+        .map(AuthorizedApiKey::try_from)
+        .transpose()
+    }
+}
+
+// "lib_<識別子>_<シークレット>" 形式のキーを生成し、識別子部分とキー全体を返す
+fn generate_api_key() -> (String, String) {
+    let id = Uuid::new_v4().simple().to_string();
+    let prefix = format!("{API_KEY_PREFIX}_{}", &id[..8]);
+    let key = format!("{prefix}_{}", Uuid::new_v4().simple());
+    (prefix, key)
+}
+
+// キーは十分な長さの乱数なので、検索できるよう SHA-256 でハッシュ化して保存する
+fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::tenant::default_tenant_id;
+    use crate::password::{policy::PasswordPolicy, PasswordHasher};
+    use crate::redis::RedisClient;
+    use crate::repository::user::UserRepositoryImpl;
+    use chrono::{Duration, Utc};
+    use kernel::model::{api_key::ApiKeyScope, user::event::CreateUser};
+    use kernel::repository::user::UserRepository;
+
+    #[sqlx::test]
+    async fn test_create_authenticate_and_revoke_api_key(
+        pool: sqlx::PgPool,
+    ) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let user = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        )
+            .create(tenant_id, CreateUser {
+                name: "Test User".into(),
+                email: "test@example.com".into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+
+        let repo = ApiKeyRepositoryImpl::new(ConnectionPool::new(pool));
+        let created = repo
+            .create(CreateApiKey::new(
+                user.user_id,
+                "script".into(),
+                vec![ApiKeyScope::Read],
+                None,
+            ))
+            .await?;
+        assert!(created.key.starts_with(&created.api_key.prefix));
+
+        let authorized = repo.authenticate(&created.key).await?.unwrap();
+        assert_eq!(authorized.user_id, user.user_id);
+        assert!(authorized.allows(ApiKeyScope::Read));
+        assert!(!authorized.allows(ApiKeyScope::Write));
+        assert!(repo.authenticate("lib_unknown_key").await?.is_none());
+
+        let keys = repo.find_by_user_id(user.user_id).await?;
+        assert_eq!(keys.len(), 1);
+        assert!(keys[0].last_used_at.is_some());
+
+        // 期限切れのキーでは認証できない
+        let expired = repo
+            .create(CreateApiKey::new(
+                user.user_id,
+                "expired".into(),
+                vec![ApiKeyScope::Read, ApiKeyScope::Write],
+                Some(Utc::now() - Duration::minutes(1)),
+            ))
+            .await?;
+        assert!(repo.authenticate(&expired.key).await?.is_none());
+
+        // 他のユーザーのキーは失効できない
+        let res = repo
+            .revoke(RevokeApiKey::new(created.api_key.api_key_id, UserId::new(), None))
+            .await;
+        assert!(matches!(res, Err(AppError::EntityNotFound(_))));
+
+        repo.revoke(RevokeApiKey::new(created.api_key.api_key_id, user.user_id, None))
+            .await?;
+        assert!(repo.authenticate(&created.key).await?.is_none());
+        assert_eq!(repo.find_by_user_id(user.user_id).await?.len(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,446 @@
+use async_trait::async_trait;
+use derive_new::new;
+use kernel::model::{
+    audit::{event::CreateAuditLog, AuditLog, AuditLogListOptions, AuditTarget},
+    id::{TenantId, UserId},
+    list::PaginatedList,
+};
+use kernel::repository::audit::AuditLogRepository;
+use serde_json::Value;
+use shared::error::{AppError, AppResult};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::database::{model::audit::AuditLogRow, ConnectionPool};
+
+#[derive(new)]
+pub struct AuditLogRepositoryImpl {
+    pool: ConnectionPool,
+}
+
+#[async_trait]
+impl AuditLogRepository for AuditLogRepositoryImpl {
+    async fn find_all(
+        &self,
+        tenant_id: TenantId,
+        options: AuditLogListOptions,
+    ) -> AppResult<PaginatedList<AuditLog>> {
+        let AuditLogListOptions {
+            actor_id,
+            action,
+            target_type,
+            target_id,
+            from,
+            to,
+            limit,
+            offset,
+        } = options;
+
+        let rows = sqlx::query_as!(
+            AuditLogRow,
+            r#"
+                SELECT
+                    COUNT(*) OVER() AS "total!",
+                    audit_log_id,
+                    actor_id AS "actor_id: UserId",
+                    impersonator_id AS "impersonator_id: UserId",
+                    action,
+                    target_type,
+                    target_id,
+                    before,
+                    after,
+                    created_at
+                FROM audit_logs
+                WHERE tenant_id = $1
+                AND ($2::uuid IS NULL OR actor_id = $2 OR impersonator_id = $2)
+                AND ($3::varchar IS NULL OR action = $3)
+                AND ($4::varchar IS NULL OR target_type = $4)
+                AND ($5::uuid IS NULL OR target_id = $5)
+                AND ($6::timestamptz IS NULL OR created_at >= $6)
+                AND ($7::timestamptz IS NULL OR created_at < $7)
+                ORDER BY created_at DESC
+                LIMIT $8 OFFSET $9
+            "#,
+            tenant_id as _,
+            actor_id as _,
+            action.as_ref().map(AsRef::<str>::as_ref),
+            target_type.as_ref().map(AsRef::<str>::as_ref),
+            target_id,
+            from,
+            to,
+            limit,
+            offset,
+        )
+        .fetch_all(self.pool.inner_ref())
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        let total = rows.first().map(|row| row.total).unwrap_or(0);
+        let items = rows
+            .into_iter()
+            .map(AuditLog::try_from)
+            .collect::<AppResult<Vec<_>>>()?;
+
+        Ok(PaginatedList {
+            total,
+            limit,
+            offset,
+            items,
+        })
+    }
+}
+
+/// 変更操作と同じトランザクションで監査ログを書き込む。
+/// テナントは操作者から、操作者がいない場合は対象の利用者から決める。
+/// 代理ログイン中の操作では、実際に操作した管理者も記録する
+pub(crate) async fn record(conn: &mut PgConnection, event: CreateAuditLog) -> AppResult<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO audit_logs (
+                tenant_id, actor_id, impersonator_id, action, target_type, target_id, before, after
+            )
+            VALUES (
+                (
+                    SELECT tenant_id FROM users
+                    WHERE user_id = COALESCE($1::uuid, CASE WHEN $3::varchar = 'User' THEN $4::uuid END)
+                ),
+                $1, $7, $2, $3, $4, $5, $6
+            )
+        "#,
+        event.actor_id as _,
+        event.action.as_ref(),
+        event.target_type.as_ref(),
+        event.target_id,
+        event.before,
+        event.after,
+        event.impersonator_id as _,
+    )
+    .execute(conn)
+    .await
+    .map_err(AppError::SpecificOperationError)?;
+
+    Ok(())
+}
+
+/// 監査ログに残すため、対象の行を JSON として取得する。
+/// パスワードハッシュは記録しない。
+pub(crate) async fn snapshot(
+    conn: &mut PgConnection,
+    target_type: AuditTarget,
+    target_id: Uuid,
+) -> AppResult<Option<Value>> {
+    let res = match target_type {
+        AuditTarget::Book => {
+            sqlx::query_scalar!(
+                r#"SELECT to_jsonb(b) AS "snapshot!" FROM books AS b WHERE b.book_id = $1"#,
+                target_id
+            )
+            .fetch_optional(conn)
+            .await
+        }
+        AuditTarget::BookTransfer => {
+            sqlx::query_scalar!(
+                r#"SELECT to_jsonb(t) AS "snapshot!" FROM book_transfers AS t WHERE t.transfer_id = $1"#,
+                target_id
+            )
+            .fetch_optional(conn)
+            .await
+        }
+        AuditTarget::User => {
+            sqlx::query_scalar!(
+                r#"SELECT to_jsonb(u) - 'password_hash' AS "snapshot!" FROM users AS u WHERE u.user_id = $1"#,
+                target_id
+            )
+            .fetch_optional(conn)
+            .await
+        }
+        AuditTarget::Checkout => {
+            sqlx::query_scalar!(
+                r#"
+                    SELECT COALESCE(
+                        (SELECT to_jsonb(c) FROM checkouts AS c WHERE c.checkout_id = $1),
+                        (SELECT to_jsonb(rc) FROM returned_checkouts AS rc WHERE rc.checkout_id = $1)
+                    ) AS "snapshot"
+                "#,
+                target_id
+            )
+            .fetch_one(conn)
+            .await
+        }
+        AuditTarget::ApiKey => {
+            sqlx::query_scalar!(
+                r#"SELECT to_jsonb(k) - 'key_hash' AS "snapshot!" FROM api_keys AS k WHERE k.api_key_id = $1"#,
+                target_id
+            )
+            .fetch_optional(conn)
+            .await
+        }
+        AuditTarget::TwoFactor => {
+            sqlx::query_scalar!(
+                r#"SELECT to_jsonb(t) - 'secret' AS "snapshot!" FROM user_totp AS t WHERE t.user_id = $1"#,
+                target_id
+            )
+            .fetch_optional(conn)
+            .await
+        }
+        // メンバーの追加・削除もグループの変更として記録する
+        AuditTarget::Group => {
+            sqlx::query_scalar!(
+                r#"
+                    SELECT to_jsonb(g) || jsonb_build_object(
+                        'members',
+                        COALESCE(
+                            (
+                                SELECT jsonb_agg(
+                                    jsonb_build_object('user_id', m.user_id, 'role', m.role)
+                                    ORDER BY m.joined_at
+                                )
+                                FROM group_members AS m WHERE m.group_id = g.group_id
+                            ),
+                            '[]'::jsonb
+                        )
+                    ) AS "snapshot!"
+                    FROM groups AS g WHERE g.group_id = $1
+                "#,
+                target_id
+            )
+            .fetch_optional(conn)
+            .await
+        }
+        AuditTarget::Tenant => {
+            sqlx::query_scalar!(
+                r#"SELECT to_jsonb(t) AS "snapshot!" FROM tenants AS t WHERE t.tenant_id = $1"#,
+                target_id
+            )
+            .fetch_optional(conn)
+            .await
+        }
+        AuditTarget::AccessToken => return Ok(None),
+    };
+
+    res.map_err(AppError::SpecificOperationError)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::tenant::default_tenant_id;
+    use crate::password::{policy::PasswordPolicy, PasswordHasher};
+    use crate::redis::RedisClient;
+    use crate::repository::{book::BookRepositoryImpl, user::UserRepositoryImpl};
+    use kernel::model::{
+        audit::AuditAction,
+        book::{
+            event::{CreateBook, UpdateBook},
+            BookListOptions,
+        },
+        user::event::CreateUser,
+    };
+    use kernel::repository::{book::BookRepository, user::UserRepository};
+
+    fn options() -> AuditLogListOptions {
+        AuditLogListOptions {
+            actor_id: None,
+            action: None,
+            target_type: None,
+            target_id: None,
+            from: None,
+            to: None,
+            limit: 10,
+            offset: 0,
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_record_book_changes(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let user = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        )
+            .create(tenant_id, CreateUser {
+                name: "Test User".into(),
+                email: "test@example.com".into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+
+        let book_repo = BookRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        book_repo
+            .create(
+                tenant_id,
+                CreateBook {
+                    title: "Test Title".into(),
+                    author: "Test Author".into(),
+                    isbn: "Test ISBN".into(),
+                    description: "Test Description".into(),
+                    group_id: None,
+                    impersonator: None,
+                },
+                user.user_id,
+            )
+            .await?;
+        let book_id = book_repo
+            .find_all(tenant_id, BookListOptions {
+                limit: 10,
+                offset: 0,
+            })
+            .await?
+            .items[0]
+            .book_id;
+        book_repo
+            .update(tenant_id, UpdateBook {
+                book_id,
+                title: "Test Title".into(),
+                author: "Test Author".into(),
+                isbn: "Test ISBN".into(),
+                description: "Updated Description".into(),
+                requested_user: user.user_id,
+                impersonator: None,
+                any_owner: false,
+                version: 1,
+            })
+            .await?;
+
+        let repo = AuditLogRepositoryImpl::new(ConnectionPool::new(pool));
+        let res = repo
+            .find_all(tenant_id, AuditLogListOptions {
+                target_type: Some(AuditTarget::Book),
+                ..options()
+            })
+            .await?;
+        assert_eq!(res.total, 2);
+
+        let res = repo
+            .find_all(tenant_id, AuditLogListOptions {
+                actor_id: Some(user.user_id),
+                action: Some(AuditAction::Update),
+                target_id: Some(book_id.raw()),
+                ..options()
+            })
+            .await?;
+        assert_eq!(res.total, 1);
+        let log = &res.items[0];
+        assert_eq!(log.before.as_ref().unwrap()["description"], "Test Description");
+        assert_eq!(log.after.as_ref().unwrap()["description"], "Updated Description");
+
+        let res = repo
+            .find_all(tenant_id, AuditLogListOptions {
+                target_type: Some(AuditTarget::User),
+                ..options()
+            })
+            .await?;
+        assert_eq!(res.total, 1);
+        assert!(res.items[0].after.as_ref().unwrap().get("password_hash").is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_record_impersonated_changes(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let user_repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let create = |email: &str| CreateUser {
+            name: "Test User".into(),
+            email: email.into(),
+            password: "test_password".into(),
+            requested_user: None,
+            impersonator: None,
+            email_verified: true,
+        };
+        let admin = user_repo.create(tenant_id, create("admin@example.com")).await?;
+        let user = user_repo.create(tenant_id, create("test@example.com")).await?;
+
+        let book_repo = BookRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        book_repo
+            .create(
+                tenant_id,
+                CreateBook {
+                    title: "Test Title".into(),
+                    author: "Test Author".into(),
+                    isbn: "Test ISBN".into(),
+                    description: "Test Description".into(),
+                    group_id: None,
+                    impersonator: None,
+                },
+                user.user_id,
+            )
+            .await?;
+        let book_id = book_repo
+            .find_all(tenant_id, BookListOptions {
+                limit: 10,
+                offset: 0,
+            })
+            .await?
+            .items[0]
+            .book_id;
+
+        // 管理者が利用者として蔵書を更新する
+        book_repo
+            .update(tenant_id, UpdateBook {
+                book_id,
+                title: "Test Title".into(),
+                author: "Test Author".into(),
+                isbn: "Test ISBN".into(),
+                description: "Updated Description".into(),
+                requested_user: user.user_id,
+                impersonator: Some(admin.user_id),
+                any_owner: false,
+                version: 1,
+            })
+            .await?;
+
+        let repo = AuditLogRepositoryImpl::new(ConnectionPool::new(pool));
+        let res = repo
+            .find_all(tenant_id, AuditLogListOptions {
+                target_type: Some(AuditTarget::Book),
+                ..options()
+            })
+            .await?;
+        assert_eq!(res.total, 2);
+        let update = res.items.iter().find(|log| log.action == AuditAction::Update).unwrap();
+        assert_eq!(update.actor_id, Some(user.user_id));
+        assert_eq!(update.impersonator_id, Some(admin.user_id));
+        let create = res.items.iter().find(|log| log.action == AuditAction::Create).unwrap();
+        assert_eq!(create.impersonator_id, None);
+
+        // 管理者で絞り込むと、代理ログイン中の操作も含まれる
+        let res = repo
+            .find_all(tenant_id, AuditLogListOptions {
+                actor_id: Some(admin.user_id),
+                target_type: Some(AuditTarget::Book),
+                ..options()
+            })
+            .await?;
+        assert_eq!(res.total, 1);
+        assert_eq!(res.items[0].action, AuditAction::Update);
+
+        Ok(())
+    }
+}
@@ -1,69 +1,1441 @@
 use crate::{
     database::{
-        model::auth::{from, AuthorizationKey, AuthorizedUserId, UserItem},
+        model::auth::{
+            from, from_verification, AuthorizationKey, AuthorizedUserId, EmailVerificationKey,
+            ImpersonationKey, ImpersonationTokenValue, ImpersonationValue, PasswordResetKey,
+            RefreshTokenKey, RefreshTokenValue, SessionIdValue, SessionKey, SessionValue,
+            UsedRefreshTokenKey, UserImpersonationsKey, UserItem, UserSessionsKey,
+        },
         ConnectionPool,
     },
+    jwt::JwtCodec,
     redis::RedisClient,
+    password::{
+        policy::{PasswordOwner, PasswordPolicy},
+        PasswordHasher,
+    },
+    repository::{audit, identity::provision_user},
 };
 use async_trait::async_trait;
-use derive_new::new;
 use kernel::{
+    directory::Directory,
     model::{
-        auth::{event::CreateToken, AccessToken},
-        id::UserId,
+        audit::{event::CreateAuditLog, AuditAction, AuditTarget},
+        auth::{
+            event::{
+                CreateEmailVerification, CreateImpersonationToken, CreatePasswordReset,
+                CreateToken, ResetPassword,
+            },
+            AccessToken, AuthTokens, ClientInfo, EmailVerificationToken, ImpersonationToken,
+            PasswordResetToken, RefreshToken, Session, TokenSubject,
+        },
+        id::{SessionId, TenantId, UserId},
     },
     repository::auth::AuthRepository,
 };
-use shared::error::{AppError, AppResult};
+use chrono::Utc;
+use serde_json::{json, Value};
+use shared::{
+    config::AuthConfig,
+    error::{AppError, AppResult},
+};
 use std::sync::Arc;
 
-#[derive(new)]
+// 最終利用日時を更新する間隔（秒）
+const SESSION_TOUCH_INTERVAL: i64 = 60;
+
 pub struct AuthRepositoryImpl {
     db: ConnectionPool,
     kv: Arc<RedisClient>,
-    ttl: u64,
+    config: AuthConfig,
+    jwt: Option<JwtCodec>,
+    verification_ttl: u64,
+    // 設定されている場合は、ローカルのパスワードより先にディレクトリで認証する
+    directory: Option<Arc<dyn Directory>>,
+    hasher: PasswordHasher,
+    policy: PasswordPolicy,
+}
+
+impl AuthRepositoryImpl {
+    pub fn new(
+        db: ConnectionPool,
+        kv: Arc<RedisClient>,
+        config: AuthConfig,
+        verification_ttl: u64,
+        directory: Option<Arc<dyn Directory>>,
+        hasher: PasswordHasher,
+        policy: PasswordPolicy,
+    ) -> Self {
+        let jwt = config.jwt.as_ref().map(JwtCodec::new);
+        Self {
+            db,
+            kv,
+            config,
+            jwt,
+            verification_ttl,
+            directory,
+            hasher,
+            policy,
+        }
+    }
 }
 
 #[async_trait]
 impl AuthRepository for AuthRepositoryImpl {
-    async fn fetch_user_id_from_token(
+    async fn fetch_token_subject(
         &self,
         access_token: &AccessToken,
-    ) -> AppResult<Option<UserId>> {
-        let key: AuthorizationKey = access_token.into();
-        self.kv
-            .get(&key)
-            .await
-            .map(|x| x.map(AuthorizedUserId::into_inner))
+    ) -> AppResult<Option<TokenSubject>> {
+        if ImpersonationKey::is_impersonation(access_token) {
+            let value = self.kv.get(&ImpersonationKey::from(access_token)).await?;
+            return Ok(value.map(|v| TokenSubject {
+                user_id: v.user_id,
+                impersonator: Some(v.impersonator),
+            }));
+        }
+
+        Ok(self
+            .fetch_user_id_from_token(access_token)
+            .await?
+            .map(|user_id| TokenSubject {
+                user_id,
+                impersonator: None,
+            }))
     }
 
-    async fn verify_user(&self, email: &str, password: &str) -> AppResult<UserId> {
+    async fn create_impersonation_token(
+        &self,
+        event: CreateImpersonationToken,
+    ) -> AppResult<ImpersonationToken> {
+        let ttl = self.config.impersonation_ttl;
+        let key = ImpersonationKey::from(&event);
+        let value = ImpersonationValue {
+            impersonator: event.impersonator,
+            user_id: event.user_id,
+        };
+        self.kv.set_ex(&key, &value, ttl).await?;
+        for user_id in [value.user_id, value.impersonator] {
+            self.kv
+                .add_to_set(
+                    &UserImpersonationsKey::from(user_id),
+                    &ImpersonationTokenValue::from(&key),
+                    ttl,
+                )
+                .await?;
+        }
+
+        let after = json!({
+            "user_id": value.user_id,
+            "impersonator": value.impersonator,
+            "ttl": ttl
+        });
+        self.record_impersonation_audit(&value, AuditAction::Create, None, Some(after))
+            .await?;
+
+        Ok(ImpersonationToken {
+            access_token: key.into(),
+            expires_in: ttl,
+        })
+    }
+
+    async fn verify_user(
+        &self,
+        tenant_id: TenantId,
+        email: &str,
+        password: &str,
+    ) -> AppResult<UserId> {
+        let provider = self.directory.as_ref().map(|d| d.provider());
+        if let Some(directory) = &self.directory {
+            match directory.authenticate(email, password).await {
+                Ok(Some(identity)) => {
+                    let mut tx = self.db.begin().await?;
+                    let user = provision_user(
+                        &mut tx,
+                        &self.hasher,
+                        tenant_id,
+                        directory.provider(),
+                        &identity,
+                        None,
+                    )
+                    .await?;
+                    tx.commit().await.map_err(AppError::TransactionError)?;
+                    return Ok(user.user_id);
+                }
+                Ok(None) => {}
+                // ディレクトリに接続できない場合も、ローカルのユーザーは認証できるようにする
+                Err(e) => tracing::warn!(error.message = %e, "Directory authentication failed"),
+            }
+        }
+
+        // ディレクトリに紐づいたユーザーは、ローカルのパスワードでは認証しない
         let user_item = sqlx::query_as!(
             UserItem,
             r#"
-                SELECT user_id, password_hash FROM users
-                WHERE email = $1;
+                SELECT
+                    user_id,
+                    password_hash,
+                    email_verified_at IS NOT NULL AS "email_verified!",
+                    active
+                FROM users
+                WHERE tenant_id = $3 AND email = $1 AND deleted_at IS NULL
+                AND NOT EXISTS (
+                    SELECT 1 FROM user_identities AS i
+                    WHERE i.user_id = users.user_id AND i.provider = $2
+                );
             "#,
-            email
+            email,
+            provider,
+            tenant_id as _
         )
-            .fetch_one(self.db.inner_ref())
+            .fetch_optional(self.db.inner_ref())
             .await
-            .map_err(AppError::SpecificOperationError)?;
-        let valid = bcrypt::verify(password, &user_item.password_hash)?;
-        if !valid {
+            .map_err(AppError::SpecificOperationError)?
+            // 登録されていないメールアドレスもパスワード誤りと同じ扱いにする
+            .ok_or(AppError::UnauthenticatedError)?;
+        if !self.hasher.verify(password, &user_item.password_hash)? {
             return Err(AppError::UnauthenticatedError);
         }
+        if self.hasher.needs_rehash(&user_item.password_hash) {
+            self.rehash_password(user_item.user_id, password, &user_item.password_hash)
+                .await;
+        }
+        if !user_item.active {
+            return Err(AppError::AccountDeactivated);
+        }
+        if !user_item.email_verified {
+            return Err(AppError::EmailNotVerified);
+        }
         Ok(user_item.user_id)
     }
 
-    async fn create_token(&self, event: CreateToken) -> AppResult<AccessToken> {
-        let (key, value) = from(event);
-        self.kv.set_ex(&key, &value, self.ttl).await?;
-        Ok(key.into())
+    async fn create_token(&self, event: CreateToken) -> AppResult<AuthTokens> {
+        let user_id = event.id;
+        let session_id = SessionId::new();
+
+        if self.jwt.is_some() {
+            let ttl = self.initial_ttl(self.refresh_ttl());
+            let session = SessionValue::new(user_id, None, event.client, ttl);
+            self.start_session(session_id, &session).await?;
+            return self
+                .issue_jwt_tokens(user_id, event.access_token, session_id, ttl)
+                .await;
+        }
+
+        let ttl = self.initial_ttl(self.config.ttl);
+        let (key, value) = from(&event, session_id);
+        let session = SessionValue::new(user_id, Some(event.access_token), event.client, ttl);
+        self.kv.set_ex(&key, &value, ttl).await?;
+        self.start_session(session_id, &session).await?;
+
+        // トークンそのものは監査ログに残さない
+        self.record_token_audit(
+            user_id,
+            AuditAction::Create,
+            None,
+            Some(json!({ "user_id": user_id, "session_id": session_id, "ttl": ttl })),
+        )
+        .await?;
+
+        Ok(AuthTokens {
+            user_id,
+            access_token: key.into(),
+            refresh_token: None,
+        })
+    }
+
+    async fn refresh_token(
+        &self,
+        refresh_token: RefreshToken,
+        client: ClientInfo,
+    ) -> AppResult<AuthTokens> {
+        if self.jwt.is_none() {
+            return Err(AppError::ForbiddenOperationError);
+        }
+
+        // 取得と同時に削除し、同じリフレッシュトークンを二度使えないようにする
+        let Some(RefreshTokenValue {
+            user_id,
+            session_id,
+        }) = self.kv.take(&RefreshTokenKey::from(&refresh_token)).await?
+        else {
+            // 使用済みのトークンが再度使われた場合は、漏洩とみなしてセッションごと無効にする
+            if let Some(SessionIdValue(session_id)) =
+                self.kv.get(&UsedRefreshTokenKey::from(&refresh_token)).await?
+                && let Some(user_id) = revoke_session(&self.kv, session_id).await?
+            {
+                tracing::warn!(%user_id, %session_id, "Refresh token reuse detected");
+                self.record_token_audit(
+                    user_id,
+                    AuditAction::Delete,
+                    Some(json!({
+                        "user_id": user_id,
+                        "session_id": session_id,
+                        "reason": "refresh_token_reuse"
+                    })),
+                    None,
+                )
+                .await?;
+            }
+            return Err(AppError::UnauthorizedError);
+        };
+
+        // セッションが失効していればリフレッシュさせない
+        let Some(session) = self
+            .touch_session(session_id, Some(client), Some(self.refresh_ttl()))
+            .await?
+        else {
+            return Err(AppError::UnauthorizedError);
+        };
+
+        self.kv
+            .set_ex(
+                &UsedRefreshTokenKey::from(&refresh_token),
+                &SessionIdValue(session_id),
+                self.refresh_ttl(),
+            )
+            .await?;
+
+        let jti = uuid::Uuid::new_v4().simple().to_string();
+        self.issue_jwt_tokens(user_id, jti, session_id, session.remaining_ttl())
+            .await
     }
 
     async fn delete_token(&self, access_token: AccessToken) -> AppResult<()> {
+        // 代理ログインを終える場合は、そのトークンだけを削除する
+        if ImpersonationKey::is_impersonation(&access_token) {
+            let key = ImpersonationKey::from(&access_token);
+            if let Some(value) = self.kv.take(&key).await? {
+                for user_id in [value.user_id, value.impersonator] {
+                    self.kv
+                        .remove_from_set(
+                            &UserImpersonationsKey::from(user_id),
+                            &ImpersonationTokenValue::from(&key),
+                        )
+                        .await?;
+                }
+                let before = json!({ "user_id": value.user_id, "impersonator": value.impersonator });
+                self.record_impersonation_audit(&value, AuditAction::Delete, Some(before), None)
+                    .await?;
+            }
+            return Ok(());
+        }
+
+        let session_id = match &self.jwt {
+            // JWT 自体は失効させられないため、同じログインのリフレッシュトークンを無効にする
+            Some(jwt) => jwt.decode(&access_token.0).map(|claims| claims.sid),
+            None => {
+                let key: AuthorizationKey = access_token.into();
+                let value = self.kv.take(&key).await?;
+                match value {
+                    Some(value) if value.session_id.is_none() => {
+                        self.record_token_audit(
+                            value.user_id,
+                            AuditAction::Delete,
+                            Some(json!({ "user_id": value.user_id })),
+                            None,
+                        )
+                        .await?;
+                        None
+                    }
+                    value => value.and_then(|v| v.session_id),
+                }
+            }
+        };
+
+        let Some(session_id) = session_id else {
+            return Ok(());
+        };
+        if let Some(user_id) = revoke_session(&self.kv, session_id).await? {
+            self.record_token_audit(
+                user_id,
+                AuditAction::Delete,
+                Some(json!({ "user_id": user_id, "session_id": session_id })),
+                None,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn create_email_verification(
+        &self,
+        event: CreateEmailVerification,
+    ) -> AppResult<EmailVerificationToken> {
+        let (key, value) = from_verification(event);
+        self.kv.set_ex(&key, &value, self.verification_ttl).await?;
+        Ok(key.into())
+    }
+
+    async fn verify_email(&self, token: &EmailVerificationToken) -> AppResult<UserId> {
+        let key: EmailVerificationKey = token.into();
+        let value = self.kv.get(&key).await?.ok_or_else(|| {
+            AppError::EntityNotFound("Verification token not found or expired".into())
+        })?;
+
+        let mut tx = self.db.begin().await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::User, value.user_id.raw()).await?;
+
+        // トークン発行後にメールアドレスが変更されていた場合は確認済みにしない
+        let res = sqlx::query!(
+            r#"
+                UPDATE users SET email_verified_at = CURRENT_TIMESTAMP(3)
+                WHERE user_id = $1
+                AND email = $2
+                AND deleted_at IS NULL
+            "#,
+            value.user_id as _,
+            value.email
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::EntityNotFound(
+                "Verification token not found or expired".into(),
+            ));
+        }
+
+        let after = audit::snapshot(&mut tx, AuditTarget::User, value.user_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(value.user_id),
+                None,
+                AuditAction::Update,
+                AuditTarget::User,
+                value.user_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        self.kv.delete(&key).await?;
+
+        Ok(value.user_id)
+    }
+
+    async fn create_password_reset(
+        &self,
+        tenant_id: TenantId,
+        event: CreatePasswordReset,
+    ) -> AppResult<Option<PasswordResetToken>> {
+        let user_id = sqlx::query_scalar!(
+            r#"
+                SELECT user_id AS "user_id: UserId" FROM users
+                WHERE tenant_id = $2 AND email = $1 AND deleted_at IS NULL;
+            "#,
+            event.email,
+            tenant_id as _
+        )
+        .fetch_optional(self.db.inner_ref())
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        let Some(user_id) = user_id else {
+            return Ok(None);
+        };
+
+        let token = PasswordResetToken(event.token);
+        self.kv
+            .set_ex(
+                &PasswordResetKey::from(&token),
+                &AuthorizedUserId::new(user_id),
+                self.config.password_reset_ttl,
+            )
+            .await?;
+
+        Ok(Some(token))
+    }
+
+    async fn reset_password(&self, event: ResetPassword) -> AppResult<()> {
+        let key = PasswordResetKey::from(&event.token);
+        let not_found =
+            || AppError::EntityNotFound("Password reset token not found or expired".into());
+        let user_id = self
+            .kv
+            .get(&key)
+            .await?
+            .map(AuthorizedUserId::into_inner)
+            .ok_or_else(not_found)?;
+
+        let mut tx = self.db.begin().await?;
+
+        let user = sqlx::query!(
+            r#"
+                SELECT name, email FROM users
+                WHERE user_id = $1 AND deleted_at IS NULL
+            "#,
+            user_id as _
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?
+        .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+
+        let owner = PasswordOwner {
+            user_id: Some(user_id),
+            email: &user.email,
+            name: &user.name,
+        };
+        self.policy
+            .validate(&mut tx, &self.hasher, owner, &event.new_password)
+            .await?;
+        let password_hash = self.hasher.hash(&event.new_password)?;
+
+        // ポリシーを満たさない場合はトークンを残し、同じトークンで再度設定できるようにする。
+        // 満たした場合は削除し、同じトークンを二度使えないようにする
+        if self.kv.take(&key).await?.is_none() {
+            return Err(not_found());
+        }
+
+        let before = audit::snapshot(&mut tx, AuditTarget::User, user_id.raw()).await?;
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE users SET password_hash = $1
+                WHERE user_id = $2 AND deleted_at IS NULL
+            "#,
+            password_hash,
+            user_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::EntityNotFound(
+                "Specified user does not exist".into(),
+            ));
+        }
+
+        self.policy.remember(&mut tx, user_id, &password_hash).await?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::User, user_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(user_id),
+                None,
+                AuditAction::Update,
+                AuditTarget::User,
+                user_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        // 再設定前に発行されたトークンはすべて無効にする
+        self.delete_all_tokens(user_id).await
+    }
+
+    async fn delete_all_tokens(&self, user_id: UserId) -> AppResult<()> {
+        let revoked = revoke_all_sessions(&self.kv, user_id).await?;
+        if revoked > 0 {
+            self.record_token_audit(
+                user_id,
+                AuditAction::Delete,
+                Some(json!({ "user_id": user_id, "revoked": revoked })),
+                None,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn find_sessions(&self, user_id: UserId) -> AppResult<Vec<Session>> {
+        let set_key = UserSessionsKey::from(user_id);
+        let mut sessions = Vec::new();
+        for member in self.kv.members(&set_key).await? {
+            match self.kv.get(&SessionKey::from(member.0)).await? {
+                Some(session) => sessions.push(session.into_session(member.0)),
+                // 有効期限切れのセッションはここで索引から取り除く
+                None => self.kv.remove_from_set(&set_key, &member).await?,
+            }
+        }
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.created_at));
+        Ok(sessions)
+    }
+
+    async fn delete_session(&self, user_id: UserId, session_id: SessionId) -> AppResult<()> {
+        // 他のユーザーのセッションは存在しないものとして扱う
+        let owned = self
+            .kv
+            .get(&SessionKey::from(session_id))
+            .await?
+            .is_some_and(|session| session.user_id == user_id);
+        if !owned || revoke_session(&self.kv, session_id).await?.is_none() {
+            return Err(AppError::EntityNotFound(
+                "Specified session not found".into(),
+            ));
+        }
+
+        self.record_token_audit(
+            user_id,
+            AuditAction::Delete,
+            Some(json!({ "user_id": user_id, "session_id": session_id })),
+            None,
+        )
+        .await
+    }
+}
+
+impl AuthRepositoryImpl {
+    fn refresh_ttl(&self) -> u64 {
+        self.config.jwt.as_ref().map_or(0, |jwt| jwt.refresh_ttl)
+    }
+
+    // スライディング方式の場合は、最初の有効期限も最大有効期間を超えないようにする
+    fn initial_ttl(&self, ttl: u64) -> u64 {
+        if self.config.sliding_expiration {
+            ttl.min(self.config.max_lifetime)
+        } else {
+            ttl
+        }
+    }
+
+    // ユーザーごとのセッション索引は、どのセッションよりも長く残るようにする
+    fn session_index_ttl(&self) -> u64 {
+        let ttl = self.config.ttl.max(self.refresh_ttl());
+        if self.config.sliding_expiration {
+            ttl.max(self.config.max_lifetime)
+        } else {
+            ttl
+        }
+    }
+
+    async fn start_session(&self, session_id: SessionId, session: &SessionValue) -> AppResult<()> {
+        self.kv
+            .set_ex(&SessionKey::from(session_id), session, session.remaining_ttl())
+            .await?;
+        self.kv
+            .add_to_set(
+                &UserSessionsKey::from(session.user_id),
+                &SessionIdValue(session_id),
+                self.session_index_ttl(),
+            )
+            .await
+    }
+
+    // 最終利用日時を更新し、extend が指定されていればその秒数だけ有効期限を延長する。
+    // セッションが存在しない場合は None を返す
+    async fn touch_session(
+        &self,
+        session_id: SessionId,
+        client: Option<ClientInfo>,
+        extend: Option<u64>,
+    ) -> AppResult<Option<SessionValue>> {
+        let key = SessionKey::from(session_id);
+        let Some(mut session) = self.kv.get(&key).await? else {
+            return Ok(None);
+        };
+
+        let now = Utc::now();
+        // リクエストのたびに書き込まないよう、一定間隔でのみ更新する
+        if client.is_none()
+            && now - session.last_used_at < chrono::Duration::seconds(SESSION_TOUCH_INTERVAL)
+        {
+            return Ok(Some(session));
+        }
+
+        session.last_used_at = now;
+        if let Some(client) = client {
+            session.user_agent = client.user_agent.or(session.user_agent);
+            session.ip_address = client.ip_address.or(session.ip_address);
+        }
+        if let Some(ttl) = extend {
+            let mut expires_at = now + chrono::Duration::seconds(ttl as i64);
+            if self.config.sliding_expiration {
+                // ログインからの最大有効期間を超えては延長しない
+                let limit = session.created_at
+                    + chrono::Duration::seconds(self.config.max_lifetime as i64);
+                expires_at = expires_at.min(limit);
+            }
+            session.expires_at = expires_at;
+            if let Some(access_token) = &session.access_token {
+                self.kv
+                    .expire(
+                        &AuthorizationKey::from(AccessToken(access_token.clone())),
+                        session.remaining_ttl(),
+                    )
+                    .await?;
+            }
+            self.kv
+                .add_to_set(
+                    &UserSessionsKey::from(session.user_id),
+                    &SessionIdValue(session_id),
+                    self.session_index_ttl(),
+                )
+                .await?;
+        }
+        self.kv
+            .set_ex(&key, &session, session.remaining_ttl())
+            .await?;
+        Ok(Some(session))
+    }
+
+    async fn issue_jwt_tokens(
+        &self,
+        user_id: UserId,
+        jti: String,
+        session_id: SessionId,
+        refresh_ttl: u64,
+    ) -> AppResult<AuthTokens> {
+        let Some(jwt) = &self.jwt else {
+            return Err(AppError::ForbiddenOperationError);
+        };
+
+        let access_token = jwt.encode(user_id, jti, session_id)?;
+
+        let refresh_key = RefreshTokenKey::generate();
+        self.kv
+            .set_ex(
+                &refresh_key,
+                &RefreshTokenValue {
+                    user_id,
+                    session_id,
+                },
+                refresh_ttl,
+            )
+            .await?;
+
+        self.record_token_audit(
+            user_id,
+            AuditAction::Create,
+            None,
+            Some(json!({
+                "user_id": user_id,
+                "session_id": session_id,
+                "ttl": self.config.jwt.as_ref().map(|c| c.ttl)
+            })),
+        )
+        .await?;
+
+        Ok(AuthTokens {
+            user_id,
+            access_token: AccessToken(access_token),
+            refresh_token: Some(refresh_key.into()),
+        })
+    }
+
+    // 古い方式のハッシュを現在の設定で作り直す。失敗してもログインは妨げない
+    async fn rehash_password(&self, user_id: UserId, password: &str, current_hash: &str) {
+        let res = async {
+            let new_hash = self.hasher.hash(password)?;
+            // 同時に変更されたパスワードを上書きしないよう、読み取ったハッシュのままの場合だけ更新する
+            sqlx::query!(
+                r#"
+                    UPDATE users SET password_hash = $1
+                    WHERE user_id = $2 AND password_hash = $3
+                "#,
+                new_hash,
+                user_id as _,
+                current_hash
+            )
+            .execute(self.db.inner_ref())
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+            AppResult::Ok(())
+        }
+        .await;
+        match res {
+            Ok(()) => tracing::info!(%user_id, "Rehashed password"),
+            Err(e) => tracing::warn!(%user_id, error.message = %e, "Failed to rehash password"),
+        }
+    }
+
+    // 通常のアクセストークンの持ち主を返す
+    async fn fetch_user_id_from_token(
+        &self,
+        access_token: &AccessToken,
+    ) -> AppResult<Option<UserId>> {
+        // JWT は署名と有効期限だけをローカルで検証する。セッションの失効はリフレッシュ時に確認する
+        if let Some(jwt) = &self.jwt {
+            return Ok(jwt.decode(&access_token.0).map(|claims| claims.sub));
+        }
+
         let key: AuthorizationKey = access_token.into();
-        self.kv.delete(&key).await
+        let Some(value) = self.kv.get(&key).await? else {
+            return Ok(None);
+        };
+        if let Some(session_id) = value.session_id {
+            let extend = self.config.sliding_expiration.then_some(self.config.ttl);
+            self.touch_session(session_id, None, extend).await?;
+        }
+        Ok(Some(value.user_id))
+    }
+
+    // 代理ログインのトークンは、実際に操作した管理者を操作者として記録する
+    async fn record_impersonation_audit(
+        &self,
+        value: &ImpersonationValue,
+        action: AuditAction,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> AppResult<()> {
+        let mut conn = self
+            .db
+            .inner_ref()
+            .acquire()
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+        audit::record(
+            &mut conn,
+            CreateAuditLog::new(
+                Some(value.impersonator),
+                None,
+                action,
+                AuditTarget::AccessToken,
+                value.user_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await
+    }
+
+    async fn record_token_audit(
+        &self,
+        user_id: UserId,
+        action: AuditAction,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> AppResult<()> {
+        let mut conn = self
+            .db
+            .inner_ref()
+            .acquire()
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+        audit::record(
+            &mut conn,
+            CreateAuditLog::new(
+                Some(user_id),
+                None,
+                action,
+                AuditTarget::AccessToken,
+                user_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await
+    }
+}
+
+// セッションと紐づくアクセストークンを削除し、セッションを持っていたユーザーを返す
+pub(crate) async fn revoke_session(
+    kv: &RedisClient,
+    session_id: SessionId,
+) -> AppResult<Option<UserId>> {
+    let Some(session) = kv.take(&SessionKey::from(session_id)).await? else {
+        return Ok(None);
+    };
+    if let Some(access_token) = session.access_token {
+        kv.delete(&AuthorizationKey::from(AccessToken(access_token)))
+            .await?;
+    }
+    kv.remove_from_set(
+        &UserSessionsKey::from(session.user_id),
+        &SessionIdValue(session_id),
+    )
+    .await?;
+    Ok(Some(session.user_id))
+}
+
+// ユーザーのすべてのセッションと、そのユーザーが関わる代理ログインのトークンを削除し、削除した件数を返す
+pub(crate) async fn revoke_all_sessions(kv: &RedisClient, user_id: UserId) -> AppResult<usize> {
+    let set_key = UserSessionsKey::from(user_id);
+    let mut revoked = 0;
+    for SessionIdValue(session_id) in kv.members(&set_key).await? {
+        if revoke_session(kv, session_id).await?.is_some() {
+            revoked += 1;
+        }
+    }
+    kv.delete(&set_key).await?;
+
+    let set_key = UserImpersonationsKey::from(user_id);
+    for token in kv.members(&set_key).await? {
+        if let Some(value) = kv.take(&ImpersonationKey::from(&token)).await? {
+            // もう一方の利用者のセットからも取り除く
+            let other = if value.user_id == user_id { value.impersonator } else { value.user_id };
+            kv.remove_from_set(&UserImpersonationsKey::from(other), &token)
+                .await?;
+            revoked += 1;
+        }
     }
-}
\ No newline at end of file
+    kv.delete(&set_key).await?;
+    Ok(revoked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::tenant::default_tenant_id;
+    use crate::repository::user::UserRepositoryImpl;
+    use kernel::{
+        model::{
+            identity::ExternalIdentity,
+            role::Role,
+            user::event::{CreateUser, DeleteUser, UpdateUserPassword, UpdateUserRole},
+        },
+        repository::user::UserRepository,
+    };
+    use shared::config::JwtConfig;
+
+    fn auth_config() -> AuthConfig {
+        AuthConfig {
+            ttl: 3600,
+            password_reset_ttl: 3600,
+            sliding_expiration: false,
+            max_lifetime: 604800,
+            impersonation_ttl: 900,
+            jwt: None,
+        }
+    }
+
+    async fn create_verified_user(
+        pool: &sqlx::PgPool,
+        tenant_id: TenantId,
+        email: &str,
+    ) -> anyhow::Result<UserId> {
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(pool)
+        .await?;
+        let user = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        )
+        .create(tenant_id, CreateUser {
+            name: "Test User".into(),
+            email: email.into(),
+            password: "test_password".into(),
+            requested_user: None,
+            impersonator: None,
+            email_verified: true,
+        })
+        .await?;
+        Ok(user.user_id)
+    }
+
+    fn auth_repository(
+        pool: &sqlx::PgPool,
+        kv: Arc<RedisClient>,
+        config: AuthConfig,
+    ) -> AuthRepositoryImpl {
+        AuthRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            kv,
+            config,
+            3600,
+            None,
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        )
+    }
+
+    fn client(user_agent: &str) -> ClientInfo {
+        ClientInfo {
+            user_agent: Some(user_agent.into()),
+            ip_address: None,
+        }
+    }
+
+    async fn is_valid(repo: &AuthRepositoryImpl, tokens: &AuthTokens) -> anyhow::Result<bool> {
+        Ok(repo.fetch_token_subject(&tokens.access_token).await?.is_some())
+    }
+
+    #[test]
+    fn test_impersonation_token_is_distinguishable() {
+        let event = CreateImpersonationToken::new(UserId::new(), UserId::new());
+        let token: AccessToken = ImpersonationKey::from(&event).into();
+        assert!(ImpersonationKey::is_impersonation(&token));
+
+        // 通常のアクセストークンは代理ログインとして扱わない
+        let token = AccessToken(CreateToken::new(UserId::new(), ClientInfo::default()).access_token);
+        assert!(!ImpersonationKey::is_impersonation(&token));
+    }
+
+    #[sqlx::test]
+    async fn test_verify_user_requires_verified_email(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let user_repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        user_repo
+            .create(tenant_id, CreateUser {
+                name: "Verified".into(),
+                email: "verified@example.com".into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+        user_repo
+            .create(tenant_id, CreateUser {
+                name: "Unverified".into(),
+                email: "unverified@example.com".into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: false,
+            })
+            .await?;
+
+        let repo = auth_repository(&pool, RedisClient::local(), auth_config());
+
+        assert!(repo.verify_user(tenant_id, "verified@example.com", "test_password").await.is_ok());
+
+        // bcrypt のハッシュはログインに成功した時点で Argon2id に作り直す
+        let legacy = bcrypt::hash("test_password", 4)?;
+        sqlx::query!(
+            "UPDATE users SET password_hash = $1 WHERE email = 'verified@example.com'",
+            legacy
+        )
+        .execute(repo.db.inner_ref())
+        .await?;
+        assert!(repo.verify_user(tenant_id, "verified@example.com", "test_password").await.is_ok());
+        let rehashed = sqlx::query_scalar!(
+            "SELECT password_hash FROM users WHERE email = 'verified@example.com'"
+        )
+        .fetch_one(repo.db.inner_ref())
+        .await?;
+        assert!(rehashed.starts_with("$argon2id$"));
+        assert!(repo.verify_user(tenant_id, "verified@example.com", "test_password").await.is_ok());
+        let res = repo.verify_user(tenant_id, "unverified@example.com", "test_password").await;
+        assert!(matches!(res, Err(AppError::EmailNotVerified)));
+        let res = repo.verify_user(tenant_id, "unverified@example.com", "wrong_password").await;
+        assert!(matches!(res, Err(AppError::UnauthenticatedError)));
+        let res = repo.verify_user(tenant_id, "unknown@example.com", "test_password").await;
+        assert!(matches!(res, Err(AppError::UnauthenticatedError)));
+
+        // 無効化された利用者は、パスワードが正しくてもログインできない
+        sqlx::query!("UPDATE users SET active = FALSE WHERE email = 'verified@example.com'")
+            .execute(repo.db.inner_ref())
+            .await?;
+        let res = repo.verify_user(tenant_id, "verified@example.com", "test_password").await;
+        assert!(matches!(res, Err(AppError::AccountDeactivated)));
+        let res = repo.verify_user(tenant_id, "verified@example.com", "wrong_password").await;
+        assert!(matches!(res, Err(AppError::UnauthenticatedError)));
+
+        Ok(())
+    }
+
+    // 固定の利用者だけを認証するディレクトリ
+    struct StubDirectory;
+
+    #[async_trait]
+    impl Directory for StubDirectory {
+        fn provider(&self) -> &str {
+            "ldap://stub"
+        }
+
+        async fn authenticate(
+            &self,
+            login: &str,
+            password: &str,
+        ) -> AppResult<Option<ExternalIdentity>> {
+            if login != "alice@example.com" || password != "directory_password" {
+                return Ok(None);
+            }
+            Ok(Some(ExternalIdentity {
+                subject: "uid=alice,ou=people,dc=example,dc=com".into(),
+                email: "alice@example.com".into(),
+                email_verified: true,
+                name: Some("Alice Directory".into()),
+                groups: Vec::new(),
+            }))
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_verify_user_with_directory(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let user_repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        for (name, email) in [("Alice", "alice@example.com"), ("Bob", "bob@example.com")] {
+            user_repo
+                .create(tenant_id, CreateUser {
+                    name: name.into(),
+                    email: email.into(),
+                    password: "local_password".into(),
+                    requested_user: None,
+                    impersonator: None,
+                    email_verified: true,
+                })
+                .await?;
+        }
+
+        let repo = AuthRepositoryImpl::new(
+            ConnectionPool::new(pool),
+            RedisClient::local(),
+            auth_config(),
+            3600,
+            Some(Arc::new(StubDirectory)),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+
+        // ディレクトリにいないユーザーはローカルのパスワードで認証する
+        assert!(repo.verify_user(tenant_id, "bob@example.com", "local_password").await.is_ok());
+
+        // ディレクトリで認証したユーザーは既存のユーザーに紐づけ、名前を同期する
+        let alice = repo
+            .verify_user(tenant_id, "alice@example.com", "directory_password")
+            .await?;
+        let user = user_repo.find_current_user(tenant_id, alice).await?.unwrap();
+        assert_eq!(user.name, "Alice Directory");
+
+        // 紐づけた後はローカルのパスワードでは認証できない
+        let res = repo.verify_user(tenant_id, "alice@example.com", "local_password").await;
+        assert!(matches!(res, Err(AppError::UnauthenticatedError)));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_password_reset(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        let user_id = create_verified_user(&pool, tenant_id, "reset@example.com").await?;
+        let repo = auth_repository(&pool, RedisClient::local(), auth_config());
+        let tokens = repo
+            .create_token(CreateToken::new(user_id, ClientInfo::default()))
+            .await?;
+
+        // 登録されていないメールアドレスでもエラーにはせず、トークンも作らない
+        let token = repo
+            .create_password_reset(tenant_id, CreatePasswordReset::new("unknown@example.com".into()))
+            .await?;
+        assert!(token.is_none());
+
+        let token = repo
+            .create_password_reset(tenant_id, CreatePasswordReset::new("reset@example.com".into()))
+            .await?
+            .unwrap();
+        repo.reset_password(ResetPassword {
+            token: PasswordResetToken(token.0.clone()),
+            new_password: "new_password".into(),
+        })
+        .await?;
+        assert_eq!(
+            repo.verify_user(tenant_id, "reset@example.com", "new_password").await?,
+            user_id
+        );
+
+        // 再設定前のセッションは使えなくなる
+        assert!(repo.fetch_token_subject(&tokens.access_token).await?.is_none());
+        assert!(repo.find_sessions(user_id).await?.is_empty());
+
+        // トークンは一度しか使えない
+        let res = repo
+            .reset_password(ResetPassword {
+                token,
+                new_password: "another_password".into(),
+            })
+            .await;
+        assert!(matches!(res, Err(AppError::EntityNotFound(_))));
+
+        // 有効期限を過ぎたトークンは使えない
+        let repo = auth_repository(&pool, RedisClient::local(), AuthConfig {
+            password_reset_ttl: 1,
+            ..auth_config()
+        });
+        let token = repo
+            .create_password_reset(tenant_id, CreatePasswordReset::new("reset@example.com".into()))
+            .await?
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let res = repo
+            .reset_password(ResetPassword {
+                token,
+                new_password: "another_password".into(),
+            })
+            .await;
+        assert!(matches!(res, Err(AppError::EntityNotFound(_))));
+        assert!(repo.verify_user(tenant_id, "reset@example.com", "new_password").await.is_ok());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_list_and_revoke_sessions(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        let user_id = create_verified_user(&pool, tenant_id, "user@example.com").await?;
+        let other_id = create_verified_user(&pool, tenant_id, "other@example.com").await?;
+        let repo = auth_repository(&pool, RedisClient::local(), auth_config());
+
+        let laptop = repo.create_token(CreateToken::new(user_id, client("laptop"))).await?;
+        let phone = repo.create_token(CreateToken::new(user_id, client("phone"))).await?;
+        let tablet = repo.create_token(CreateToken::new(user_id, client("tablet"))).await?;
+        let other = repo.create_token(CreateToken::new(other_id, client("laptop"))).await?;
+
+        let sessions = repo.find_sessions(user_id).await?;
+        let mut agents: Vec<_> = sessions
+            .iter()
+            .filter_map(|s| s.client.user_agent.clone())
+            .collect();
+        agents.sort();
+        assert_eq!(agents, ["laptop", "phone", "tablet"]);
+        let session_of = |agent: &str| {
+            sessions
+                .iter()
+                .find(|s| s.client.user_agent.as_deref() == Some(agent))
+                .unwrap()
+                .session_id
+        };
+
+        // 他のユーザーのセッションは削除できない
+        let res = repo.delete_session(other_id, session_of("laptop")).await;
+        assert!(matches!(res, Err(AppError::EntityNotFound(_))));
+        assert!(is_valid(&repo, &laptop).await?);
+
+        // 指定したセッションだけが失効する
+        repo.delete_session(user_id, session_of("laptop")).await?;
+        assert!(!is_valid(&repo, &laptop).await?);
+        assert!(is_valid(&repo, &phone).await?);
+        assert_eq!(repo.find_sessions(user_id).await?.len(), 2);
+        let res = repo.delete_session(user_id, session_of("laptop")).await;
+        assert!(matches!(res, Err(AppError::EntityNotFound(_))));
+
+        // すべての端末からのログアウト。管理者による強制ログアウトも同じ処理を使う
+        repo.delete_all_tokens(user_id).await?;
+        assert!(!is_valid(&repo, &phone).await?);
+        assert!(!is_valid(&repo, &tablet).await?);
+        assert!(repo.find_sessions(user_id).await?.is_empty());
+        assert!(is_valid(&repo, &other).await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_revoked_jwt_session_cannot_refresh(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        let user_id = create_verified_user(&pool, tenant_id, "user@example.com").await?;
+        let repo = auth_repository(&pool, RedisClient::local(), AuthConfig {
+            jwt: Some(JwtConfig {
+                secret: "test_secret".into(),
+                ttl: 900,
+                refresh_ttl: 3600,
+            }),
+            ..auth_config()
+        });
+
+        let laptop = repo.create_token(CreateToken::new(user_id, client("laptop"))).await?;
+        let phone = repo.create_token(CreateToken::new(user_id, client("phone"))).await?;
+        let laptop = repo
+            .refresh_token(laptop.refresh_token.unwrap(), client("laptop"))
+            .await?;
+        assert_eq!(repo.find_sessions(user_id).await?.len(), 2);
+
+        repo.delete_all_tokens(user_id).await?;
+
+        // アクセストークンは有効期限まで使えるが、リフレッシュはできない
+        assert!(is_valid(&repo, &laptop).await?);
+        for tokens in [laptop, phone] {
+            let res = repo.refresh_token(tokens.refresh_token.unwrap(), client("laptop")).await;
+            assert!(matches!(res, Err(AppError::UnauthorizedError)));
+        }
+        assert!(repo.find_sessions(user_id).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_user_changes_revoke_sessions(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        let admin_id = create_verified_user(&pool, tenant_id, "admin@example.com").await?;
+        let user_id = create_verified_user(&pool, tenant_id, "user@example.com").await?;
+        // 利用者の変更とトークンの検証で同じ Redis を使う
+        let kv = RedisClient::local();
+        let repo = auth_repository(&pool, kv.clone(), auth_config());
+        let user_repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            kv,
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let admin = repo.create_token(CreateToken::new(admin_id, client("laptop"))).await?;
+
+        // パスワードの変更
+        let tokens = repo.create_token(CreateToken::new(user_id, client("laptop"))).await?;
+        user_repo
+            .update_password(tenant_id, UpdateUserPassword {
+                user_id,
+                current_password: "test_password".into(),
+                new_password: "new_password".into(),
+            })
+            .await?;
+        assert!(!is_valid(&repo, &tokens).await?);
+
+        // ロールの変更
+        let tokens = repo.create_token(CreateToken::new(user_id, client("laptop"))).await?;
+        user_repo
+            .update_role(tenant_id, UpdateUserRole {
+                user_id,
+                role: Role::Admin,
+                requested_user: admin_id,
+                impersonator: None,
+                version: 1,
+            })
+            .await?;
+        assert!(!is_valid(&repo, &tokens).await?);
+
+        // 利用者の削除
+        let tokens = repo.create_token(CreateToken::new(user_id, client("laptop"))).await?;
+        user_repo
+            .delete(tenant_id, DeleteUser {
+                user_id,
+                requested_user: admin_id,
+                impersonator: None,
+            })
+            .await?;
+        assert!(!is_valid(&repo, &tokens).await?);
+        assert!(repo.find_sessions(user_id).await?.is_empty());
+
+        // 操作した管理者のセッションはそのまま
+        assert!(is_valid(&repo, &admin).await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_sliding_expiration_is_capped(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        let user_id = create_verified_user(&pool, tenant_id, "user@example.com").await?;
+        let repo = auth_repository(&pool, RedisClient::local(), AuthConfig {
+            ttl: 3600,
+            sliding_expiration: true,
+            max_lifetime: 7200,
+            ..auth_config()
+        });
+        let tokens = repo.create_token(CreateToken::new(user_id, client("laptop"))).await?;
+        let session_id = repo.find_sessions(user_id).await?[0].session_id;
+        let key = SessionKey::from(session_id);
+        let seconds = chrono::Duration::seconds;
+
+        // ログイン直後のセッションは使うたびに ttl 秒先まで延長する
+        let mut session = repo.kv.get(&key).await?.unwrap();
+        let now = Utc::now();
+        session.last_used_at = now - seconds(120);
+        session.expires_at = now + seconds(60);
+        repo.kv.set_ex(&key, &session, 60).await?;
+        assert!(is_valid(&repo, &tokens).await?);
+        let expires_at = repo.kv.get(&key).await?.unwrap().expires_at;
+        assert!(expires_at >= now + seconds(3600));
+
+        // ログインから max_lifetime 秒を超えては延長しない
+        let mut session = repo.kv.get(&key).await?.unwrap();
+        let now = Utc::now();
+        session.created_at = now - seconds(5400);
+        session.last_used_at = now - seconds(120);
+        repo.kv.set_ex(&key, &session, 60).await?;
+        assert!(is_valid(&repo, &tokens).await?);
+        let expires_at = repo.kv.get(&key).await?.unwrap().expires_at;
+        assert_eq!(expires_at, session.created_at + seconds(7200));
+
+        // 最初の有効期限も max_lifetime 秒を超えない
+        let repo = auth_repository(&pool, RedisClient::local(), AuthConfig {
+            ttl: 3600,
+            sliding_expiration: true,
+            max_lifetime: 1800,
+            ..auth_config()
+        });
+        repo.create_token(CreateToken::new(user_id, client("laptop"))).await?;
+        let session = &repo.find_sessions(user_id).await?[0];
+        assert!(session.expires_at <= session.created_at + seconds(1800));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_revoking_sessions_revokes_impersonation(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        let admin_id = create_verified_user(&pool, tenant_id, "admin@example.com").await?;
+        let user_id = create_verified_user(&pool, tenant_id, "user@example.com").await?;
+        let kv = RedisClient::local();
+        let repo = auth_repository(&pool, kv.clone(), auth_config());
+        let user_repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            kv,
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let impersonate = || async {
+            repo.create_impersonation_token(CreateImpersonationToken::new(admin_id, user_id))
+                .await
+                .map(|token| token.access_token)
+        };
+
+        let token = impersonate().await?;
+        let subject = repo.fetch_token_subject(&token).await?.unwrap();
+        assert_eq!(subject.user_id, user_id);
+        assert_eq!(subject.impersonator, Some(admin_id));
+
+        // 代理された利用者のセッションをすべて失効させる
+        repo.delete_all_tokens(user_id).await?;
+        assert!(repo.fetch_token_subject(&token).await?.is_none());
+
+        // 管理者のロールが変わった場合も失効する
+        let token = impersonate().await?;
+        user_repo
+            .update_role(tenant_id, UpdateUserRole {
+                user_id: admin_id,
+                role: Role::User,
+                requested_user: admin_id,
+                impersonator: None,
+                version: 1,
+            })
+            .await?;
+        assert!(repo.fetch_token_subject(&token).await?.is_none());
+
+        // 代理ログインを終えたトークンは、どちらのセットからも取り除く
+        let token = impersonate().await?;
+        repo.delete_token(AccessToken(token.0.clone())).await?;
+        assert!(repo.fetch_token_subject(&token).await?.is_none());
+        for user_id in [user_id, admin_id] {
+            let members = repo.kv.members(&UserImpersonationsKey::from(user_id)).await?;
+            assert!(members.is_empty());
+        }
+
+        Ok(())
+    }
+}
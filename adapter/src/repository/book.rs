@@ -1,10 +1,22 @@
-use crate::database::model::book::{BookCheckoutRow, BookRow, PaginatedBookRow};
+use crate::database::model::book::{
+    BookCheckoutRow, BookOwnershipHistoryRow, BookOwnershipStateRow, BookRow, BookTransferRow,
+    DeletedBookRow, PaginatedBookRow,
+};
 use crate::database::ConnectionPool;
+use crate::repository::audit;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use derive_new::new;
-use kernel::model::book::event::{DeleteBook, UpdateBook};
-use kernel::model::book::{event::CreateBook, Book, BookListOptions, Checkout};
-use kernel::model::id::{BookId, UserId};
+use kernel::model::audit::{event::CreateAuditLog, AuditAction, AuditTarget};
+use kernel::model::book::event::{
+    AcceptBookTransfer, CancelBookTransfer, ChangeBookGroup, DeleteBook, ForceBookTransfer, PatchBook,
+    RequestBookTransfer, RestoreBook, UpdateBook,
+};
+use kernel::model::book::{
+    event::CreateBook, Book, BookListOptions, BookOwnershipHistory, BookTransfer, Checkout,
+    DeletedBook,
+};
+use kernel::model::id::{BookId, BookTransferId, GroupId, TenantId, UserId};
 use kernel::model::list::PaginatedList;
 use kernel::repository::book::BookRepository;
 use shared::error::{AppError, AppResult};
@@ -15,28 +27,67 @@ pub struct BookRepositoryImpl {
     pool: ConnectionPool,
 }
 
+// 所有者の付け替え。譲渡の承認では受け取る利用者自身が transferred_by になる
+struct OwnershipChange {
+    transfer_to: UserId,
+    transferred_by: UserId,
+    impersonator: Option<UserId>,
+    forced: bool,
+    transferred_at: DateTime<Utc>,
+}
+
 #[async_trait]
 impl BookRepository for BookRepositoryImpl {
-    async fn create(&self, event: CreateBook, user_id: UserId) -> AppResult<()> {
-        sqlx::query!(
+    async fn create(&self, tenant_id: TenantId, event: CreateBook, user_id: UserId) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        if let Some(group_id) = event.group_id {
+            self.check_membership(&mut tx, tenant_id, group_id, user_id).await?;
+        }
+
+        let book_id = sqlx::query_scalar!(
             r#"
-                INSERT INTO books (title, author, isbn, description, user_id)
-                VALUES ($1, $2, $3, $4, $5)
+                INSERT INTO books (tenant_id, title, author, isbn, description, user_id, group_id)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                RETURNING book_id AS "book_id: BookId"
             "#,
+            tenant_id as _,
             event.title,
             event.author,
             event.isbn,
             event.description,
-            user_id as _
+            user_id as _,
+            event.group_id as _
         )
-        .execute(self.pool.inner_ref())
+        .fetch_one(&mut *tx)
         .await
         .map_err(AppError::SpecificOperationError)?;
 
+        let after = audit::snapshot(&mut tx, AuditTarget::Book, book_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(user_id),
+                event.impersonator,
+                AuditAction::Create,
+                AuditTarget::Book,
+                book_id.raw(),
+                None,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
         Ok(())
     }
 
-    async fn find_all(&self, options: BookListOptions) -> AppResult<PaginatedList<Book>> {
+    async fn find_all(
+        &self,
+        tenant_id: TenantId,
+        options: BookListOptions,
+    ) -> AppResult<PaginatedList<Book>> {
         let BookListOptions { limit, offset } = options;
 
         let rows: Vec<PaginatedBookRow> = sqlx::query_as!(
@@ -46,11 +97,14 @@ impl BookRepository for BookRepositoryImpl {
                     COUNT(*) OVER() AS "total!",
                     b.book_id AS book_id
                 FROM books AS b
+                WHERE b.tenant_id = $3
+                AND b.deleted_at IS NULL
                 ORDER BY b.created_at DESC
                 LIMIT $1 OFFSET $2
             "#,
             limit,
             offset,
+            tenant_id as _,
         )
         .fetch_all(self.pool.inner_ref())
         .await
@@ -73,9 +127,13 @@ impl BookRepository for BookRepositoryImpl {
                  b.isbn AS isbn,
                  b.description AS description,
                  u.user_id AS owned_by,
-                 u.name AS owner_name
+                 u.name AS owner_name,
+                 g.group_id AS "group_id?: GroupId",
+                 g.name AS "group_name?",
+                 b.version AS version
                 FROM books AS b
                 INNER JOIN users as u ON u.user_id = b.user_id
+                LEFT JOIN groups AS g ON g.group_id = b.group_id
                 WHERE b.book_id IN (SELECT * FROM UNNEST($1::uuid[]))
                 ORDER BY b.created_at DESC
             "#,
@@ -103,7 +161,7 @@ impl BookRepository for BookRepositoryImpl {
         })
     }
 
-    async fn find_by_id(&self, id: BookId) -> AppResult<Option<Book>> {
+    async fn find_by_id(&self, tenant_id: TenantId, id: BookId) -> AppResult<Option<Book>> {
         let row: Option<BookRow> = sqlx::query_as!(
             BookRow,
             r#"
@@ -114,12 +172,19 @@ impl BookRepository for BookRepositoryImpl {
                  b.isbn AS isbn,
                  b.description AS description,
                  u.user_id AS owned_by,
-                 u.name AS owner_name
+                 u.name AS owner_name,
+                 g.group_id AS "group_id?: GroupId",
+                 g.name AS "group_name?",
+                 b.version AS version
                 FROM books AS b
                 INNER JOIN users as u ON u.user_id = b.user_id
+                LEFT JOIN groups AS g ON g.group_id = b.group_id
                 WHERE b.book_id = $1
+                AND b.tenant_id = $2
+                AND b.deleted_at IS NULL
             "#,
-            id as _
+            id as _,
+            tenant_id as _
         )
         .fetch_optional(self.pool.inner_ref())
         .await
@@ -137,7 +202,14 @@ impl BookRepository for BookRepositoryImpl {
         }
     }
 
-    async fn update(&self, event: UpdateBook) -> AppResult<()> {
+    async fn update(&self, tenant_id: TenantId, event: UpdateBook) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        self.check_version(&mut tx, tenant_id, event.book_id, event.owner(), event.version)
+            .await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::Book, event.book_id.raw()).await?;
+
         let res = sqlx::query!(
             r#"
                 UPDATE books
@@ -147,16 +219,19 @@ impl BookRepository for BookRepositoryImpl {
                     isbn = $3,
                     description = $4
                 WHERE book_id = $5
-                AND user_id = $6
+                AND ($6::uuid IS NULL OR user_id = $6 OR group_id IN (
+                    SELECT group_id FROM group_members WHERE user_id = $6
+                ))
+                AND deleted_at IS NULL
             "#,
             event.title,
             event.author,
             event.isbn,
             event.description,
             event.book_id as _,
-            event.requested_user as _
+            event.owner() as _
         )
-        .execute(self.pool.inner_ref())
+        .execute(&mut *tx)
         .await
         .map_err(AppError::SpecificOperationError)?;
 
@@ -166,20 +241,97 @@ impl BookRepository for BookRepositoryImpl {
                 event.book_id
             )));
         }
+
+        let after = audit::snapshot(&mut tx, AuditTarget::Book, event.book_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::Book,
+                event.book_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn patch(&self, tenant_id: TenantId, event: PatchBook) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        self.check_version(&mut tx, tenant_id, event.book_id, event.owner(), event.version)
+            .await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::Book, event.book_id.raw()).await?;
+
+        sqlx::query!(
+            r#"
+                UPDATE books
+                SET
+                    title = COALESCE($1, title),
+                    author = COALESCE($2, author),
+                    isbn = COALESCE($3, isbn),
+                    description = COALESCE($4, description)
+                WHERE book_id = $5
+            "#,
+            event.title,
+            event.author,
+            event.isbn,
+            event.description,
+            event.book_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::Book, event.book_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::Book,
+                event.book_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
         Ok(())
     }
 
-    async fn delete(&self, event: DeleteBook) -> AppResult<()> {
+    async fn delete(&self, tenant_id: TenantId, event: DeleteBook) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        self.check_version(&mut tx, tenant_id, event.book_id, event.owner(), event.version)
+            .await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::Book, event.book_id.raw()).await?;
+
         let res = sqlx::query!(
             r#"
-                DELETE FROM books
+                UPDATE books
+                SET deleted_at = CURRENT_TIMESTAMP(3)
                 WHERE book_id = $1
-                AND user_id = $2
+                AND ($2::uuid IS NULL OR user_id = $2 OR group_id IN (
+                    SELECT group_id FROM group_members WHERE user_id = $2
+                ))
+                AND deleted_at IS NULL
             "#,
             event.book_id as _,
-            event.requested_user as _
+            event.owner() as _
         )
-        .execute(self.pool.inner_ref())
+        .execute(&mut *tx)
         .await
         .map_err(AppError::SpecificOperationError)?;
 
@@ -190,11 +342,725 @@ impl BookRepository for BookRepositoryImpl {
             )));
         }
 
+        // 貸出中であれば、削除した時点で返却済みとして履歴に残す
+        sqlx::query!(
+            r#"
+                WITH returned AS (
+                    DELETE FROM checkouts WHERE book_id = $1
+                    RETURNING checkout_id, book_id, user_id, checked_out_at, tenant_id
+                )
+                INSERT INTO returned_checkouts (
+                checkout_id, book_id, user_id, checked_out_at, returned_at, tenant_id)
+                SELECT checkout_id, book_id, user_id, checked_out_at, CURRENT_TIMESTAMP(3), tenant_id
+                FROM returned
+            "#,
+            event.book_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM book_transfers WHERE book_id = $1
+            "#,
+            event.book_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::Book, event.book_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Delete,
+                AuditTarget::Book,
+                event.book_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn change_group(&self, tenant_id: TenantId, event: ChangeBookGroup) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // 移す先のグループのメンバーでなければ、そのグループの蔵書にはできない
+        if let Some(group_id) = event.group_id {
+            if event.any_owner {
+                self.check_group(&mut tx, tenant_id, group_id).await?;
+            } else {
+                self.check_membership(&mut tx, tenant_id, group_id, event.requested_user)
+                    .await?;
+            }
+        }
+
+        let before = audit::snapshot(&mut tx, AuditTarget::Book, event.book_id.raw()).await?;
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE books
+                SET group_id = $1
+                WHERE book_id = $2
+                AND tenant_id = $4
+                AND ($3::uuid IS NULL OR user_id = $3 OR group_id IN (
+                    SELECT group_id FROM group_members WHERE user_id = $3
+                ))
+                AND deleted_at IS NULL
+            "#,
+            event.group_id as _,
+            event.book_id as _,
+            event.owner() as _,
+            tenant_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::EntityNotFound(format!(
+                "Book with id {} not found",
+                event.book_id
+            )));
+        }
+
+        let after = audit::snapshot(&mut tx, AuditTarget::Book, event.book_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::Book,
+                event.book_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn find_all_deleted(&self, tenant_id: TenantId) -> AppResult<Vec<DeletedBook>> {
+        sqlx::query_as!(
+            DeletedBookRow,
+            r#"
+                SELECT
+                 b.book_id AS book_id,
+                 b.title AS title,
+                 b.author AS author,
+                 b.isbn AS isbn,
+                 u.user_id AS owned_by,
+                 u.name AS owner_name,
+                 g.group_id AS "group_id?: GroupId",
+                 g.name AS "group_name?",
+                 b.deleted_at AS "deleted_at!"
+                FROM books AS b
+                INNER JOIN users as u ON u.user_id = b.user_id
+                LEFT JOIN groups AS g ON g.group_id = b.group_id
+                WHERE b.tenant_id = $1
+                AND b.deleted_at IS NOT NULL
+                ORDER BY b.deleted_at DESC
+            "#,
+            tenant_id as _
+        )
+        .fetch_all(self.pool.inner_ref())
+        .await
+        .map(|rows| rows.into_iter().map(DeletedBook::from).collect())
+        .map_err(AppError::SpecificOperationError)
+    }
+
+    async fn restore(&self, tenant_id: TenantId, event: RestoreBook) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::Book, event.book_id.raw()).await?;
+
+        // 所有者が削除されたままの蔵書は復元できない
+        let res = sqlx::query!(
+            r#"
+                UPDATE books AS b
+                SET deleted_at = NULL
+                FROM users AS u
+                WHERE b.book_id = $1
+                AND b.tenant_id = $2
+                AND b.deleted_at IS NOT NULL
+                AND u.user_id = b.user_id
+                AND u.deleted_at IS NULL
+            "#,
+            event.book_id as _,
+            tenant_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::EntityNotFound(format!(
+                "Deleted book with id {} not found or its owner has been deleted",
+                event.book_id
+            )));
+        }
+
+        let after = audit::snapshot(&mut tx, AuditTarget::Book, event.book_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::Book,
+                event.book_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
         Ok(())
     }
+
+    async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> AppResult<u64> {
+        let res = sqlx::query!(
+            r#"
+                WITH purged AS (
+                    DELETE FROM books AS b WHERE b.deleted_at < $1
+                    RETURNING b.book_id, b.tenant_id, to_jsonb(b) AS before
+                )
+                INSERT INTO audit_logs (tenant_id, actor_id, action, target_type, target_id, before, after)
+                SELECT tenant_id, NULL, 'Delete', 'Book', book_id, before, NULL
+                FROM purged
+            "#,
+            deleted_before
+        )
+        .execute(self.pool.inner_ref())
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        Ok(res.rows_affected())
+    }
+
+    async fn request_transfer(
+        &self,
+        tenant_id: TenantId,
+        event: RequestBookTransfer,
+    ) -> AppResult<BookTransferId> {
+        let mut tx = self.pool.begin().await?;
+
+        let state = self.find_ownership_state(&mut tx, tenant_id, event.book_id).await?;
+        if state.owned_by != event.requested_user {
+            return Err(AppError::ForbiddenOperationError);
+        }
+        if state.owned_by == event.transfer_to {
+            return Err(AppError::UnprocessableEntity(format!(
+                "Book with id {} is already owned by user with id {}",
+                event.book_id, event.transfer_to
+            )));
+        }
+
+        // 同じ蔵書に未承諾の譲渡依頼があれば、新しい依頼で置き換える
+        let transfer_id = BookTransferId::new();
+        let res = sqlx::query!(
+            r#"
+                INSERT INTO book_transfers (transfer_id, book_id, from_user_id, to_user_id, requested_at)
+                SELECT $1, $2, $3, user_id, $5 FROM users
+                WHERE user_id = $4 AND tenant_id = $6 AND deleted_at IS NULL
+                ON CONFLICT (book_id) DO UPDATE SET
+                    transfer_id = EXCLUDED.transfer_id,
+                    from_user_id = EXCLUDED.from_user_id,
+                    to_user_id = EXCLUDED.to_user_id,
+                    requested_at = EXCLUDED.requested_at
+            "#,
+            transfer_id as _,
+            event.book_id as _,
+            event.requested_user as _,
+            event.transfer_to as _,
+            event.requested_at,
+            tenant_id as _,
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::EntityNotFound(
+                "Specified user does not exist".into(),
+            ));
+        }
+
+        let after = audit::snapshot(&mut tx, AuditTarget::BookTransfer, transfer_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Create,
+                AuditTarget::BookTransfer,
+                transfer_id.raw(),
+                None,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(transfer_id)
+    }
+
+    async fn accept_transfer(&self, tenant_id: TenantId, event: AcceptBookTransfer) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let transfer = sqlx::query!(
+            r#"
+                SELECT
+                    t.from_user_id AS "from_user_id: UserId",
+                    t.to_user_id AS "to_user_id: UserId"
+                FROM book_transfers AS t
+                INNER JOIN books AS b USING(book_id)
+                WHERE t.transfer_id = $1
+                AND t.book_id = $2
+                AND b.tenant_id = $3
+            "#,
+            event.transfer_id as _,
+            event.book_id as _,
+            tenant_id as _
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?
+        .ok_or_else(|| {
+            AppError::EntityNotFound(format!(
+                "Transfer with id {} not found",
+                event.transfer_id
+            ))
+        })?;
+
+        if transfer.to_user_id != event.accepted_user {
+            return Err(AppError::ForbiddenOperationError);
+        }
+
+        let state = self.find_ownership_state(&mut tx, tenant_id, event.book_id).await?;
+        if state.owned_by != transfer.from_user_id {
+            return Err(AppError::UnprocessableEntity(format!(
+                "Book with id {} is no longer owned by user with id {}",
+                event.book_id, transfer.from_user_id
+            )));
+        }
+
+        self.transfer_ownership(&mut tx, state, OwnershipChange {
+            transfer_to: event.accepted_user,
+            transferred_by: event.accepted_user,
+            impersonator: event.impersonator,
+            forced: false,
+            transferred_at: event.accepted_at,
+        })
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn cancel_transfer(&self, tenant_id: TenantId, event: CancelBookTransfer) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let before =
+            audit::snapshot(&mut tx, AuditTarget::BookTransfer, event.transfer_id.raw()).await?;
+
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM book_transfers
+                WHERE transfer_id = $1
+                AND book_id = $2
+                AND (from_user_id = $3 OR to_user_id = $3)
+                AND book_id IN (SELECT book_id FROM books WHERE tenant_id = $4)
+            "#,
+            event.transfer_id as _,
+            event.book_id as _,
+            event.requested_user as _,
+            tenant_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::EntityNotFound(format!(
+                "Transfer with id {} not found",
+                event.transfer_id
+            )));
+        }
+
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Delete,
+                AuditTarget::BookTransfer,
+                event.transfer_id.raw(),
+                before,
+                None,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn force_transfer(&self, tenant_id: TenantId, event: ForceBookTransfer) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let user_exists = sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM users WHERE user_id = $1 AND tenant_id = $2 AND deleted_at IS NULL
+                ) AS "exists!"
+            "#,
+            event.transfer_to as _,
+            tenant_id as _
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if !user_exists {
+            return Err(AppError::EntityNotFound(
+                "Specified user does not exist".into(),
+            ));
+        }
+
+        let state = self.find_ownership_state(&mut tx, tenant_id, event.book_id).await?;
+        if state.owned_by == event.transfer_to {
+            return Err(AppError::UnprocessableEntity(format!(
+                "Book with id {} is already owned by user with id {}",
+                event.book_id, event.transfer_to
+            )));
+        }
+
+        self.transfer_ownership(&mut tx, state, OwnershipChange {
+            transfer_to: event.transfer_to,
+            transferred_by: event.requested_user,
+            impersonator: event.impersonator,
+            forced: true,
+            transferred_at: event.transferred_at,
+        })
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn find_transfers_by_user_id(
+        &self,
+        tenant_id: TenantId,
+        user_id: UserId,
+    ) -> AppResult<Vec<BookTransfer>> {
+        sqlx::query_as!(
+            BookTransferRow,
+            r#"
+                SELECT
+                    t.transfer_id,
+                    t.book_id,
+                    b.title,
+                    t.from_user_id,
+                    t.to_user_id,
+                    t.requested_at
+                FROM book_transfers AS t
+                INNER JOIN books AS b USING(book_id)
+                WHERE (t.from_user_id = $1 OR t.to_user_id = $1)
+                AND b.tenant_id = $2
+                ORDER BY t.requested_at DESC
+            "#,
+            user_id as _,
+            tenant_id as _
+        )
+        .fetch_all(self.pool.inner_ref())
+        .await
+        .map(|rows| rows.into_iter().map(BookTransfer::from).collect())
+        .map_err(AppError::SpecificOperationError)
+    }
+
+    async fn find_ownership_history(
+        &self,
+        tenant_id: TenantId,
+        book_id: BookId,
+    ) -> AppResult<Vec<BookOwnershipHistory>> {
+        sqlx::query_as!(
+            BookOwnershipHistoryRow,
+            r#"
+                SELECT
+                    book_id,
+                    from_user_id,
+                    to_user_id,
+                    transferred_by,
+                    forced,
+                    transferred_at
+                FROM book_ownership_histories
+                WHERE book_id = $1
+                AND book_id IN (SELECT book_id FROM books WHERE tenant_id = $2)
+                ORDER BY transferred_at DESC
+            "#,
+            book_id as _,
+            tenant_id as _
+        )
+        .fetch_all(self.pool.inner_ref())
+        .await
+        .map(|rows| rows.into_iter().map(BookOwnershipHistory::from).collect())
+        .map_err(AppError::SpecificOperationError)
+    }
 }
 
 impl BookRepositoryImpl {
+    // 更新対象の行をロックしたうえで、クライアントが参照したバージョンと一致するか確認する
+    async fn check_version(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tenant_id: TenantId,
+        book_id: BookId,
+        owner: Option<UserId>,
+        expected: i64,
+    ) -> AppResult<()> {
+        // owner が None の場合は所有者を問わない。グループの蔵書はメンバーであれば操作できる
+        let current = sqlx::query_scalar!(
+            r#"
+                SELECT version FROM books
+                WHERE book_id = $1
+                AND tenant_id = $3
+                AND ($2::uuid IS NULL OR user_id = $2 OR group_id IN (
+                    SELECT group_id FROM group_members WHERE user_id = $2
+                ))
+                AND deleted_at IS NULL
+                FOR UPDATE
+            "#,
+            book_id as _,
+            owner as _,
+            tenant_id as _
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?
+        .ok_or_else(|| AppError::EntityNotFound(format!("Book with id {} not found", book_id)))?;
+
+        if current != expected {
+            return Err(AppError::PreconditionFailed(format!(
+                "Book with id {} has been modified (current version: {}, expected: {})",
+                book_id, current, expected
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn check_membership(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tenant_id: TenantId,
+        group_id: GroupId,
+        user_id: UserId,
+    ) -> AppResult<()> {
+        let is_member = sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM group_members AS m
+                    INNER JOIN groups AS g USING(group_id)
+                    WHERE m.group_id = $1 AND m.user_id = $2 AND g.tenant_id = $3
+                ) AS "is_member!"
+            "#,
+            group_id as _,
+            user_id as _,
+            tenant_id as _
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if !is_member {
+            return Err(AppError::ForbiddenOperationError);
+        }
+        Ok(())
+    }
+
+    // メンバーでなくてもグループを指定できる場合に、同じテナントのグループであることを確認する
+    async fn check_group(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tenant_id: TenantId,
+        group_id: GroupId,
+    ) -> AppResult<()> {
+        let exists = sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM groups WHERE group_id = $1 AND tenant_id = $2
+                ) AS "exists!"
+            "#,
+            group_id as _,
+            tenant_id as _
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if !exists {
+            return Err(AppError::EntityNotFound(format!(
+                "Group with id {} not found",
+                group_id
+            )));
+        }
+        Ok(())
+    }
+
+    async fn find_ownership_state(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tenant_id: TenantId,
+        book_id: BookId,
+    ) -> AppResult<BookOwnershipStateRow> {
+        sqlx::query_as!(
+            BookOwnershipStateRow,
+            r#"
+                SELECT
+                    b.book_id,
+                    b.user_id AS owned_by,
+                    c.user_id AS "checked_out_by?: UserId"
+                FROM books AS b
+                LEFT JOIN checkouts AS c USING(book_id)
+                WHERE b.book_id = $1
+                AND b.tenant_id = $2
+                AND b.deleted_at IS NULL
+                FOR UPDATE OF b
+            "#,
+            book_id as _,
+            tenant_id as _
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?
+        .ok_or_else(|| AppError::EntityNotFound(format!("Book with id {} not found", book_id)))
+    }
+
+    // 所有者を付け替え、履歴を残す。
+    // 新しい所有者自身が貸出中だった場合は、その時点で返却済みとして扱う。
+    async fn transfer_ownership(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        state: BookOwnershipStateRow,
+        change: OwnershipChange,
+    ) -> AppResult<()> {
+        let OwnershipChange {
+            transfer_to,
+            transferred_by,
+            impersonator,
+            forced,
+            transferred_at,
+        } = change;
+        let before = audit::snapshot(tx, AuditTarget::Book, state.book_id.raw()).await?;
+
+        let res = sqlx::query!(
+            r#"
+                UPDATE books SET user_id = $1 WHERE book_id = $2
+            "#,
+            transfer_to as _,
+            state.book_id as _
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::NoRowsAffectedError(
+                "No book owner has been updated".into(),
+            ));
+        }
+
+        sqlx::query!(
+            r#"
+                INSERT INTO book_ownership_histories
+                    (book_id, from_user_id, to_user_id, transferred_by, forced, transferred_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            state.book_id as _,
+            state.owned_by as _,
+            transfer_to as _,
+            transferred_by as _,
+            forced,
+            transferred_at,
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if state.checked_out_by == Some(transfer_to) {
+            sqlx::query!(
+                r#"
+                    INSERT INTO returned_checkouts (
+                    checkout_id, book_id, user_id, checked_out_at, returned_at, tenant_id)
+                    SELECT checkout_id, book_id, user_id, checked_out_at, $2, tenant_id
+                    FROM checkouts
+                    WHERE book_id = $1;
+                "#,
+                state.book_id as _,
+                transferred_at,
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+            sqlx::query!(
+                r#"
+                    DELETE FROM checkouts WHERE book_id = $1;
+                "#,
+                state.book_id as _
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+        }
+
+        sqlx::query!(
+            r#"
+                DELETE FROM book_transfers WHERE book_id = $1
+            "#,
+            state.book_id as _
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        let after = audit::snapshot(tx, AuditTarget::Book, state.book_id.raw()).await?;
+        audit::record(
+            tx,
+            CreateAuditLog::new(
+                Some(transferred_by),
+                impersonator,
+                AuditAction::Update,
+                AuditTarget::Book,
+                state.book_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        Ok(())
+    }
+
     async fn find_checkouts(&self, book_ids: &[BookId]) -> AppResult<HashMap<BookId, Checkout>> {
         let res = sqlx::query_as!(
             BookCheckoutRow,
@@ -224,26 +1090,41 @@ impl BookRepositoryImpl {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repository::tenant::default_tenant_id;
+    use crate::password::{policy::PasswordPolicy, PasswordHasher};
+    use crate::redis::RedisClient;
+    use crate::repository::checkout::CheckoutRepositoryImpl;
     use crate::repository::user::UserRepositoryImpl;
+    use kernel::model::checkout::event::CreateCheckout;
+    use kernel::repository::checkout::CheckoutRepository;
     use kernel::{model::user::event::CreateUser, repository::user::UserRepository};
 
     #[sqlx::test]
     async fn test_register_book(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
         sqlx::query!(
             r#"
-            INSERT INTO roles(name) VALUES ('Admin'), ('User');
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
             "#
         )
         .execute(&pool)
         .await?;
 
-        let user_repo = UserRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        let user_repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
 
         let user = user_repo
-            .create(CreateUser {
+            .create(tenant_id, CreateUser {
                 name: "Test User".into(),
                 email: "test@example.com".into(),
                 password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
             })
             .await?;
 
@@ -254,19 +1135,21 @@ mod tests {
             author: "Test Author".into(),
             isbn: "Test ISBN".into(),
             description: "Test Description".into(),
+            group_id: None,
+            impersonator: None,
         };
 
-        repo.create(book, user.user_id).await?;
+        repo.create(tenant_id, book, user.user_id).await?;
 
         let options = BookListOptions {
             limit: 10,
             offset: 0,
         };
-        let res = repo.find_all(options).await?;
+        let res = repo.find_all(tenant_id, options).await?;
         assert_eq!(res.items.len(), 1);
 
         let book_id = res.items[0].book_id;
-        let res = repo.find_by_id(book_id).await?;
+        let res = repo.find_by_id(tenant_id, book_id).await?;
         assert!(res.is_some());
 
         let Book {
@@ -287,4 +1170,331 @@ mod tests {
 
         Ok(())
     }
+
+    #[sqlx::test]
+    async fn test_update_book_with_stale_version(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let user = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        )
+            .create(tenant_id, CreateUser {
+                name: "Test User".into(),
+                email: "test@example.com".into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+
+        let repo = BookRepositoryImpl::new(ConnectionPool::new(pool));
+        repo.create(
+            tenant_id,
+            CreateBook {
+                title: "Test Title".into(),
+                author: "Test Author".into(),
+                isbn: "Test ISBN".into(),
+                description: "Test Description".into(),
+                group_id: None,
+                impersonator: None,
+            },
+            user.user_id,
+        )
+        .await?;
+        let book = repo
+            .find_all(tenant_id, BookListOptions {
+                limit: 10,
+                offset: 0,
+            })
+            .await?
+            .items
+            .remove(0);
+        assert_eq!(book.version, 1);
+
+        let update = |description: &str| UpdateBook {
+            book_id: book.book_id,
+            title: "Test Title".into(),
+            author: "Test Author".into(),
+            isbn: "Test ISBN".into(),
+            description: description.into(),
+            requested_user: user.user_id,
+            impersonator: None,
+            any_owner: false,
+            version: book.version,
+        };
+
+        repo.update(tenant_id, update("First")).await?;
+
+        // 同じバージョンを元にした2回目の更新は失敗する
+        let res = repo.update(tenant_id, update("Second")).await;
+        assert!(matches!(res, Err(AppError::PreconditionFailed(_))));
+
+        let book = repo.find_by_id(tenant_id, book.book_id).await?.unwrap();
+        assert_eq!(book.description, "First");
+        assert_eq!(book.version, 2);
+
+        let res = repo
+            .delete(tenant_id, DeleteBook {
+                book_id: book.book_id,
+                requested_user: user.user_id,
+                impersonator: None,
+                any_owner: false,
+                version: 1,
+            })
+            .await;
+        assert!(matches!(res, Err(AppError::PreconditionFailed(_))));
+
+        repo.delete(tenant_id, DeleteBook {
+            book_id: book.book_id,
+            requested_user: user.user_id,
+            impersonator: None,
+            any_owner: false,
+            version: book.version,
+        })
+        .await?;
+        assert!(repo.find_by_id(tenant_id, book.book_id).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_patch_book(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let user_repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let create_user = |name: &str, email: &str| CreateUser {
+            name: name.into(),
+            email: email.into(),
+            password: "test_password".into(),
+            requested_user: None,
+            impersonator: None,
+            email_verified: true,
+        };
+        let user = user_repo
+            .create(tenant_id, create_user("Test User", "test@example.com"))
+            .await?;
+        let other = user_repo
+            .create(tenant_id, create_user("Other User", "other@example.com"))
+            .await?
+            .user_id;
+
+        let repo = BookRepositoryImpl::new(ConnectionPool::new(pool));
+        repo.create(
+            tenant_id,
+            CreateBook {
+                title: "Test Title".into(),
+                author: "Test Author".into(),
+                isbn: "Test ISBN".into(),
+                description: "Test Description".into(),
+                group_id: None,
+                impersonator: None,
+            },
+            user.user_id,
+        )
+        .await?;
+        let book_id = repo
+            .find_all(tenant_id, BookListOptions {
+                limit: 10,
+                offset: 0,
+            })
+            .await?
+            .items[0]
+            .book_id;
+
+        repo.patch(tenant_id, PatchBook {
+            book_id,
+            title: Some("Fixed Title".into()),
+            author: None,
+            isbn: None,
+            description: None,
+            requested_user: user.user_id,
+            impersonator: None,
+            any_owner: false,
+            version: 1,
+        })
+        .await?;
+
+        // 所有者以外は any_owner がなければ変更できない
+        let patch = |any_owner| PatchBook {
+            book_id,
+            title: None,
+            author: Some("Fixed Author".into()),
+            isbn: None,
+            description: None,
+            requested_user: other,
+            impersonator: None,
+            any_owner,
+            version: 2,
+        };
+        let res = repo.patch(tenant_id, patch(false)).await;
+        assert!(matches!(res, Err(AppError::EntityNotFound(_))));
+        repo.patch(tenant_id, patch(true)).await?;
+
+        let book = repo.find_by_id(tenant_id, book_id).await?.unwrap();
+        assert_eq!(book.title, "Fixed Title");
+        assert_eq!(book.author, "Fixed Author");
+        assert_eq!(book.isbn, "Test ISBN");
+        assert_eq!(book.description, "Test Description");
+        assert_eq!(book.version, 3);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_transfer_book(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let user_repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let owner = user_repo
+            .create(tenant_id, CreateUser {
+                name: "Owner".into(),
+                email: "owner@example.com".into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+        let recipient = user_repo
+            .create(tenant_id, CreateUser {
+                name: "Recipient".into(),
+                email: "recipient@example.com".into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+
+        let repo = BookRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        repo.create(
+            tenant_id,
+            CreateBook {
+                title: "Test Title".into(),
+                author: "Test Author".into(),
+                isbn: "Test ISBN".into(),
+                description: "Test Description".into(),
+                group_id: None,
+                impersonator: None,
+            },
+            owner.user_id,
+        )
+        .await?;
+        let book_id = repo
+            .find_all(tenant_id, BookListOptions {
+                limit: 10,
+                offset: 0,
+            })
+            .await?
+            .items[0]
+            .book_id;
+
+        // 受け取る側が貸出中の状態で譲渡する
+        let checkout_repo = CheckoutRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        checkout_repo
+            .create(tenant_id, CreateCheckout::new(book_id, recipient.user_id, None, Utc::now()))
+            .await?;
+
+        let res = repo
+            .request_transfer(tenant_id, RequestBookTransfer::new(
+                book_id,
+                recipient.user_id,
+                recipient.user_id,
+                None,
+                Utc::now(),
+            ))
+            .await;
+        assert!(matches!(res, Err(AppError::ForbiddenOperationError)));
+
+        let transfer_id = repo
+            .request_transfer(tenant_id, RequestBookTransfer::new(
+                book_id,
+                recipient.user_id,
+                owner.user_id,
+                None,
+                Utc::now(),
+            ))
+            .await?;
+        assert_eq!(repo.find_transfers_by_user_id(tenant_id, recipient.user_id).await?.len(), 1);
+
+        let res = repo
+            .accept_transfer(tenant_id, AcceptBookTransfer::new(
+                transfer_id,
+                book_id,
+                owner.user_id,
+                None,
+                Utc::now(),
+            ))
+            .await;
+        assert!(matches!(res, Err(AppError::ForbiddenOperationError)));
+
+        repo.accept_transfer(tenant_id, AcceptBookTransfer::new(
+            transfer_id,
+            book_id,
+            recipient.user_id,
+            None,
+            Utc::now(),
+        ))
+        .await?;
+
+        let book = repo.find_by_id(tenant_id, book_id).await?.unwrap();
+        assert_eq!(book.owner.user_id, recipient.user_id);
+        assert!(book.checkout.is_none());
+        assert!(repo.find_transfers_by_user_id(tenant_id, recipient.user_id).await?.is_empty());
+
+        let history = repo.find_ownership_history(tenant_id, book_id).await?;
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].from_user_id, owner.user_id);
+        assert_eq!(history[0].to_user_id, recipient.user_id);
+        assert!(!history[0].forced);
+
+        repo.force_transfer(tenant_id, ForceBookTransfer::new(
+            book_id,
+            owner.user_id,
+            owner.user_id,
+            None,
+            Utc::now(),
+        ))
+        .await?;
+        let book = repo.find_by_id(tenant_id, book_id).await?.unwrap();
+        assert_eq!(book.owner.user_id, owner.user_id);
+        assert_eq!(repo.find_ownership_history(tenant_id, book_id).await?.len(), 2);
+
+        Ok(())
+    }
 }
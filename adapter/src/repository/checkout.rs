@@ -2,11 +2,13 @@ use crate::database::{
     ConnectionPool,
     model::checkout::{CheckoutRow, CheckoutStateRow, ReturnedCheckoutRow},
 };
+use crate::repository::audit;
 use async_trait::async_trait;
 use derive_new::new;
+use kernel::model::audit::{event::CreateAuditLog, AuditAction, AuditTarget};
 use kernel::model::checkout::Checkout;
 use kernel::model::checkout::event::{CreateCheckout, UpdateReturned};
-use kernel::model::id::{BookId, CheckoutId, UserId};
+use kernel::model::id::{BookId, CheckoutId, TenantId, UserId};
 use kernel::repository::checkout::CheckoutRepository;
 use shared::error::{AppError, AppResult};
 
@@ -17,7 +19,7 @@ pub struct CheckoutRepositoryImpl {
 
 #[async_trait]
 impl CheckoutRepository for CheckoutRepositoryImpl {
-    async fn create(&self, event: CreateCheckout) -> AppResult<()> {
+    async fn create(&self, tenant_id: TenantId, event: CreateCheckout) -> AppResult<()> {
         let mut tx = self.pool.begin().await?;
 
         self.set_transaction_serializable(&mut tx).await?;
@@ -32,9 +34,12 @@ impl CheckoutRepository for CheckoutRepositoryImpl {
                 NULL AS "user_id?: UserId"
                 FROM books AS b
                 LEFT JOIN checkouts AS c USING(book_id)
-                WHERE b.book_id = $1;
+                WHERE b.book_id = $1
+                AND b.tenant_id = $2
+                AND b.deleted_at IS NULL;
                 "#,
-                event.book_id as _
+                event.book_id as _,
+                tenant_id as _
             )
                 .fetch_optional(&mut *tx)
                 .await
@@ -60,17 +65,62 @@ impl CheckoutRepository for CheckoutRepositoryImpl {
             }
         }
 
+        // メンバー限定で貸し出すグループの蔵書は、そのグループのメンバーしか借りられない
+        let lendable = sqlx::query_scalar!(
+            r#"
+            SELECT
+            NOT g.members_only_lending OR EXISTS (
+                SELECT 1 FROM group_members AS m
+                WHERE m.group_id = g.group_id AND m.user_id = $2
+            ) AS "lendable!"
+            FROM books AS b
+            INNER JOIN groups AS g USING(group_id)
+            WHERE b.book_id = $1;
+            "#,
+            event.book_id as _,
+            event.checked_out_by as _
+        )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+        if lendable == Some(false) {
+            return Err(AppError::ForbiddenOperationError);
+        }
+
+        // 無効化された利用者の個人所有の蔵書は、再び有効になるまで貸し出さない
+        let owner_active = sqlx::query_scalar!(
+            r#"
+            SELECT u.active OR b.group_id IS NOT NULL AS "owner_active!"
+            FROM books AS b
+            INNER JOIN users AS u USING(user_id)
+            WHERE b.book_id = $1;
+            "#,
+            event.book_id as _
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+        if !owner_active {
+            return Err(AppError::UnprocessableEntity(format!(
+                "Owner of book with id {} is deactivated",
+                event.book_id
+            )));
+        }
+
         let checkout_id = CheckoutId::new();
         let res = sqlx::query!(
             r#"
             INSERT  INTO checkouts (
-            checkout_id, book_id, user_id, checked_out_at
-            ) VALUES ($1, $2, $3, $4);
+            checkout_id, book_id, user_id, checked_out_at, tenant_id
+            ) VALUES ($1, $2, $3, $4, $5);
             "#,
             checkout_id as _,
             event.book_id as _,
             event.checked_out_by as _,
             event.checked_out_at,
+            tenant_id as _,
         )
             .execute(&mut *tx)
             .await
@@ -82,12 +132,27 @@ impl CheckoutRepository for CheckoutRepositoryImpl {
             ));
         }
 
+        let after = audit::snapshot(&mut tx, AuditTarget::Checkout, checkout_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.checked_out_by),
+                event.impersonator,
+                AuditAction::Create,
+                AuditTarget::Checkout,
+                checkout_id.raw(),
+                None,
+                after,
+            ),
+        )
+        .await?;
+
         tx.commit().await.map_err(AppError::TransactionError)?;
 
         Ok(())
     }
 
-    async fn update_returned(&self, event: UpdateReturned) -> AppResult<()> {
+    async fn update_returned(&self, tenant_id: TenantId, event: UpdateReturned) -> AppResult<()> {
         let mut tx = self.pool.begin().await?;
 
         self.set_transaction_serializable(&mut tx).await?;
@@ -102,9 +167,12 @@ impl CheckoutRepository for CheckoutRepositoryImpl {
                 c.user_id AS "user_id?: UserId"
                 FROM books AS b
                 LEFT JOIN checkouts AS c USING(book_id)
-                WHERE b.book_id = $1;
+                WHERE b.book_id = $1
+                AND b.tenant_id = $2
+                AND b.deleted_at IS NULL;
                 "#,
-                event.book_id as _
+                event.book_id as _,
+                tenant_id as _
             )
                 .fetch_optional(&mut *tx)
                 .await
@@ -121,7 +189,7 @@ impl CheckoutRepository for CheckoutRepositoryImpl {
                          checkout_id: Some(c),
                          user_id: Some(u),
                          ..
-                }) if (c, u) != (event.checkout_id, event.returned_by) => {
+                }) if c != event.checkout_id || (!event.force && u != event.returned_by) => {
                     return Err(AppError::UnprocessableEntity(format!(
                         "Checkout with id {} is not checked out by user with id {} for book with id {}",
                         event.checkout_id, event.returned_by, event.book_id
@@ -131,11 +199,13 @@ impl CheckoutRepository for CheckoutRepositoryImpl {
             }
         }
 
+        let before = audit::snapshot(&mut tx, AuditTarget::Checkout, event.checkout_id.raw()).await?;
+
         let res = sqlx::query!(
             r#"
             INSERT INTO returned_checkouts (
-            checkout_id, book_id, user_id, checked_out_at, returned_at)
-            SELECT checkout_id, book_id, user_id, checked_out_at, $2
+            checkout_id, book_id, user_id, checked_out_at, returned_at, tenant_id)
+            SELECT checkout_id, book_id, user_id, checked_out_at, $2, tenant_id
             FROM checkouts
             WHERE checkout_id = $1;
             "#,
@@ -169,12 +239,27 @@ impl CheckoutRepository for CheckoutRepositoryImpl {
             ));
         }
 
+        let after = audit::snapshot(&mut tx, AuditTarget::Checkout, event.checkout_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.returned_by),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::Checkout,
+                event.checkout_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
         tx.commit().await.map_err(AppError::TransactionError)?;
 
         Ok(())
     }
 
-    async fn find_unreturned_all(&self) -> AppResult<Vec<Checkout>> {
+    async fn find_unreturned_all(&self, tenant_id: TenantId) -> AppResult<Vec<Checkout>> {
         sqlx::query_as!(
             CheckoutRow,
             r#"
@@ -188,8 +273,11 @@ impl CheckoutRepository for CheckoutRepositoryImpl {
                 b.isbn
             FROM checkouts AS c
             INNER JOIN books AS b USING(book_id)
+            WHERE c.tenant_id = $1
+            AND b.deleted_at IS NULL
             ORDER BY c.checked_out_at;
-        "#
+        "#,
+            tenant_id as _
         )
             .fetch_all(self.pool.inner_ref())
             .await
@@ -197,7 +285,11 @@ impl CheckoutRepository for CheckoutRepositoryImpl {
             .map_err(AppError::SpecificOperationError)
     }
 
-    async fn find_unreturned_by_user_id(&self, user_id: UserId) -> AppResult<Vec<Checkout>> {
+    async fn find_unreturned_by_user_id(
+        &self,
+        tenant_id: TenantId,
+        user_id: UserId,
+    ) -> AppResult<Vec<Checkout>> {
 
         sqlx::query_as!(
             CheckoutRow,
@@ -213,9 +305,12 @@ impl CheckoutRepository for CheckoutRepositoryImpl {
             FROM checkouts AS c
             INNER JOIN books AS b USING(book_id)
             WHERE c.user_id = $1
+            AND c.tenant_id = $2
+            AND b.deleted_at IS NULL
             ORDER BY c.checked_out_at;
             "#,
-            user_id as _
+            user_id as _,
+            tenant_id as _
         )
             .fetch_all(self.pool.inner_ref())
             .await
@@ -223,9 +318,13 @@ impl CheckoutRepository for CheckoutRepositoryImpl {
             .map_err(AppError::SpecificOperationError)
     }
 
-    async fn find_history_by_book_id(&self, book_id: BookId) -> AppResult<Vec<Checkout>> {
+    async fn find_history_by_book_id(
+        &self,
+        tenant_id: TenantId,
+        book_id: BookId,
+    ) -> AppResult<Vec<Checkout>> {
 
-        let checkout: Option<Checkout> = self.find_unreturned_by_book_id(book_id).await?;
+        let checkout: Option<Checkout> = self.find_unreturned_by_book_id(tenant_id, book_id).await?;
 
         let mut checkout_histories: Vec<Checkout> = sqlx::query_as!(
             ReturnedCheckoutRow,
@@ -242,9 +341,12 @@ impl CheckoutRepository for CheckoutRepositoryImpl {
             FROM returned_checkouts AS rc
             INNER JOIN books AS b USING(book_id)
             WHERE rc.book_id = $1
+            AND rc.tenant_id = $2
+            AND b.deleted_at IS NULL
             ORDER BY rc.checked_out_at DESC;
             "#,
-            book_id as _
+            book_id as _,
+            tenant_id as _
         )
         .fetch_all(self.pool.inner_ref())
             .await
@@ -274,7 +376,11 @@ impl CheckoutRepositoryImpl {
         Ok(())
     }
 
-    async fn find_unreturned_by_book_id(&self, book_id: BookId) -> AppResult<Option<Checkout>> {
+    async fn find_unreturned_by_book_id(
+        &self,
+        tenant_id: TenantId,
+        book_id: BookId,
+    ) -> AppResult<Option<Checkout>> {
         let res = sqlx::query_as!(
             CheckoutRow,
             r#"
@@ -288,9 +394,12 @@ impl CheckoutRepositoryImpl {
                 b.isbn
             FROM checkouts AS c
             INNER JOIN books AS b USING(book_id)
-            WHERE c.book_id = $1;
+            WHERE c.book_id = $1
+            AND c.tenant_id = $2
+            AND b.deleted_at IS NULL;
             "#,
             book_id as _,
+            tenant_id as _,
         )
             .fetch_optional(self.pool.inner_ref())
             .await
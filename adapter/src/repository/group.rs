@@ -0,0 +1,609 @@
+use async_trait::async_trait;
+use derive_new::new;
+use kernel::model::{
+    audit::{event::CreateAuditLog, AuditAction, AuditTarget},
+    group::{
+        event::{CreateGroup, DeleteGroup, RemoveGroupMember, UpdateGroup, UpsertGroupMember},
+        Group, GroupMember, GroupRole,
+    },
+    id::{GroupId, TenantId, UserId},
+};
+use kernel::repository::group::GroupRepository;
+use shared::error::{AppError, AppResult};
+use std::collections::HashMap;
+
+use crate::database::{
+    model::group::{GroupMemberRow, GroupRow},
+    ConnectionPool,
+};
+use crate::repository::audit;
+
+#[derive(new)]
+pub struct GroupRepositoryImpl {
+    pool: ConnectionPool,
+}
+
+#[async_trait]
+impl GroupRepository for GroupRepositoryImpl {
+    async fn create(&self, tenant_id: TenantId, event: CreateGroup) -> AppResult<GroupId> {
+        let mut tx = self.pool.begin().await?;
+
+        let group_id = sqlx::query_scalar!(
+            r#"
+                INSERT INTO groups (tenant_id, name, members_only_lending)
+                VALUES ($1, $2, $3)
+                RETURNING group_id AS "group_id: GroupId"
+            "#,
+            tenant_id as _,
+            event.name,
+            event.members_only_lending
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| name_conflict(e, &event.name))?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO group_members (group_id, user_id, role)
+                VALUES ($1, $2, $3)
+            "#,
+            group_id as _,
+            event.requested_user as _,
+            GroupRole::Owner.as_ref()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::Group, group_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Create,
+                AuditTarget::Group,
+                group_id.raw(),
+                None,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(group_id)
+    }
+
+    async fn find_by_id(&self, tenant_id: TenantId, group_id: GroupId) -> AppResult<Option<Group>> {
+        let row = sqlx::query_as!(
+            GroupRow,
+            r#"
+                SELECT
+                    group_id AS "group_id: GroupId",
+                    name,
+                    members_only_lending,
+                    created_at
+                FROM groups
+                WHERE group_id = $1
+                AND tenant_id = $2
+            "#,
+            group_id as _,
+            tenant_id as _
+        )
+        .fetch_optional(self.pool.inner_ref())
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        match row {
+            Some(row) => {
+                let members = self
+                    .find_members(&[row.group_id])
+                    .await?
+                    .remove(&row.group_id)
+                    .unwrap_or_default();
+                Ok(Some(row.into_group(members)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn find_by_user_id(&self, tenant_id: TenantId, user_id: UserId) -> AppResult<Vec<Group>> {
+        let rows = sqlx::query_as!(
+            GroupRow,
+            r#"
+                SELECT
+                    g.group_id AS "group_id: GroupId",
+                    g.name,
+                    g.members_only_lending,
+                    g.created_at
+                FROM groups AS g
+                INNER JOIN group_members AS m USING (group_id)
+                WHERE m.user_id = $1
+                AND g.tenant_id = $2
+                ORDER BY g.name
+            "#,
+            user_id as _,
+            tenant_id as _
+        )
+        .fetch_all(self.pool.inner_ref())
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        let group_ids = rows.iter().map(|row| row.group_id).collect::<Vec<_>>();
+        let mut members = self.find_members(&group_ids).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                let members = members.remove(&row.group_id).unwrap_or_default();
+                row.into_group(members)
+            })
+            .collect())
+    }
+
+    async fn update(&self, tenant_id: TenantId, event: UpdateGroup) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        self.check_owner(&mut tx, tenant_id, event.group_id, event.requested_user)
+            .await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::Group, event.group_id.raw()).await?;
+
+        sqlx::query!(
+            r#"
+                UPDATE groups
+                SET name = $1, members_only_lending = $2
+                WHERE group_id = $3
+            "#,
+            event.name,
+            event.members_only_lending,
+            event.group_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| name_conflict(e, &event.name))?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::Group, event.group_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::Group,
+                event.group_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, tenant_id: TenantId, event: DeleteGroup) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        self.check_owner(&mut tx, tenant_id, event.group_id, event.requested_user)
+            .await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::Group, event.group_id.raw()).await?;
+
+        // 所有していた蔵書は外部キーの ON DELETE SET NULL で個人の所有に戻る
+        sqlx::query!(
+            r#"
+                DELETE FROM groups WHERE group_id = $1
+            "#,
+            event.group_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Delete,
+                AuditTarget::Group,
+                event.group_id.raw(),
+                before,
+                None,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn upsert_member(&self, tenant_id: TenantId, event: UpsertGroupMember) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        self.check_owner(&mut tx, tenant_id, event.group_id, event.requested_user)
+            .await?;
+
+        let exists = sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM users WHERE user_id = $1 AND tenant_id = $2 AND deleted_at IS NULL
+                ) AS "exists!"
+            "#,
+            event.user_id as _,
+            tenant_id as _
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+        if !exists {
+            return Err(AppError::EntityNotFound(format!(
+                "User with id {} not found",
+                event.user_id
+            )));
+        }
+
+        if event.role != GroupRole::Owner {
+            self.check_remaining_owner(&mut tx, event.group_id, event.user_id)
+                .await?;
+        }
+
+        let before = audit::snapshot(&mut tx, AuditTarget::Group, event.group_id.raw()).await?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO group_members (group_id, user_id, role)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (group_id, user_id) DO UPDATE SET role = EXCLUDED.role
+            "#,
+            event.group_id as _,
+            event.user_id as _,
+            event.role.as_ref()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::Group, event.group_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::Group,
+                event.group_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn remove_member(&self, tenant_id: TenantId, event: RemoveGroupMember) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // 自分自身はいつでも抜けられる
+        if event.user_id == event.requested_user {
+            self.lock_group(&mut tx, tenant_id, event.group_id).await?;
+        } else {
+            self.check_owner(&mut tx, tenant_id, event.group_id, event.requested_user)
+                .await?;
+        }
+
+        self.check_remaining_owner(&mut tx, event.group_id, event.user_id)
+            .await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::Group, event.group_id.raw()).await?;
+
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM group_members WHERE group_id = $1 AND user_id = $2
+            "#,
+            event.group_id as _,
+            event.user_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::EntityNotFound(format!(
+                "User with id {} is not a member of group with id {}",
+                event.user_id, event.group_id
+            )));
+        }
+
+        let after = audit::snapshot(&mut tx, AuditTarget::Group, event.group_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::Group,
+                event.group_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+}
+
+impl GroupRepositoryImpl {
+    async fn find_members(
+        &self,
+        group_ids: &[GroupId],
+    ) -> AppResult<HashMap<GroupId, Vec<GroupMember>>> {
+        let rows = sqlx::query_as!(
+            GroupMemberRow,
+            r#"
+                SELECT
+                    m.group_id AS "group_id: GroupId",
+                    m.user_id AS "user_id: UserId",
+                    u.name,
+                    m.role,
+                    m.joined_at
+                FROM group_members AS m
+                INNER JOIN users AS u USING (user_id)
+                WHERE m.group_id = ANY($1)
+                ORDER BY m.joined_at
+            "#,
+            group_ids as _
+        )
+        .fetch_all(self.pool.inner_ref())
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        let mut members: HashMap<GroupId, Vec<GroupMember>> = HashMap::new();
+        for row in rows {
+            let group_id = row.group_id;
+            members.entry(group_id).or_default().push(row.try_into()?);
+        }
+        Ok(members)
+    }
+
+    // メンバーの変更が同時に行われても Owner がいなくならないよう、グループの行をロックする
+    async fn lock_group(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tenant_id: TenantId,
+        group_id: GroupId,
+    ) -> AppResult<()> {
+        sqlx::query_scalar!(
+            r#"
+                SELECT group_id FROM groups WHERE group_id = $1 AND tenant_id = $2 FOR UPDATE
+            "#,
+            group_id as _,
+            tenant_id as _
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?
+        .map(|_| ())
+        .ok_or_else(|| AppError::EntityNotFound(format!("Group with id {} not found", group_id)))
+    }
+
+    async fn check_owner(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tenant_id: TenantId,
+        group_id: GroupId,
+        user_id: UserId,
+    ) -> AppResult<()> {
+        self.lock_group(tx, tenant_id, group_id).await?;
+
+        let role = sqlx::query_scalar!(
+            r#"
+                SELECT role FROM group_members WHERE group_id = $1 AND user_id = $2
+            "#,
+            group_id as _,
+            user_id as _
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if role.as_deref() != Some(GroupRole::Owner.as_ref()) {
+            return Err(AppError::ForbiddenOperationError);
+        }
+        Ok(())
+    }
+
+    // user_id が Owner でなくなっても、他に Owner が残ることを確認する
+    async fn check_remaining_owner(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        group_id: GroupId,
+        user_id: UserId,
+    ) -> AppResult<()> {
+        let remaining = sqlx::query_scalar!(
+            r#"
+                SELECT COUNT(*) AS "count!" FROM group_members
+                WHERE group_id = $1 AND role = $2 AND user_id <> $3
+            "#,
+            group_id as _,
+            GroupRole::Owner.as_ref(),
+            user_id as _
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if remaining == 0 {
+            return Err(AppError::UnprocessableEntity(
+                "A group must have at least one owner".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn name_conflict(e: sqlx::Error, name: &str) -> AppError {
+    let is_conflict = e
+        .as_database_error()
+        .and_then(|e| e.constraint())
+        .is_some_and(|c| c == "groups_tenant_id_name_key");
+    if is_conflict {
+        AppError::Conflict(format!("Group {} already exists", name))
+    } else {
+        AppError::SpecificOperationError(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::tenant::default_tenant_id;
+    use crate::password::{policy::PasswordPolicy, PasswordHasher};
+    use crate::redis::RedisClient;
+    use crate::repository::{
+        book::BookRepositoryImpl, checkout::CheckoutRepositoryImpl, user::UserRepositoryImpl,
+    };
+    use kernel::model::{
+        book::{
+            event::{ChangeBookGroup, CreateBook, DeleteBook},
+            BookListOptions,
+        },
+        checkout::event::CreateCheckout,
+        user::event::CreateUser,
+    };
+    use kernel::repository::{
+        book::BookRepository, checkout::CheckoutRepository, user::UserRepository,
+    };
+
+    #[sqlx::test]
+    async fn test_group_membership_and_books(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        let user_repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let mut users = Vec::new();
+        for name in ["Owner", "Member", "Outsider"] {
+            let user = user_repo
+                .create(tenant_id, CreateUser {
+                    name: name.into(),
+                    email: format!("{}@example.com", name.to_lowercase()),
+                    password: "test_password".into(),
+                    requested_user: None,
+                    impersonator: None,
+                    email_verified: true,
+                })
+                .await?;
+            users.push(user.user_id);
+        }
+        let [owner, member, outsider] = users[..] else {
+            unreachable!()
+        };
+
+        let repo = GroupRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        let group_id = repo
+            .create(tenant_id, CreateGroup::new("Book Club".into(), true, owner, None))
+            .await?;
+
+        let res = repo
+            .create(tenant_id, CreateGroup::new("Book Club".into(), false, member, None))
+            .await;
+        assert!(matches!(res, Err(AppError::Conflict(_))));
+
+        // Owner 以外はメンバーを追加できない
+        let res = repo
+            .upsert_member(tenant_id, UpsertGroupMember::new(group_id, outsider, GroupRole::Member, member, None))
+            .await;
+        assert!(matches!(res, Err(AppError::ForbiddenOperationError)));
+        repo.upsert_member(tenant_id, UpsertGroupMember::new(group_id, member, GroupRole::Member, owner, None))
+            .await?;
+
+        // 最後の Owner は抜けることも降格することもできない
+        let res = repo
+            .remove_member(tenant_id, RemoveGroupMember::new(group_id, owner, owner, None))
+            .await;
+        assert!(matches!(res, Err(AppError::UnprocessableEntity(_))));
+        let res = repo
+            .upsert_member(tenant_id, UpsertGroupMember::new(group_id, owner, GroupRole::Member, owner, None))
+            .await;
+        assert!(matches!(res, Err(AppError::UnprocessableEntity(_))));
+
+        let group = repo.find_by_id(tenant_id, group_id).await?.unwrap();
+        assert_eq!(group.members.len(), 2);
+        assert_eq!(repo.find_by_user_id(tenant_id, member).await?.len(), 1);
+        assert!(repo.find_by_user_id(tenant_id, outsider).await?.is_empty());
+
+        // グループの蔵書はメンバーであれば編集でき、メンバー以外には貸し出さない
+        let book_repo = BookRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        let create_book = |group_id| CreateBook {
+            title: "Test Title".into(),
+            author: "Test Author".into(),
+            isbn: "Test ISBN".into(),
+            description: "Test Description".into(),
+            group_id,
+            impersonator: None,
+        };
+        let res = book_repo.create(tenant_id, create_book(Some(group_id)), outsider).await;
+        assert!(matches!(res, Err(AppError::ForbiddenOperationError)));
+        book_repo.create(tenant_id, create_book(Some(group_id)), owner).await?;
+
+        let book = book_repo
+            .find_all(tenant_id, BookListOptions {
+                limit: 10,
+                offset: 0,
+            })
+            .await?
+            .items
+            .remove(0);
+        let book_group = book.owner.group.as_ref().unwrap();
+        assert_eq!(book_group.group_id, group_id);
+        assert_eq!(book_group.name, "Book Club");
+
+        let checkout_repo = CheckoutRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        let res = checkout_repo
+            .create(tenant_id, CreateCheckout::new(book.book_id, outsider, None, chrono::Utc::now()))
+            .await;
+        assert!(matches!(res, Err(AppError::ForbiddenOperationError)));
+        checkout_repo
+            .create(tenant_id, CreateCheckout::new(book.book_id, member, None, chrono::Utc::now()))
+            .await?;
+
+        let res = book_repo
+            .change_group(tenant_id, ChangeBookGroup::new(book.book_id, None, outsider, None, false))
+            .await;
+        assert!(matches!(res, Err(AppError::EntityNotFound(_))));
+
+        // メンバーを外れると編集できなくなる
+        repo.remove_member(tenant_id, RemoveGroupMember::new(group_id, member, member, None))
+            .await?;
+        let res = book_repo
+            .delete(tenant_id, DeleteBook {
+                book_id: book.book_id,
+                requested_user: member,
+                impersonator: None,
+                any_owner: false,
+                version: book.version,
+            })
+            .await;
+        assert!(matches!(res, Err(AppError::EntityNotFound(_))));
+
+        // グループを削除すると登録者の個人所有に戻る
+        repo.delete(tenant_id, DeleteGroup::new(group_id, owner, None)).await?;
+        let book = book_repo.find_by_id(tenant_id, book.book_id).await?.unwrap();
+        assert!(book.owner.group.is_none());
+        assert_eq!(book.owner.user_id, owner);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+use kernel::model::{
+    audit::{event::CreateAuditLog, AuditAction, AuditTarget},
+    id::{TenantId, UserId},
+    identity::ExternalIdentity,
+    role::Role,
+};
+use shared::error::{AppError, AppResult};
+use sqlx::PgConnection;
+use std::str::FromStr;
+
+use crate::password::PasswordHasher;
+use crate::repository::{audit, user::email_conflict};
+
+pub(crate) struct ProvisionedUser {
+    pub user_id: UserId,
+    pub role_changed: bool,
+}
+
+// ID プロバイダやディレクトリのアカウントに対応するテナント内のユーザーを返す。
+// 未登録の場合は確認済みのメールアドレスで既存ユーザーに紐づけ、それもなければ作成する
+pub(crate) async fn provision_user(
+    conn: &mut PgConnection,
+    hasher: &PasswordHasher,
+    tenant_id: TenantId,
+    provider: &str,
+    identity: &ExternalIdentity,
+    role: Option<Role>,
+) -> AppResult<ProvisionedUser> {
+    let linked = sqlx::query_scalar!(
+        r#"
+            UPDATE user_identities AS i
+            SET last_login_at = CURRENT_TIMESTAMP(3)
+            FROM users AS u
+            WHERE i.user_id = u.user_id
+            AND i.tenant_id = $3 AND i.provider = $1 AND i.subject = $2
+            AND u.deleted_at IS NULL
+            RETURNING i.user_id AS "user_id: UserId"
+        "#,
+        provider,
+        identity.subject,
+        tenant_id as _
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(AppError::SpecificOperationError)?;
+
+    let user_id = match linked {
+        Some(user_id) => user_id,
+        None => {
+            let user_id = match find_user_by_email(conn, tenant_id, &identity.email).await? {
+                // 未確認のメールアドレスで紐づけると、他人のアカウントを乗っ取れてしまう
+                Some(_) if !identity.email_verified => {
+                    return Err(AppError::Conflict(format!(
+                        "Email {} is already used by another user",
+                        identity.email
+                    )));
+                }
+                Some(user_id) => user_id,
+                None => {
+                    let user_id =
+                        create_user(conn, hasher, tenant_id, identity, role.as_ref().unwrap_or(&Role::User))
+                            .await?;
+                    return link_identity(conn, tenant_id, provider, identity, user_id)
+                        .await
+                        .map(|_| ProvisionedUser {
+                            user_id,
+                            role_changed: false,
+                        });
+                }
+            };
+            link_identity(conn, tenant_id, provider, identity, user_id).await?;
+            user_id
+        }
+    };
+
+    // 無効化された利用者は、外部の認証に成功してもログインさせない
+    let active = sqlx::query_scalar!(
+        r#"SELECT active FROM users WHERE user_id = $1"#,
+        user_id as _
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(AppError::SpecificOperationError)?;
+    if !active {
+        return Err(AppError::AccountDeactivated);
+    }
+
+    let role_changed = sync_user(conn, user_id, identity, role).await?;
+    Ok(ProvisionedUser {
+        user_id,
+        role_changed,
+    })
+}
+
+async fn find_user_by_email(
+    conn: &mut PgConnection,
+    tenant_id: TenantId,
+    email: &str,
+) -> AppResult<Option<UserId>> {
+    sqlx::query_scalar!(
+        r#"
+            SELECT user_id AS "user_id: UserId" FROM users
+            WHERE tenant_id = $2 AND email = $1 AND deleted_at IS NULL
+        "#,
+        email,
+        tenant_id as _
+    )
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(AppError::SpecificOperationError)
+}
+
+async fn create_user(
+    conn: &mut PgConnection,
+    hasher: &PasswordHasher,
+    tenant_id: TenantId,
+    identity: &ExternalIdentity,
+    role: &Role,
+) -> AppResult<UserId> {
+    let user_id = UserId::new();
+    // パスワードではログインさせないため、誰も知らない値をハッシュ化して入れておく
+    let unusable_password = hasher.hash(&uuid::Uuid::new_v4().to_string())?;
+    let name = identity.name.as_deref().unwrap_or(&identity.email);
+
+    sqlx::query!(
+        r#"
+            INSERT INTO users(user_id, tenant_id, name, email, password_hash, role_id, email_verified_at)
+            SELECT $1, $7, $2, $3, $4, role_id, CASE WHEN $6 THEN CURRENT_TIMESTAMP(3) END
+            FROM roles WHERE name = $5;
+        "#,
+        user_id as _,
+        name,
+        identity.email,
+        unusable_password,
+        role.as_ref(),
+        identity.email_verified,
+        tenant_id as _
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| email_conflict(e, &identity.email))?;
+
+    let after = audit::snapshot(conn, AuditTarget::User, user_id.raw()).await?;
+    audit::record(
+        conn,
+        CreateAuditLog::new(
+            None,
+            None,
+            AuditAction::Create,
+            AuditTarget::User,
+            user_id.raw(),
+            None,
+            after,
+        ),
+    )
+    .await?;
+
+    Ok(user_id)
+}
+
+async fn link_identity(
+    conn: &mut PgConnection,
+    tenant_id: TenantId,
+    provider: &str,
+    identity: &ExternalIdentity,
+    user_id: UserId,
+) -> AppResult<()> {
+    sqlx::query!(
+        r#"
+            INSERT INTO user_identities(tenant_id, provider, subject, user_id)
+            VALUES ($1, $2, $3, $4)
+        "#,
+        tenant_id as _,
+        provider,
+        identity.subject,
+        user_id as _
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(AppError::SpecificOperationError)?;
+    Ok(())
+}
+
+// 名前・確認済みのメールアドレス・ロールを ID プロバイダに合わせる。ロールが変わった場合は true を返す
+async fn sync_user(
+    conn: &mut PgConnection,
+    user_id: UserId,
+    identity: &ExternalIdentity,
+    role: Option<Role>,
+) -> AppResult<bool> {
+    let current = sqlx::query!(
+        r#"
+            SELECT u.name, u.email, r.name AS role_name
+            FROM users AS u
+            INNER JOIN roles AS r USING (role_id)
+            WHERE u.user_id = $1
+        "#,
+        user_id as _
+    )
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(AppError::SpecificOperationError)?;
+
+    let name = identity.name.as_ref().filter(|n| **n != current.name);
+    let email = Some(&identity.email).filter(|e| identity.email_verified && **e != current.email);
+    // グループから決まるのは Admin と User だけなので、それ以外のロールはアプリ側の割り当てを優先する
+    let current_role = Role::from_str(&current.role_name).ok();
+    let role = role.filter(|r| {
+        matches!(current_role, Some(Role::Admin | Role::User)) && current_role.as_ref() != Some(r)
+    });
+    if name.is_none() && email.is_none() && role.is_none() {
+        return Ok(false);
+    }
+
+    let before = audit::snapshot(conn, AuditTarget::User, user_id.raw()).await?;
+    sqlx::query!(
+        r#"
+            UPDATE users
+            SET
+                name = COALESCE($2, name),
+                email = COALESCE($3, email),
+                email_verified_at = CASE
+                    WHEN $3 IS NULL THEN email_verified_at
+                    ELSE CURRENT_TIMESTAMP(3)
+                END,
+                role_id = COALESCE((SELECT role_id FROM roles WHERE name = $4), role_id)
+            WHERE user_id = $1
+        "#,
+        user_id as _,
+        name,
+        email,
+        role.as_ref().map(|r| r.as_ref())
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| email_conflict(e, &identity.email))?;
+
+    let after = audit::snapshot(conn, AuditTarget::User, user_id.raw()).await?;
+    audit::record(
+        conn,
+        CreateAuditLog::new(
+            None,
+            None,
+            AuditAction::Update,
+            AuditTarget::User,
+            user_id.raw(),
+            before,
+            after,
+        ),
+    )
+    .await?;
+
+    Ok(role.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::tenant::default_tenant_id;
+
+    fn identity(email_verified: bool, groups: &[&str]) -> ExternalIdentity {
+        ExternalIdentity {
+            subject: "idp-user-1".into(),
+            email: "sso@example.com".into(),
+            email_verified,
+            name: Some("SSO User".into()),
+            groups: groups.iter().map(|g| g.to_string()).collect(),
+        }
+    }
+
+    async fn role_of(pool: &sqlx::PgPool, user_id: UserId) -> anyhow::Result<String> {
+        Ok(sqlx::query_scalar!(
+            r#"
+                SELECT r.name FROM users INNER JOIN roles AS r USING (role_id)
+                WHERE user_id = $1
+            "#,
+            user_id as _
+        )
+        .fetch_one(pool)
+        .await?)
+    }
+
+    #[sqlx::test]
+    async fn test_provision_user(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+        let tenant_id = default_tenant_id(&pool).await?;
+        let issuer = "http://idp.example.com";
+        let mut conn = pool.acquire().await?;
+        let hasher = PasswordHasher::local();
+
+        // 初回ログインでユーザーを作成し、グループからロールを決める
+        let first = provision_user(
+            &mut conn,
+            &hasher,
+            tenant_id,
+            issuer,
+            &identity(true, &["library-admins"]),
+            Some(Role::Admin),
+        )
+        .await?;
+        assert!(!first.role_changed);
+        assert_eq!(role_of(&pool, first.user_id).await?, "Admin");
+
+        // 2 回目以降は同じユーザーを返し、グループの変更をロールに反映する
+        let second =
+            provision_user(&mut conn, &hasher, tenant_id, issuer, &identity(true, &[]), Some(Role::User)).await?;
+        assert_eq!(second.user_id, first.user_id);
+        assert!(second.role_changed);
+        assert_eq!(role_of(&pool, first.user_id).await?, "User");
+
+        // 別のプロバイダでも、確認済みのメールアドレスなら既存ユーザーに紐づける
+        let other = provision_user(&mut conn, &hasher, tenant_id, "http://other.example.com", &identity(true, &[]), None)
+            .await?;
+        assert_eq!(other.user_id, first.user_id);
+
+        // 未確認のメールアドレスでは紐づけない
+        let res = provision_user(
+            &mut conn,
+            &hasher,
+            tenant_id,
+            "http://unverified.example.com",
+            &identity(false, &[]),
+            None,
+        )
+        .await;
+        assert!(matches!(res, Err(AppError::Conflict(_))));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_provision_keeps_other_roles(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('Librarian'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+        let tenant_id = default_tenant_id(&pool).await?;
+        let issuer = "http://idp.example.com";
+        let mut conn = pool.acquire().await?;
+        let hasher = PasswordHasher::local();
+
+        let user =
+            provision_user(&mut conn, &hasher, tenant_id, issuer, &identity(true, &[]), Some(Role::User)).await?;
+        sqlx::query!(
+            "UPDATE users SET role_id = (SELECT role_id FROM roles WHERE name = 'Librarian') WHERE user_id = $1",
+            user.user_id as _
+        )
+        .execute(&pool)
+        .await?;
+
+        // 司書は管理者グループの有無にかかわらず、SSO でログインしても司書のまま
+        for role in [Role::User, Role::Admin] {
+            let res =
+                provision_user(&mut conn, &hasher, tenant_id, issuer, &identity(true, &[]), Some(role)).await?;
+            assert!(!res.role_changed);
+            assert_eq!(role_of(&pool, user.user_id).await?, "Librarian");
+        }
+
+        Ok(())
+    }
+}
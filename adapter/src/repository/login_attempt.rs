@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use kernel::model::{auth::LoginAttempt, id::TenantId};
+use kernel::repository::login_attempt::LoginAttemptRepository;
+use shared::{
+    config::LoginThrottleConfig,
+    error::{AppError, AppResult},
+};
+use std::sync::Arc;
+
+use crate::database::model::login_attempt::{
+    BlockedUntil, LoginDelayKey, LoginFailureKey, LoginLockoutKey,
+};
+use crate::redis::RedisClient;
+
+pub struct LoginAttemptRepositoryImpl {
+    kv: Arc<RedisClient>,
+    config: LoginThrottleConfig,
+}
+
+impl LoginAttemptRepositoryImpl {
+    pub fn new(kv: Arc<RedisClient>, config: LoginThrottleConfig) -> Self {
+        Self { kv, config }
+    }
+}
+
+#[async_trait]
+impl LoginAttemptRepository for LoginAttemptRepositoryImpl {
+    async fn check(&self, attempt: &LoginAttempt) -> AppResult<()> {
+        if let Some(until) = self.kv.get(&LoginLockoutKey::email(attempt.tenant_id, &attempt.email)).await? {
+            return Err(AppError::AccountLocked(until.remaining_secs()));
+        }
+        if let Some(ip_address) = &attempt.ip_address
+            && let Some(until) = self.kv.get(&LoginLockoutKey::ip(ip_address)).await?
+        {
+            return Err(AppError::TooManyRequests(until.remaining_secs()));
+        }
+        if let Some(until) = self.kv.get(&LoginDelayKey::email(attempt.tenant_id, &attempt.email)).await? {
+            return Err(AppError::TooManyRequests(until.remaining_secs()));
+        }
+        Ok(())
+    }
+
+    async fn record_failure(&self, attempt: &LoginAttempt) -> AppResult<()> {
+        let failures = self
+            .kv
+            .increment(
+                &LoginFailureKey::email(attempt.tenant_id, &attempt.email),
+                self.config.attempt_window,
+            )
+            .await?;
+
+        if failures >= self.config.max_attempts {
+            tracing::warn!(email = %attempt.email, failures, "Account locked after failed logins");
+            self.kv
+                .set_ex(
+                    &LoginLockoutKey::email(attempt.tenant_id, &attempt.email),
+                    &BlockedUntil::after(self.config.lockout_duration),
+                    self.config.lockout_duration,
+                )
+                .await?;
+            self.clear_failures(attempt.tenant_id, &attempt.email).await?;
+        } else {
+            let delay = progressive_delay(failures, self.config.max_delay);
+            self.kv
+                .set_ex(
+                    &LoginDelayKey::email(attempt.tenant_id, &attempt.email),
+                    &BlockedUntil::after(delay),
+                    delay,
+                )
+                .await?;
+        }
+
+        // 多数のアカウントを狙う攻撃は接続元ごとに止める
+        if let Some(ip_address) = &attempt.ip_address {
+            let key = LoginFailureKey::ip(ip_address);
+            let failures = self.kv.increment(&key, self.config.attempt_window).await?;
+            if failures >= self.config.ip_max_attempts {
+                tracing::warn!(%ip_address, failures, "Client blocked after failed logins");
+                self.kv
+                    .set_ex(
+                        &LoginLockoutKey::ip(ip_address),
+                        &BlockedUntil::after(self.config.lockout_duration),
+                        self.config.lockout_duration,
+                    )
+                    .await?;
+                self.kv.delete(&key).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // 接続元の失敗回数は、別のアカウントでの成功によって消されないよう残しておく
+    async fn record_success(&self, attempt: &LoginAttempt) -> AppResult<()> {
+        self.clear_failures(attempt.tenant_id, &attempt.email).await
+    }
+
+    async fn unlock(&self, tenant_id: TenantId, email: &str) -> AppResult<()> {
+        let email = email.trim().to_lowercase();
+        self.kv.delete(&LoginLockoutKey::email(tenant_id, &email)).await?;
+        self.clear_failures(tenant_id, &email).await
+    }
+}
+
+impl LoginAttemptRepositoryImpl {
+    async fn clear_failures(&self, tenant_id: TenantId, email: &str) -> AppResult<()> {
+        self.kv.delete(&LoginFailureKey::email(tenant_id, email)).await?;
+        self.kv.delete(&LoginDelayKey::email(tenant_id, email)).await
+    }
+}
+
+// n 回目の失敗後の待ち時間。1, 2, 4, ... 秒と倍々に増やし、上限で頭打ちにする
+fn progressive_delay(failures: u64, max_delay: u64) -> u64 {
+    let exponent = failures.saturating_sub(1).min(63) as u32;
+    2u64.saturating_pow(exponent).min(max_delay).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progressive_delay() {
+        assert_eq!(progressive_delay(1, 30), 1);
+        assert_eq!(progressive_delay(2, 30), 2);
+        assert_eq!(progressive_delay(4, 30), 8);
+        assert_eq!(progressive_delay(6, 30), 30);
+        assert_eq!(progressive_delay(100, 30), 30);
+    }
+}
@@ -1,5 +1,14 @@
+pub mod api_key;
+pub mod audit;
 pub mod book;
+pub mod group;
 pub mod health;
+pub mod identity;
+pub mod login_attempt;
+pub mod oidc;
+pub mod role;
+pub mod tenant;
 pub mod auth;
 pub mod user;
 pub mod checkout;
+pub mod two_factor;
@@ -0,0 +1,134 @@
+use async_trait::async_trait;
+use kernel::model::{
+    id::{TenantId, UserId},
+    oidc::{event::CompleteOidcLogin, OidcAuthorization},
+    role::Role,
+};
+use kernel::repository::oidc::OidcRepository;
+use shared::{
+    config::OidcConfig,
+    error::{AppError, AppResult},
+};
+use std::sync::Arc;
+
+use crate::database::{
+    model::oidc::{OidcStateKey, OidcStateValue},
+    ConnectionPool,
+};
+use crate::oidc::{OidcClient, Pkce};
+use crate::password::PasswordHasher;
+use crate::redis::RedisClient;
+use crate::repository::{auth::revoke_all_sessions, identity::provision_user};
+
+pub struct OidcRepositoryImpl {
+    db: ConnectionPool,
+    kv: Arc<RedisClient>,
+    client: OidcClient,
+    hasher: PasswordHasher,
+}
+
+impl OidcRepositoryImpl {
+    pub fn new(
+        db: ConnectionPool,
+        kv: Arc<RedisClient>,
+        config: OidcConfig,
+        hasher: PasswordHasher,
+    ) -> Self {
+        Self {
+            db,
+            kv,
+            client: OidcClient::new(config),
+            hasher,
+        }
+    }
+}
+
+#[async_trait]
+impl OidcRepository for OidcRepositoryImpl {
+    async fn start_login(&self, tenant_id: TenantId) -> AppResult<OidcAuthorization> {
+        let key = OidcStateKey::generate();
+        let nonce = uuid::Uuid::new_v4().simple().to_string();
+        let pkce = Pkce::generate();
+
+        let authorization_url = self
+            .client
+            .authorization_url(key.state(), &nonce, &pkce.challenge)
+            .await?;
+        let value = OidcStateValue {
+            tenant_id,
+            code_verifier: pkce.verifier,
+            nonce,
+        };
+        self.kv
+            .set_ex(&key, &value, self.client.config().state_ttl)
+            .await?;
+
+        Ok(OidcAuthorization { authorization_url })
+    }
+
+    async fn complete_login(
+        &self,
+        tenant_id: TenantId,
+        event: CompleteOidcLogin,
+    ) -> AppResult<UserId> {
+        let state = self
+            .kv
+            .take(&OidcStateKey::from(event.state))
+            .await?
+            .filter(|state| state.tenant_id == tenant_id)
+            .ok_or(AppError::UnauthenticatedError)?;
+        let identity = self
+            .client
+            .exchange_code(&event.code, &state.code_verifier, &state.nonce)
+            .await?;
+
+        let config = self.client.config();
+        let role = map_role(&identity.groups, &config.admin_groups);
+
+        let mut tx = self.db.begin().await?;
+        let provisioned = provision_user(&mut tx, &self.hasher, tenant_id, &config.issuer, &identity, role).await?;
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        // ID プロバイダ側でロールが変わった場合も、既存のセッションは引き継がない
+        if provisioned.role_changed {
+            let user_id = provisioned.user_id;
+            match revoke_all_sessions(&self.kv, user_id).await {
+                Ok(revoked) => tracing::info!(%user_id, revoked, "Revoked user tokens"),
+                Err(e) => tracing::error!(
+                    %user_id, error.message = %e, "Failed to revoke user tokens"
+                ),
+            }
+        }
+
+        Ok(provisioned.user_id)
+    }
+}
+
+// 管理者グループが設定されていない場合は、ロールを ID プロバイダに合わせない
+pub(crate) fn map_role(groups: &[String], admin_groups: &[String]) -> Option<Role> {
+    if admin_groups.is_empty() {
+        return None;
+    }
+    if groups.iter().any(|g| admin_groups.contains(g)) {
+        Some(Role::Admin)
+    } else {
+        Some(Role::User)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_role() {
+        let admin_groups = vec!["library-admins".to_string()];
+        let groups = |g: &[&str]| g.iter().map(|g| g.to_string()).collect::<Vec<_>>();
+        assert_eq!(map_role(&groups(&["staff"]), &[]), None);
+        assert_eq!(
+            map_role(&groups(&["staff", "library-admins"]), &admin_groups),
+            Some(Role::Admin)
+        );
+        assert_eq!(map_role(&groups(&["staff"]), &admin_groups), Some(Role::User));
+    }
+}
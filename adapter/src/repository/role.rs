@@ -0,0 +1,78 @@
+use async_trait::async_trait;
+use derive_new::new;
+use kernel::{
+    model::role::{Permission, Role},
+    repository::role::RoleRepository,
+};
+use shared::error::{AppError, AppResult};
+use std::str::FromStr;
+
+use crate::database::ConnectionPool;
+
+#[derive(new)]
+pub struct RoleRepositoryImpl {
+    pool: ConnectionPool,
+}
+
+#[async_trait]
+impl RoleRepository for RoleRepositoryImpl {
+    async fn find_permissions(&self, role: Role) -> AppResult<Vec<Permission>> {
+        let permissions = sqlx::query_scalar!(
+            r#"
+                SELECT rp.permission
+                FROM role_permissions AS rp
+                INNER JOIN roles AS r USING (role_id)
+                WHERE r.name = $1
+                ORDER BY rp.permission
+            "#,
+            role.as_ref()
+        )
+        .fetch_all(self.pool.inner_ref())
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        // コードが知らない権限が登録されていても、それ以外の権限は有効にする
+        Ok(permissions
+            .iter()
+            .filter_map(|p| match Permission::from_str(p) {
+                Ok(permission) => Some(permission),
+                Err(_) => {
+                    tracing::warn!(permission = %p, role = role.as_ref(), "Unknown permission is ignored");
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    async fn test_find_permissions(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let repo = RoleRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+
+        let admin = repo.find_permissions(Role::Admin).await?;
+        assert_eq!(admin.len(), 4);
+
+        let librarian = repo.find_permissions(Role::Librarian).await?;
+        assert!(librarian.contains(&Permission::BookWriteAny));
+        assert!(librarian.contains(&Permission::CheckoutForceReturn));
+        assert!(!librarian.contains(&Permission::UserManage));
+
+        assert!(repo.find_permissions(Role::User).await?.is_empty());
+
+        sqlx::query!(
+            r#"
+                INSERT INTO role_permissions(role_id, permission)
+                SELECT role_id, 'unknown:permission' FROM roles WHERE name = 'User'
+            "#
+        )
+        .execute(&pool)
+        .await?;
+        assert!(repo.find_permissions(Role::User).await?.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,264 @@
+use async_trait::async_trait;
+use derive_new::new;
+use kernel::model::{
+    audit::{event::CreateAuditLog, AuditAction, AuditTarget},
+    id::TenantId,
+    role::Role,
+    tenant::{event::CreateTenant, Tenant},
+    user::event::CreateUser,
+};
+use kernel::repository::tenant::TenantRepository;
+use shared::error::{AppError, AppResult};
+
+use crate::database::{model::tenant::TenantRow, ConnectionPool};
+use crate::password::{policy::PasswordPolicy, PasswordHasher};
+use crate::repository::{audit, user::insert_user};
+
+#[derive(new)]
+pub struct TenantRepositoryImpl {
+    pool: ConnectionPool,
+    hasher: PasswordHasher,
+    policy: PasswordPolicy,
+}
+
+#[async_trait]
+impl TenantRepository for TenantRepositoryImpl {
+    async fn create(&self, event: CreateTenant) -> AppResult<Tenant> {
+        let mut tx = self.pool.begin().await?;
+
+        let tenant = sqlx::query_as!(
+            TenantRow,
+            r#"
+                INSERT INTO tenants (slug, name)
+                VALUES ($1, $2)
+                RETURNING tenant_id, slug, name, created_at
+            "#,
+            event.slug,
+            event.name
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| slug_conflict(e, &event.slug))
+        .map(Tenant::from)?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::Tenant, tenant.tenant_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Create,
+                AuditTarget::Tenant,
+                tenant.tenant_id.raw(),
+                None,
+                after,
+            ),
+        )
+        .await?;
+
+        // テナントの管理者がいなければ、利用者の登録もできないため同時に作成する
+        let admin = CreateUser {
+            name: event.admin_name,
+            email: event.admin_email,
+            password: event.admin_password,
+            requested_user: Some(event.requested_user),
+            impersonator: event.impersonator,
+            email_verified: true,
+        };
+        insert_user(
+            &mut tx,
+            &self.hasher,
+            &self.policy,
+            tenant.tenant_id,
+            admin,
+            Role::Admin,
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(tenant)
+    }
+
+    async fn find_by_slug(&self, slug: &str) -> AppResult<Option<Tenant>> {
+        sqlx::query_as!(
+            TenantRow,
+            r#"
+                SELECT tenant_id, slug, name, created_at
+                FROM tenants
+                WHERE slug = $1
+            "#,
+            slug
+        )
+        .fetch_optional(self.pool.inner_ref())
+        .await
+        .map(|row| row.map(Tenant::from))
+        .map_err(AppError::SpecificOperationError)
+    }
+
+    async fn find_by_id(&self, tenant_id: TenantId) -> AppResult<Option<Tenant>> {
+        sqlx::query_as!(
+            TenantRow,
+            r#"
+                SELECT tenant_id, slug, name, created_at
+                FROM tenants
+                WHERE tenant_id = $1
+            "#,
+            tenant_id as _
+        )
+        .fetch_optional(self.pool.inner_ref())
+        .await
+        .map(|row| row.map(Tenant::from))
+        .map_err(AppError::SpecificOperationError)
+    }
+
+    async fn find_all(&self) -> AppResult<Vec<Tenant>> {
+        sqlx::query_as!(
+            TenantRow,
+            r#"
+                SELECT tenant_id, slug, name, created_at
+                FROM tenants
+                ORDER BY created_at
+            "#
+        )
+        .fetch_all(self.pool.inner_ref())
+        .await
+        .map(|rows| rows.into_iter().map(Tenant::from).collect())
+        .map_err(AppError::SpecificOperationError)
+    }
+}
+
+fn slug_conflict(e: sqlx::Error, slug: &str) -> AppError {
+    let is_conflict = e
+        .as_database_error()
+        .and_then(|e| e.constraint())
+        .is_some_and(|c| c == "tenants_slug_key");
+    if is_conflict {
+        AppError::Conflict(format!("Tenant {} already exists", slug))
+    } else {
+        AppError::SpecificOperationError(e)
+    }
+}
+
+/// テスト用に、マイグレーションで作成される default テナントの ID を返す
+#[cfg(test)]
+pub(crate) async fn default_tenant_id(pool: &sqlx::PgPool) -> anyhow::Result<TenantId> {
+    Ok(sqlx::query_scalar!(
+        r#"SELECT tenant_id AS "tenant_id: TenantId" FROM tenants WHERE slug = 'default'"#
+    )
+    .fetch_one(pool)
+    .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redis::RedisClient;
+    use crate::repository::{book::BookRepositoryImpl, user::UserRepositoryImpl};
+    use kernel::model::book::{event::CreateBook, BookListOptions};
+    use kernel::model::user::UserListOptions;
+    use kernel::repository::{book::BookRepository, user::UserRepository};
+
+    #[sqlx::test]
+    async fn test_tenant_isolation(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('SuperAdmin'), ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+        let default_tenant = default_tenant_id(&pool).await?;
+        let user_repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let super_admin = user_repo
+            .create(default_tenant, CreateUser {
+                name: "Super Admin".into(),
+                email: "admin@example.com".into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+
+        let repo = TenantRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let event = |slug: &str| {
+            CreateTenant::new(
+                slug.into(),
+                "Branch Library".into(),
+                "Branch Admin".into(),
+                "admin@example.com".into(),
+                "test_password".into(),
+                super_admin.user_id,
+                None,
+            )
+        };
+        // 別テナントであれば同じメールアドレスで登録できる
+        let tenant = repo.create(event("branch")).await?;
+        assert_eq!(
+            repo.find_by_slug("branch").await?.map(|t| t.tenant_id),
+            Some(tenant.tenant_id)
+        );
+        assert!(matches!(
+            repo.create(event("branch")).await,
+            Err(AppError::Conflict(_))
+        ));
+
+        let branch_users = user_repo
+            .find_all(tenant.tenant_id, UserListOptions {
+                keyword: None,
+                search_email: true,
+                role: None,
+                active: None,
+                limit: 10,
+                offset: 0,
+            })
+            .await?
+            .items;
+        assert_eq!(branch_users.len(), 1);
+        assert_eq!(branch_users[0].role, Role::Admin);
+        assert_ne!(branch_users[0].user_id, super_admin.user_id);
+        assert!(user_repo
+            .find_current_user(tenant.tenant_id, super_admin.user_id)
+            .await?
+            .is_none());
+
+        // 蔵書も他テナントからは見えない
+        let book_repo = BookRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        book_repo
+            .create(
+                default_tenant,
+                CreateBook {
+                    title: "Test Title".into(),
+                    author: "Test Author".into(),
+                    isbn: "Test ISBN".into(),
+                    description: "Test Description".into(),
+                    group_id: None,
+                    impersonator: None,
+                },
+                super_admin.user_id,
+            )
+            .await?;
+        let options = || BookListOptions {
+            limit: 10,
+            offset: 0,
+        };
+        let book = &book_repo.find_all(default_tenant, options()).await?.items[0];
+        assert_eq!(book_repo.find_all(tenant.tenant_id, options()).await?.total, 0);
+        assert!(book_repo
+            .find_by_id(tenant.tenant_id, book.book_id)
+            .await?
+            .is_none());
+
+        Ok(())
+    }
+}
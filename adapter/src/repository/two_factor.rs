@@ -0,0 +1,482 @@
+use async_trait::async_trait;
+use kernel::model::{
+    audit::{event::CreateAuditLog, AuditAction, AuditTarget},
+    id::UserId,
+    two_factor::{
+        event::{ActivateTotp, CreateLoginChallenge, DisableTwoFactor, StartTotpEnrollment},
+        LoginChallenge, LoginChallengeToken, RecoveryCodes, TotpEnrollment,
+    },
+};
+use kernel::repository::two_factor::TwoFactorRepository;
+use sha2::{Digest, Sha256};
+use shared::{
+    config::TwoFactorConfig,
+    error::{AppError, AppResult},
+};
+use sqlx::PgConnection;
+use std::sync::Arc;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::database::{
+    model::two_factor::{LoginChallengeKey, LoginChallengeValue, UserTotpRow},
+    ConnectionPool,
+};
+use crate::redis::RedisClient;
+use crate::repository::audit;
+
+const TOTP_DIGITS: usize = 6;
+const TOTP_STEP: u64 = 30;
+// 端末の時計のずれを考慮し、前後 1 ステップのコードまで受け付ける
+const TOTP_SKEW: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+pub struct TwoFactorRepositoryImpl {
+    db: ConnectionPool,
+    kv: Arc<RedisClient>,
+    config: TwoFactorConfig,
+}
+
+impl TwoFactorRepositoryImpl {
+    pub fn new(db: ConnectionPool, kv: Arc<RedisClient>, config: TwoFactorConfig) -> Self {
+        Self { db, kv, config }
+    }
+}
+
+#[async_trait]
+impl TwoFactorRepository for TwoFactorRepositoryImpl {
+    async fn is_enabled(&self, user_id: UserId) -> AppResult<bool> {
+        sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM user_totp
+                    WHERE user_id = $1 AND enabled_at IS NOT NULL
+                ) AS "exists!"
+            "#,
+            user_id as _
+        )
+        .fetch_one(self.db.inner_ref())
+        .await
+        .map_err(AppError::SpecificOperationError)
+    }
+
+    async fn start_enrollment(&self, event: StartTotpEnrollment) -> AppResult<TotpEnrollment> {
+        let Secret::Encoded(secret) = Secret::generate_secret().to_encoded() else {
+            unreachable!("to_encoded always returns an encoded secret");
+        };
+        let totp = build_totp(&secret, &self.config.issuer, &event.account_name)?;
+
+        // 有効化済みの場合は上書きしない。登録途中であればシークレットを作り直す
+        let res = sqlx::query!(
+            r#"
+                INSERT INTO user_totp (user_id, secret)
+                VALUES ($1, $2)
+                ON CONFLICT (user_id) DO UPDATE
+                SET secret = EXCLUDED.secret,
+                    last_used_step = NULL,
+                    created_at = CURRENT_TIMESTAMP(3)
+                WHERE user_totp.enabled_at IS NULL
+            "#,
+            event.user_id as _,
+            secret
+        )
+        .execute(self.db.inner_ref())
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::Conflict(
+                "Two-factor authentication is already enabled".into(),
+            ));
+        }
+
+        Ok(TotpEnrollment {
+            otpauth_uri: totp.get_url(),
+            secret,
+        })
+    }
+
+    async fn activate(&self, event: ActivateTotp) -> AppResult<RecoveryCodes> {
+        let mut tx = self.db.begin().await?;
+
+        let row = find_totp(&mut tx, event.user_id).await?.ok_or_else(|| {
+            AppError::EntityNotFound("Two-factor enrollment has not been started".into())
+        })?;
+        if row.enabled_at.is_some() {
+            return Err(AppError::Conflict(
+                "Two-factor authentication is already enabled".into(),
+            ));
+        }
+
+        if !self.check_totp(&mut tx, event.user_id, &row, &event.code).await? {
+            return Err(AppError::UnprocessableEntity(
+                "Invalid verification code".into(),
+            ));
+        }
+
+        let before = audit::snapshot(&mut tx, AuditTarget::TwoFactor, event.user_id.raw()).await?;
+
+        sqlx::query!(
+            r#"
+                UPDATE user_totp SET enabled_at = CURRENT_TIMESTAMP(3)
+                WHERE user_id = $1
+            "#,
+            event.user_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        let codes = replace_recovery_codes(&mut tx, event.user_id).await?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::TwoFactor, event.user_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.user_id),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::TwoFactor,
+                event.user_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(RecoveryCodes(codes))
+    }
+
+    async fn verify_code(&self, user_id: UserId, code: &str) -> AppResult<bool> {
+        let code = code.trim();
+        let mut tx = self.db.begin().await?;
+
+        let Some(row) = find_totp(&mut tx, user_id).await? else {
+            return Ok(false);
+        };
+        if row.enabled_at.is_none() {
+            return Ok(false);
+        }
+
+        let valid = if is_totp_code(code) {
+            self.check_totp(&mut tx, user_id, &row, code).await?
+        } else {
+            use_recovery_code(&mut tx, user_id, code).await?
+        };
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(valid)
+    }
+
+    async fn disable(&self, event: DisableTwoFactor) -> AppResult<()> {
+        let mut tx = self.db.begin().await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::TwoFactor, event.user_id.raw()).await?;
+
+        let res = sqlx::query!(
+            r#"
+                DELETE FROM user_totp WHERE user_id = $1
+            "#,
+            event.user_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        if res.rows_affected() < 1 {
+            return Err(AppError::EntityNotFound(
+                "Two-factor authentication is not enabled".into(),
+            ));
+        }
+
+        sqlx::query!(
+            r#"
+                DELETE FROM user_recovery_codes WHERE user_id = $1
+            "#,
+            event.user_id as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Delete,
+                AuditTarget::TwoFactor,
+                event.user_id.raw(),
+                before,
+                None,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn create_login_challenge(
+        &self,
+        event: CreateLoginChallenge,
+    ) -> AppResult<LoginChallengeToken> {
+        let key = LoginChallengeKey::generate();
+        self.kv
+            .set_ex(
+                &key,
+                &LoginChallengeValue::from(event),
+                self.config.challenge_ttl,
+            )
+            .await?;
+        Ok(key.into())
+    }
+
+    async fn take_login_challenge(
+        &self,
+        token: &LoginChallengeToken,
+    ) -> AppResult<Option<LoginChallenge>> {
+        self.kv
+            .take(&LoginChallengeKey::from(token))
+            .await
+            .map(|x| x.map(LoginChallenge::from))
+    }
+}
+
+impl TwoFactorRepositoryImpl {
+    // 前後のステップも含めてコードを照合し、一度受け付けたステップ以前のコードは拒否する
+    async fn check_totp(
+        &self,
+        conn: &mut PgConnection,
+        user_id: UserId,
+        row: &UserTotpRow,
+        code: &str,
+    ) -> AppResult<bool> {
+        let totp = build_totp(&row.secret, &self.config.issuer, "")?;
+        let now = chrono::Utc::now().timestamp();
+        let current_step = now / TOTP_STEP as i64;
+
+        let matched = (-TOTP_SKEW..=TOTP_SKEW)
+            .map(|offset| current_step + offset)
+            .filter(|step| row.last_used_step.is_none_or(|last| *step > last))
+            .find(|step| totp.check(code, (*step as u64) * TOTP_STEP));
+        let Some(step) = matched else {
+            return Ok(false);
+        };
+
+        // 同時に同じコードが使われた場合も、一方だけが成功するようにする
+        let res = sqlx::query!(
+            r#"
+                UPDATE user_totp SET last_used_step = $2
+                WHERE user_id = $1
+                AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+            user_id as _,
+            step
+        )
+        .execute(conn)
+        .await
+        .map_err(AppError::SpecificOperationError)?;
+
+        Ok(res.rows_affected() > 0)
+    }
+}
+
+pub(crate) fn build_totp(secret: &str, issuer: &str, account_name: &str) -> AppResult<TOTP> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::ConversionEntityError(e.to_string()))?;
+    TOTP::new(
+        Algorithm::SHA1,
+        TOTP_DIGITS,
+        0,
+        TOTP_STEP,
+        secret,
+        Some(issuer.to_string()),
+        account_name.to_string(),
+    )
+    .map_err(|e| AppError::ConversionEntityError(e.to_string()))
+}
+
+async fn find_totp(conn: &mut PgConnection, user_id: UserId) -> AppResult<Option<UserTotpRow>> {
+    sqlx::query_as!(
+        UserTotpRow,
+        r#"
+            SELECT secret, last_used_step, enabled_at
+            FROM user_totp
+            WHERE user_id = $1
+            FOR UPDATE
+        "#,
+        user_id as _
+    )
+    .fetch_optional(conn)
+    .await
+    .map_err(AppError::SpecificOperationError)
+}
+
+fn is_totp_code(code: &str) -> bool {
+    code.len() == TOTP_DIGITS && code.chars().all(|c| c.is_ascii_digit())
+}
+
+// リカバリーコードは "xxxxx-xxxxx" 形式。ハイフンと大文字小文字の違いは無視する
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    hex::encode(Sha256::digest(normalize_recovery_code(code).as_bytes()))
+}
+
+// 既存のリカバリーコードを破棄して新しく発行する
+async fn replace_recovery_codes(
+    conn: &mut PgConnection,
+    user_id: UserId,
+) -> AppResult<Vec<String>> {
+    sqlx::query!(
+        r#"
+            DELETE FROM user_recovery_codes WHERE user_id = $1
+        "#,
+        user_id as _
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(AppError::SpecificOperationError)?;
+
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw = uuid::Uuid::new_v4().simple().to_string();
+            format!("{}-{}", &raw[..5], &raw[5..10])
+        })
+        .collect();
+    let hashes: Vec<String> = codes.iter().map(|c| hash_recovery_code(c)).collect();
+
+    sqlx::query!(
+        r#"
+            INSERT INTO user_recovery_codes (user_id, code_hash)
+            SELECT $1, UNNEST($2::VARCHAR[])
+        "#,
+        user_id as _,
+        &hashes
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(AppError::SpecificOperationError)?;
+
+    Ok(codes)
+}
+
+async fn use_recovery_code(conn: &mut PgConnection, user_id: UserId, code: &str) -> AppResult<bool> {
+    let res = sqlx::query!(
+        r#"
+            UPDATE user_recovery_codes SET used_at = CURRENT_TIMESTAMP(3)
+            WHERE recovery_code_id = (
+                SELECT recovery_code_id FROM user_recovery_codes
+                WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+                LIMIT 1
+            )
+        "#,
+        user_id as _,
+        hash_recovery_code(code)
+    )
+    .execute(conn)
+    .await
+    .map_err(AppError::SpecificOperationError)?;
+
+    Ok(res.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::tenant::default_tenant_id;
+    use crate::password::{policy::PasswordPolicy, PasswordHasher};
+    use crate::repository::user::UserRepositoryImpl;
+    use kernel::{model::user::event::CreateUser, repository::user::UserRepository};
+
+    #[sqlx::test]
+    async fn test_enroll_verify_and_disable_totp(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let user = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        )
+            .create(tenant_id, CreateUser {
+                name: "Test User".into(),
+                email: "test@example.com".into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+
+        let repo = TwoFactorRepositoryImpl::new(
+            ConnectionPool::new(pool),
+            RedisClient::local(),
+            TwoFactorConfig {
+                issuer: "Book API".into(),
+                challenge_ttl: 300,
+            },
+        );
+
+        let enrollment = repo
+            .start_enrollment(StartTotpEnrollment::new(
+                user.user_id,
+                "test@example.com".into(),
+            ))
+            .await?;
+        assert!(enrollment.otpauth_uri.starts_with("otpauth://totp/"));
+        assert!(!repo.is_enabled(user.user_id).await?);
+
+        let res = repo
+            .activate(ActivateTotp::new(user.user_id, None, "000000".into()))
+            .await;
+        assert!(matches!(res, Err(AppError::UnprocessableEntity(_))));
+
+        let code = build_totp(&enrollment.secret, "Book API", "")?.generate_current()?;
+        let RecoveryCodes(recovery_codes) = repo
+            .activate(ActivateTotp::new(user.user_id, None, code.clone()))
+            .await?;
+        assert_eq!(recovery_codes.len(), RECOVERY_CODE_COUNT);
+        assert!(repo.is_enabled(user.user_id).await?);
+
+        // 有効化に使ったコードは再利用できない
+        assert!(!repo.verify_code(user.user_id, &code).await?);
+
+        // リカバリーコードは一度だけ使える
+        let recovery_code = recovery_codes[0].to_uppercase();
+        assert!(repo.verify_code(user.user_id, &recovery_code).await?);
+        assert!(!repo.verify_code(user.user_id, &recovery_code).await?);
+
+        let res = repo
+            .start_enrollment(StartTotpEnrollment::new(
+                user.user_id,
+                "test@example.com".into(),
+            ))
+            .await;
+        assert!(matches!(res, Err(AppError::Conflict(_))));
+
+        repo.disable(DisableTwoFactor::new(user.user_id, user.user_id, None))
+            .await?;
+        assert!(!repo.is_enabled(user.user_id).await?);
+        assert!(!repo.verify_code(user.user_id, &recovery_codes[1]).await?);
+
+        Ok(())
+    }
+}
@@ -1,26 +1,49 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use derive_new::new;
 use kernel::model::{
-    id::UserId,
+    id::{TenantId, UserId},
+    list::PaginatedList,
     user::{
-        User,
-        event::{CreateUser, DeleteUser, UpdateUserPassword, UpdateUserRole},
+        DeletedUser, User, UserListOptions, UserProfile,
+        event::{
+            CreateUser, DeactivateUser, DeleteUser, PatchUser, ReactivateUser, RestoreUser,
+            UpdateProfile, UpdateUserPassword, UpdateUserRole,
+        },
     },
 };
+use kernel::model::audit::{event::CreateAuditLog, AuditAction, AuditTarget};
 use kernel::model::role::Role;
 use kernel::repository::user::UserRepository;
 use shared::error::{AppError, AppResult};
+use std::sync::Arc;
 
-use crate::database::{ConnectionPool, model::user::UserRow};
+use crate::database::{
+    ConnectionPool,
+    model::user::{DeletedUserRow, PaginatedUserRow, UserRow},
+};
+use crate::redis::RedisClient;
+use crate::password::{
+    policy::{PasswordOwner, PasswordPolicy},
+    PasswordHasher,
+};
+use crate::repository::{audit, auth::revoke_all_sessions};
 
 #[derive(new)]
 pub struct UserRepositoryImpl {
     pool: ConnectionPool,
+    kv: Arc<RedisClient>,
+    hasher: PasswordHasher,
+    policy: PasswordPolicy,
 }
 
 #[async_trait]
 impl UserRepository for UserRepositoryImpl {
-    async fn find_current_user(&self, current_user_id: UserId) -> AppResult<Option<User>> {
+    async fn find_current_user(
+        &self,
+        tenant_id: TenantId,
+        current_user_id: UserId,
+    ) -> AppResult<Option<User>> {
         let row = sqlx::query_as!(
             UserRow,
             r#"
@@ -29,13 +52,18 @@ impl UserRepository for UserRepositoryImpl {
                 u.name,
                 u.email,
                 r.name as role_name,
+                u.active,
+                u.version,
                 u.created_at,
                 u.updated_at
             FROM users AS u 
             INNER JOIN roles AS r ON u.role_id = r.role_id
             WHERE u.user_id = $1
+            AND u.tenant_id = $2
+            AND u.deleted_at IS NULL
             "#,
-            current_user_id as _
+            current_user_id as _,
+            tenant_id as _
         )
         .fetch_optional(self.pool.inner_ref())
         .await
@@ -47,85 +75,139 @@ impl UserRepository for UserRepositoryImpl {
         }
     }
 
-    async fn find_all(&self) -> AppResult<Vec<User>> {
-        let users = sqlx::query_as!(
-            UserRow,
+    async fn find_all(
+        &self,
+        tenant_id: TenantId,
+        options: UserListOptions,
+    ) -> AppResult<PaginatedList<User>> {
+        let UserListOptions {
+            keyword,
+            search_email,
+            role,
+            active,
+            limit,
+            offset,
+        } = options;
+        let pattern = keyword.map(|k| format!("%{}%", escape_like(&k)));
+
+        let rows = sqlx::query_as!(
+            PaginatedUserRow,
             r#"
-            SELECT 
+            SELECT
+                COUNT(*) OVER() AS "total!",
                 u.user_id,
                 u.name,
                 u.email,
                 r.name as role_name,
-                u.created_at,
-                u.updated_at
-            FROM users AS u 
+                u.active,
+                u.version
+            FROM users AS u
             INNER JOIN roles AS r USING (role_id)
-            ORDER BY u.created_at DESC;
-            "#
+            WHERE u.tenant_id = $1
+            AND u.deleted_at IS NULL
+            AND ($2::varchar IS NULL OR u.name ILIKE $2 OR ($3 AND u.email ILIKE $2))
+            AND ($4::varchar IS NULL OR r.name = $4)
+            AND ($5::bool IS NULL OR u.active = $5)
+            ORDER BY u.name, u.user_id
+            LIMIT $6 OFFSET $7
+            "#,
+            tenant_id as _,
+            pattern,
+            search_email,
+            role.as_ref().map(AsRef::<str>::as_ref),
+            active,
+            limit,
+            offset,
         )
         .fetch_all(self.pool.inner_ref())
         .await
-        .map_err(AppError::SpecificOperationError)?
-        .into_iter()
-        .filter_map(|r| User::try_from(r).ok())
-        .collect();
+        .map_err(AppError::SpecificOperationError)?;
 
-        Ok(users)
-    }
+        let total = rows.first().map(|row| row.total).unwrap_or(0);
+        let items = rows
+            .into_iter()
+            .map(User::try_from)
+            .collect::<AppResult<Vec<_>>>()?;
 
-    async fn create(&self, event: CreateUser) -> AppResult<User> {
+        Ok(PaginatedList {
+            total,
+            limit,
+            offset,
+            items,
+        })
+    }
 
-        let user_id = UserId::new();
-        let hashed_password = hashed_password(&event.password)?;
-        let role = Role::User;
+    async fn find_profile(
+        &self,
+        tenant_id: TenantId,
+        user_id: UserId,
+    ) -> AppResult<Option<UserProfile>> {
+        let Some(user) = self.find_current_user(tenant_id, user_id).await? else {
+            return Ok(None);
+        };
 
-        let res = sqlx::query!(
+        let counts = sqlx::query!(
             r#"
-            INSERT INTO users(user_id, name, email, password_hash, role_id)
-            SELECT $1, $2, $3, $4, role_id FROM roles WHERE name = $5;
+            SELECT
+                (SELECT COUNT(*) FROM checkouts WHERE user_id = $1) AS "checkout_count!",
+                (SELECT COUNT(*) FROM returned_checkouts WHERE user_id = $1) AS "returned_count!"
             "#,
-            user_id as _,
-            event.name,
-            event.email,
-            hashed_password,
-            role.as_ref()
+            user_id as _
         )
-            .execute(self.pool.inner_ref())
-            .await
-            .map_err(AppError::SpecificOperationError)?;
+        .fetch_one(self.pool.inner_ref())
+        .await
+        .map_err(AppError::SpecificOperationError)?;
 
-        if res.rows_affected() < 1 {
-            return Err(AppError::NoRowsAffectedError(
-                "No user has been created".to_string(),
-            ));
-        }
+        Ok(Some(UserProfile {
+            user,
+            checkout_count: counts.checkout_count,
+            returned_count: counts.returned_count,
+        }))
+    }
 
-        Ok(User {
-            user_id,
-            name: event.name,
-            email: event.email,
-            role,
-        })
+    async fn create(&self, tenant_id: TenantId, event: CreateUser) -> AppResult<User> {
+        let mut tx = self.pool.begin().await?;
+        let user = insert_user(&mut tx, &self.hasher, &self.policy, tenant_id, event, Role::User).await?;
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(user)
     }
 
-    async fn update_password(&self, event: UpdateUserPassword) -> AppResult<()> {
+    async fn update_password(
+        &self,
+        tenant_id: TenantId,
+        event: UpdateUserPassword,
+    ) -> AppResult<()> {
 
         let mut tx = self.pool.begin().await?;
 
-        let original_password_hash = sqlx::query!(
+        let original = sqlx::query!(
             r#"
-            SELECT password_hash FROM users WHERE user_id = $1;
+            SELECT password_hash, name, email FROM users
+            WHERE user_id = $1 AND tenant_id = $2 AND deleted_at IS NULL;
             "#,
-            event.user_id as _
+            event.user_id as _,
+            tenant_id as _
         )
             .fetch_one(&mut *tx)
         .await
-        .map_err(AppError::SpecificOperationError)?
-        .password_hash;
+        .map_err(AppError::SpecificOperationError)?;
+
+        if !self.hasher.verify(&event.current_password, &original.password_hash)? {
+            return Err(AppError::UnauthenticatedError);
+        }
 
-        verify_password(&event.current_password, &original_password_hash)?;
+        let owner = PasswordOwner {
+            user_id: Some(event.user_id),
+            email: &original.email,
+            name: &original.name,
+        };
+        self.policy
+            .validate(&mut tx, &self.hasher, owner, &event.new_password)
+            .await?;
+        let new_password_hash = self.hasher.hash(&event.new_password)?;
 
-        let new_password_hash = hashed_password(&event.new_password)?;
+        let before = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
 
         sqlx::query!(
             r#"
@@ -138,13 +220,40 @@ impl UserRepository for UserRepositoryImpl {
         .await
         .map_err(AppError::SpecificOperationError)?;
 
+        self.policy
+            .remember(&mut tx, event.user_id, &new_password_hash)
+            .await?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.user_id),
+                None,
+                AuditAction::Update,
+                AuditTarget::User,
+                event.user_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
         tx.commit().await.map_err(AppError::TransactionError)?;
 
+        self.revoke_tokens(event.user_id, "password_changed").await;
+
         Ok(())
 
     }
 
-    async fn update_role(&self, event: UpdateUserRole) -> AppResult<()> {
+    async fn update_role(&self, tenant_id: TenantId, event: UpdateUserRole) -> AppResult<()> {
+
+        let mut tx = self.pool.begin().await?;
+
+        self.check_version(&mut tx, tenant_id, event.user_id, event.version).await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
 
         let res = sqlx::query!(
             r#"
@@ -152,11 +261,12 @@ impl UserRepository for UserRepositoryImpl {
             SET role_id = (
                 SELECT role_id FROM roles WHERE name = $1
             ) WHERE user_id = $2
+            AND deleted_at IS NULL
             "#,
             event.role.as_ref(),
             event.user_id as _,
         )
-            .execute(self.pool.inner_ref())
+            .execute(&mut *tx)
             .await
             .map_err(AppError::SpecificOperationError)?;
 
@@ -166,39 +276,1200 @@ impl UserRepository for UserRepositoryImpl {
             ))
         }
 
+        let after = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::User,
+                event.user_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        self.revoke_tokens(event.user_id, "role_changed").await;
+
         Ok(())
     }
 
-    async fn delete(&self, event: DeleteUser) -> AppResult<()> {
+    async fn update_profile(&self, tenant_id: TenantId, event: UpdateProfile) -> AppResult<User> {
+        self.patch(tenant_id, PatchUser {
+            user_id: event.user_id,
+            name: Some(event.name),
+            email: Some(event.email),
+            version: event.version,
+            impersonator: event.impersonator,
+        })
+        .await
+    }
 
-        let res = sqlx::query!(
+    async fn patch(&self, tenant_id: TenantId, event: PatchUser) -> AppResult<User> {
+
+        let mut tx = self.pool.begin().await?;
+
+        self.check_version(&mut tx, tenant_id, event.user_id, event.version).await?;
+
+        if let Some(email) = &event.email {
+            let email_in_use = sqlx::query_scalar!(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM users
+                    WHERE tenant_id = $3 AND email = $1 AND user_id <> $2 AND deleted_at IS NULL
+                ) AS "exists!";
+                "#,
+                email,
+                event.user_id as _,
+                tenant_id as _
+            )
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(AppError::SpecificOperationError)?;
+
+            if email_in_use {
+                return Err(AppError::Conflict(format!(
+                    "Email {} is already used by another user",
+                    email
+                )));
+            }
+        }
+
+        let before = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
+
+        // メールアドレスが変わった場合は確認済みの状態を取り消す
+        sqlx::query!(
             r#"
-            DELETE FROM users WHERE user_id = $1;
+            UPDATE users
+            SET
+                name = COALESCE($1, name),
+                email = COALESCE($2, email),
+                email_verified_at = CASE
+                    WHEN $2 IS NULL OR $2 = email THEN email_verified_at
+                END
+            WHERE user_id = $3
             "#,
+            event.name,
+            event.email,
             event.user_id as _
         )
-        .execute(self.pool.inner_ref())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| email_conflict(e, event.email.as_deref().unwrap_or_default()))?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.user_id),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::User,
+                event.user_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        self.find_current_user(tenant_id, event.user_id)
+            .await?
+            .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))
+    }
+
+    async fn delete(&self, tenant_id: TenantId, event: DeleteUser) -> AppResult<()> {
+
+        let mut tx = self.pool.begin().await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
+
+        let deleted_at = sqlx::query_scalar!(
+            r#"
+            UPDATE users SET deleted_at = CURRENT_TIMESTAMP(3)
+            WHERE user_id = $1 AND tenant_id = $2 AND deleted_at IS NULL
+            RETURNING deleted_at AS "deleted_at!";
+            "#,
+            event.user_id as _,
+            tenant_id as _
+        )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?
+            .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+
+        // 所有する蔵書も同じ時刻で論理削除し、復元時にまとめて戻せるようにする
+        sqlx::query!(
+            r#"
+            UPDATE books SET deleted_at = $2
+            WHERE user_id = $1 AND deleted_at IS NULL;
+            "#,
+            event.user_id as _,
+            deleted_at
+        )
+            .execute(&mut *tx)
             .await
             .map_err(AppError::SpecificOperationError)?;
 
-        if res.rows_affected() < 1 {
-            return Err(AppError::EntityNotFound(
-                "Specified user does not exist".into(),
-            ));
+        // 本人の貸出と、本人の蔵書に対する貸出は返却済みとして履歴に残す
+        sqlx::query!(
+            r#"
+            WITH returned AS (
+                DELETE FROM checkouts
+                WHERE user_id = $1
+                OR book_id IN (SELECT book_id FROM books WHERE user_id = $1)
+                RETURNING checkout_id, book_id, user_id, checked_out_at, tenant_id
+            )
+            INSERT INTO returned_checkouts (
+            checkout_id, book_id, user_id, checked_out_at, returned_at, tenant_id)
+            SELECT checkout_id, book_id, user_id, checked_out_at, $2, tenant_id
+            FROM returned;
+            "#,
+            event.user_id as _,
+            deleted_at
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM book_transfers WHERE from_user_id = $1 OR to_user_id = $1;
+            "#,
+            event.user_id as _
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Delete,
+                AuditTarget::User,
+                event.user_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        self.revoke_tokens(event.user_id, "user_deleted").await;
+
+        Ok(())
+    }
+
+    async fn find_all_deleted(&self, tenant_id: TenantId) -> AppResult<Vec<DeletedUser>> {
+        sqlx::query_as!(
+            DeletedUserRow,
+            r#"
+            SELECT
+                user_id,
+                name,
+                email,
+                deleted_at AS "deleted_at!"
+            FROM users
+            WHERE tenant_id = $1 AND deleted_at IS NOT NULL
+            ORDER BY deleted_at DESC;
+            "#,
+            tenant_id as _
+        )
+            .fetch_all(self.pool.inner_ref())
+            .await
+            .map(|rows| rows.into_iter().map(DeletedUser::from).collect())
+            .map_err(AppError::SpecificOperationError)
+    }
+
+    async fn restore(&self, tenant_id: TenantId, event: RestoreUser) -> AppResult<()> {
+
+        let mut tx = self.pool.begin().await?;
+
+        let before = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
+
+        let deleted = sqlx::query!(
+            r#"
+            SELECT email, deleted_at AS "deleted_at!"
+            FROM users
+            WHERE user_id = $1 AND tenant_id = $2 AND deleted_at IS NOT NULL
+            FOR UPDATE;
+            "#,
+            event.user_id as _,
+            tenant_id as _
+        )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?
+            .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+
+        let email_in_use = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM users WHERE tenant_id = $2 AND email = $1 AND deleted_at IS NULL
+            ) AS "exists!";
+            "#,
+            deleted.email,
+            tenant_id as _
+        )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+        if email_in_use {
+            return Err(AppError::Conflict(format!(
+                "Email {} is already used by another user",
+                deleted.email
+            )));
         }
 
+        sqlx::query!(
+            r#"
+            UPDATE users SET deleted_at = NULL WHERE user_id = $1;
+            "#,
+            event.user_id as _
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE books SET deleted_at = NULL
+            WHERE user_id = $1 AND deleted_at = $2;
+            "#,
+            event.user_id as _,
+            deleted.deleted_at
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::User,
+                event.user_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
         Ok(())
     }
+
+    async fn deactivate(&self, tenant_id: TenantId, event: DeactivateUser) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let active = sqlx::query_scalar!(
+            r#"
+            SELECT active FROM users
+            WHERE user_id = $1 AND tenant_id = $2 AND deleted_at IS NULL
+            FOR UPDATE;
+            "#,
+            event.user_id as _,
+            tenant_id as _
+        )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?
+            .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+        if !active {
+            return Ok(());
+        }
+
+        let before = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE users SET active = FALSE, deactivated_at = CURRENT_TIMESTAMP(3)
+            WHERE user_id = $1;
+            "#,
+            event.user_id as _
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+        // 削除と異なり、蔵書と貸出はそのまま残す（借りている本は管理者が強制返却できる）。
+        // 本人が応じられなくなる譲渡の申し出だけを取り消す
+        sqlx::query!(
+            r#"
+            DELETE FROM book_transfers WHERE from_user_id = $1 OR to_user_id = $1;
+            "#,
+            event.user_id as _
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::User,
+                event.user_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        self.revoke_tokens(event.user_id, "user_deactivated").await;
+
+        Ok(())
+    }
+
+    async fn reactivate(&self, tenant_id: TenantId, event: ReactivateUser) -> AppResult<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let active = sqlx::query_scalar!(
+            r#"
+            SELECT active FROM users
+            WHERE user_id = $1 AND tenant_id = $2 AND deleted_at IS NULL
+            FOR UPDATE;
+            "#,
+            event.user_id as _,
+            tenant_id as _
+        )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?
+            .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+        if active {
+            return Ok(());
+        }
+
+        let before = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE users SET active = TRUE, deactivated_at = NULL
+            WHERE user_id = $1;
+            "#,
+            event.user_id as _
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+        let after = audit::snapshot(&mut tx, AuditTarget::User, event.user_id.raw()).await?;
+        audit::record(
+            &mut tx,
+            CreateAuditLog::new(
+                Some(event.requested_user),
+                event.impersonator,
+                AuditAction::Update,
+                AuditTarget::User,
+                event.user_id.raw(),
+                before,
+                after,
+            ),
+        )
+        .await?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> AppResult<u64> {
+
+        let mut tx = self.pool.begin().await?;
+
+        // 蔵書はユーザーの削除に連動して消えるため、先に監査ログを残して削除しておく
+        sqlx::query!(
+            r#"
+            WITH purged AS (
+                DELETE FROM books AS b
+                WHERE b.user_id IN (SELECT user_id FROM users WHERE deleted_at < $1)
+                RETURNING b.book_id, b.tenant_id, to_jsonb(b) AS before
+            )
+            INSERT INTO audit_logs (tenant_id, actor_id, action, target_type, target_id, before, after)
+            SELECT tenant_id, NULL, 'Delete', 'Book', book_id, before, NULL
+            FROM purged;
+            "#,
+            deleted_before
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+        let res = sqlx::query!(
+            r#"
+            WITH purged AS (
+                DELETE FROM users AS u WHERE u.deleted_at < $1
+                RETURNING u.user_id, u.tenant_id, to_jsonb(u) - 'password_hash' AS before
+            )
+            INSERT INTO audit_logs (tenant_id, actor_id, action, target_type, target_id, before, after)
+            SELECT tenant_id, NULL, 'Delete', 'User', user_id, before, NULL
+            FROM purged;
+            "#,
+            deleted_before
+        )
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?;
+
+        tx.commit().await.map_err(AppError::TransactionError)?;
+
+        Ok(res.rows_affected())
+    }
 }
 
-fn hashed_password(password: &str) -> AppResult<String> {
-    bcrypt::hash(password, bcrypt::DEFAULT_COST).map_err(AppError::from)
+impl UserRepositoryImpl {
+    // 発行済みのトークンをすべて無効にする。DB の変更は確定済みのため、失敗してもエラーにはしない
+    // （Redis に接続できない間はトークンの検証自体も失敗するので、古いトークンが使われることはない）
+    async fn revoke_tokens(&self, user_id: UserId, reason: &str) {
+        match revoke_all_sessions(&self.kv, user_id).await {
+            Ok(revoked) => tracing::info!(%user_id, revoked, reason, "Revoked user tokens"),
+            Err(e) => tracing::error!(
+                %user_id, reason, error.message = %e, "Failed to revoke user tokens"
+            ),
+        }
+    }
+
+    // 更新対象の行をロックしたうえで、クライアントが参照したバージョンと一致するか確認する
+    async fn check_version(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        tenant_id: TenantId,
+        user_id: UserId,
+        expected: i64,
+    ) -> AppResult<()> {
+        let current = sqlx::query_scalar!(
+            r#"
+            SELECT version FROM users
+            WHERE user_id = $1
+            AND tenant_id = $2
+            AND deleted_at IS NULL
+            FOR UPDATE
+            "#,
+            user_id as _,
+            tenant_id as _,
+        )
+            .fetch_optional(&mut **tx)
+            .await
+            .map_err(AppError::SpecificOperationError)?
+            .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+
+        if current != expected {
+            return Err(AppError::PreconditionFailed(format!(
+                "User with id {} has been modified (current version: {}, expected: {})",
+                user_id, current, expected
+            )));
+        }
+
+        Ok(())
+    }
 }
 
-fn verify_password(password: &str, hashed_password: &str) -> AppResult<()> {
-    let valid = bcrypt:: verify(password, hashed_password)?;
-    if !valid {
-        return Err(AppError::UnauthenticatedError);
+/// 利用者を登録する。テナントの作成時に最初の管理者を登録するためにも使う
+pub(crate) async fn insert_user(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    hasher: &PasswordHasher,
+    policy: &PasswordPolicy,
+    tenant_id: TenantId,
+    event: CreateUser,
+    role: Role,
+) -> AppResult<User> {
+    let user_id = UserId::new();
+
+    let owner = PasswordOwner {
+        user_id: None,
+        email: &event.email,
+        name: &event.name,
+    };
+    policy.validate(tx, hasher, owner, &event.password).await?;
+    let hashed_password = hasher.hash(&event.password)?;
+
+    let email_in_use = sqlx::query_scalar!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM users WHERE tenant_id = $2 AND email = $1 AND deleted_at IS NULL
+        ) AS "exists!";
+        "#,
+        event.email,
+        tenant_id as _
+    )
+    .fetch_one(&mut **tx)
+    .await
+    .map_err(AppError::SpecificOperationError)?;
+
+    if email_in_use {
+        return Err(AppError::Conflict(format!(
+            "Email {} is already used by another user",
+            event.email
+        )));
+    }
+
+    let res = sqlx::query!(
+        r#"
+        INSERT INTO users(user_id, tenant_id, name, email, password_hash, role_id, email_verified_at)
+        SELECT $1, $7, $2, $3, $4, role_id, CASE WHEN $6 THEN CURRENT_TIMESTAMP(3) END
+        FROM roles WHERE name = $5;
+        "#,
+        user_id as _,
+        event.name,
+        event.email,
+        hashed_password,
+        role.as_ref(),
+        event.email_verified,
+        tenant_id as _
+    )
+    .execute(&mut **tx)
+    .await
+    .map_err(|e| email_conflict(e, &event.email))?;
+
+    if res.rows_affected() < 1 {
+        return Err(AppError::NoRowsAffectedError(
+            "No user has been created".to_string(),
+        ));
+    }
+
+    policy.remember(tx, user_id, &hashed_password).await?;
+
+    let after = audit::snapshot(tx, AuditTarget::User, user_id.raw()).await?;
+    audit::record(
+        tx,
+        CreateAuditLog::new(
+            event.requested_user,
+            event.impersonator,
+            AuditAction::Create,
+            AuditTarget::User,
+            user_id.raw(),
+            None,
+            after,
+        ),
+    )
+    .await?;
+
+    Ok(User {
+        user_id,
+        name: event.name,
+        email: event.email,
+        role,
+        active: true,
+        version: 1,
+    })
+}
+
+// LIKE のパターンとして解釈されないよう、ワイルドカードをエスケープする
+fn escape_like(keyword: &str) -> String {
+    keyword
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+// 同時に同じメールアドレスで登録・変更された場合、一意制約違反を 409 として扱う
+pub(crate) fn email_conflict(e: sqlx::Error, email: &str) -> AppError {
+    let is_conflict = e
+        .as_database_error()
+        .and_then(|e| e.constraint())
+        .is_some_and(|c| c == "users_email_active_key");
+    if is_conflict {
+        AppError::Conflict(format!("Email {} is already used by another user", email))
+    } else {
+        AppError::SpecificOperationError(e)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::tenant::default_tenant_id;
+    use crate::repository::{book::BookRepositoryImpl, checkout::CheckoutRepositoryImpl};
+    use shared::error::PasswordRule;
+    use kernel::model::book::{BookListOptions, event::CreateBook};
+    use kernel::model::checkout::event::{CreateCheckout, UpdateReturned};
+    use kernel::repository::{book::BookRepository, checkout::CheckoutRepository};
+
+    #[sqlx::test]
+    async fn test_soft_delete_and_restore_user(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let user = repo
+            .create(tenant_id, CreateUser {
+                name: "Test User".into(),
+                email: "test@example.com".into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+
+        let book_repo = BookRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        book_repo
+            .create(
+                tenant_id,
+                CreateBook {
+                    title: "Test Title".into(),
+                    author: "Test Author".into(),
+                    isbn: "Test ISBN".into(),
+                    description: "Test Description".into(),
+                    group_id: None,
+                    impersonator: None,
+                },
+                user.user_id,
+            )
+            .await?;
+        let options = || BookListOptions {
+            limit: 10,
+            offset: 0,
+        };
+
+        repo.delete(tenant_id, DeleteUser {
+            user_id: user.user_id,
+            requested_user: user.user_id,
+            impersonator: None,
+        })
+        .await?;
+
+        assert!(repo.find_current_user(tenant_id, user.user_id).await?.is_none());
+        assert_eq!(repo.find_all_deleted(tenant_id).await?.len(), 1);
+        assert!(book_repo.find_all(tenant_id, options()).await?.items.is_empty());
+
+        repo.restore(tenant_id, RestoreUser {
+            user_id: user.user_id,
+            requested_user: user.user_id,
+            impersonator: None,
+        })
+        .await?;
+
+        assert!(repo.find_current_user(tenant_id, user.user_id).await?.is_some());
+        assert!(repo.find_all_deleted(tenant_id).await?.is_empty());
+        assert_eq!(book_repo.find_all(tenant_id, options()).await?.items.len(), 1);
+
+        repo.delete(tenant_id, DeleteUser {
+            user_id: user.user_id,
+            requested_user: user.user_id,
+            impersonator: None,
+        })
+        .await?;
+        assert_eq!(repo.purge_deleted(Utc::now()).await?, 1);
+        assert!(repo.find_all_deleted(tenant_id).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_deactivate_and_reactivate_user(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let create = |email: &str| CreateUser {
+            name: "Test User".into(),
+            email: email.into(),
+            password: "test_password".into(),
+            requested_user: None,
+            impersonator: None,
+            email_verified: true,
+        };
+        let owner = repo.create(tenant_id, create("owner@example.com")).await?;
+        let borrower = repo.create(tenant_id, create("borrower@example.com")).await?;
+
+        let book_repo = BookRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        book_repo
+            .create(
+                tenant_id,
+                CreateBook {
+                    title: "Test Title".into(),
+                    author: "Test Author".into(),
+                    isbn: "Test ISBN".into(),
+                    description: "Test Description".into(),
+                    group_id: None,
+                    impersonator: None,
+                },
+                owner.user_id,
+            )
+            .await?;
+        let book_id = book_repo
+            .find_all(tenant_id, BookListOptions {
+                limit: 10,
+                offset: 0,
+            })
+            .await?
+            .items[0]
+            .book_id;
+
+        repo.deactivate(tenant_id, DeactivateUser {
+            user_id: owner.user_id,
+            requested_user: borrower.user_id,
+            impersonator: None,
+        })
+        .await?;
+
+        // 削除と異なり、利用者も蔵書も残る
+        let deactivated = repo.find_current_user(tenant_id, owner.user_id).await?;
+        assert_eq!(deactivated.map(|u| u.active), Some(false));
+        assert!(book_repo.find_by_id(tenant_id, book_id).await?.is_some());
+
+        let checkout_repo = CheckoutRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        let checkout = || CreateCheckout::new(book_id, borrower.user_id, None, Utc::now());
+        let res = checkout_repo.create(tenant_id, checkout()).await;
+        assert!(matches!(res, Err(AppError::UnprocessableEntity(_))));
+
+        repo.reactivate(tenant_id, ReactivateUser {
+            user_id: owner.user_id,
+            requested_user: borrower.user_id,
+            impersonator: None,
+        })
+        .await?;
+
+        let reactivated = repo.find_current_user(tenant_id, owner.user_id).await?;
+        assert_eq!(reactivated.map(|u| u.active), Some(true));
+        checkout_repo.create(tenant_id, checkout()).await?;
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_find_all_users(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let create = |name: &str, email: &str| CreateUser {
+            name: name.into(),
+            email: email.into(),
+            password: "test_password".into(),
+            requested_user: None,
+            impersonator: None,
+            email_verified: true,
+        };
+        let alice = repo.create(tenant_id, create("Alice", "alice@example.com")).await?;
+        let bob = repo.create(tenant_id, create("Bob", "bob@library.example.com")).await?;
+        repo.create(tenant_id, create("Carol_1", "carol@example.com")).await?;
+        repo.update_role(tenant_id, UpdateUserRole {
+            user_id: alice.user_id,
+            role: Role::Admin,
+            requested_user: alice.user_id,
+            impersonator: None,
+            version: 1,
+        })
+        .await?;
+        repo.deactivate(tenant_id, DeactivateUser {
+            user_id: bob.user_id,
+            requested_user: alice.user_id,
+            impersonator: None,
+        })
+        .await?;
+
+        let options = || UserListOptions {
+            keyword: None,
+            search_email: false,
+            role: None,
+            active: None,
+            limit: 2,
+            offset: 0,
+        };
+        let names = |list: PaginatedList<User>| {
+            list.items.into_iter().map(|u| u.name).collect::<Vec<_>>()
+        };
+
+        // 名前順に並べてページ分割する
+        let page = repo.find_all(tenant_id, options()).await?;
+        assert_eq!(page.total, 3);
+        assert_eq!(names(page), ["Alice", "Bob"]);
+        let page = repo.find_all(tenant_id, UserListOptions { offset: 2, ..options() }).await?;
+        assert_eq!(names(page), ["Carol_1"]);
+
+        // メールアドレスは search_email の場合だけ検索対象にする
+        let keyword = || Some("library".to_string());
+        let page = repo
+            .find_all(tenant_id, UserListOptions { keyword: keyword(), ..options() })
+            .await?;
+        assert_eq!(page.total, 0);
+        let page = repo
+            .find_all(tenant_id, UserListOptions {
+                keyword: keyword(),
+                search_email: true,
+                ..options()
+            })
+            .await?;
+        assert_eq!(names(page), ["Bob"]);
+
+        // ワイルドカードは文字としてそのまま扱う
+        let page = repo
+            .find_all(tenant_id, UserListOptions { keyword: Some("_".into()), ..options() })
+            .await?;
+        assert_eq!(names(page), ["Carol_1"]);
+
+        let page = repo
+            .find_all(tenant_id, UserListOptions { role: Some(Role::Admin), ..options() })
+            .await?;
+        assert_eq!(names(page), ["Alice"]);
+        let page = repo
+            .find_all(tenant_id, UserListOptions { active: Some(false), ..options() })
+            .await?;
+        assert_eq!(names(page), ["Bob"]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_find_profile(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let user = repo
+            .create(tenant_id, CreateUser {
+                name: "Test User".into(),
+                email: "test@example.com".into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+
+        let book_repo = BookRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        for title in ["First", "Second"] {
+            book_repo
+                .create(
+                    tenant_id,
+                    CreateBook {
+                        title: title.into(),
+                        author: "Test Author".into(),
+                        isbn: "Test ISBN".into(),
+                        description: "Test Description".into(),
+                        group_id: None,
+                        impersonator: None,
+                    },
+                    user.user_id,
+                )
+                .await?;
+        }
+        let book_ids = book_repo
+            .find_all(tenant_id, BookListOptions {
+                limit: 10,
+                offset: 0,
+            })
+            .await?
+            .items
+            .into_iter()
+            .map(|b| b.book_id)
+            .collect::<Vec<_>>();
+
+        let checkout_repo = CheckoutRepositoryImpl::new(ConnectionPool::new(pool.clone()));
+        for book_id in &book_ids {
+            checkout_repo
+                .create(tenant_id, CreateCheckout::new(*book_id, user.user_id, None, Utc::now()))
+                .await?;
+        }
+        let checkout = &checkout_repo
+            .find_unreturned_by_user_id(tenant_id, user.user_id)
+            .await?[0];
+        checkout_repo
+            .update_returned(
+                tenant_id,
+                UpdateReturned::new(
+                    checkout.checkout_id,
+                    checkout.book.book_id,
+                    user.user_id,
+                    None,
+                    Utc::now(),
+                    false,
+                ),
+            )
+            .await?;
+
+        let profile = repo.find_profile(tenant_id, user.user_id).await?.unwrap();
+        assert_eq!(profile.user.user_id, user.user_id);
+        assert_eq!(profile.checkout_count, 1);
+        assert_eq!(profile.returned_count, 1);
+        assert!(repo.find_profile(tenant_id, UserId::new()).await?.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_update_profile(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let user = repo
+            .create(tenant_id, CreateUser {
+                name: "Test User".into(),
+                email: "test@example.com".into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+        repo.create(tenant_id, CreateUser {
+            name: "Other User".into(),
+            email: "other@example.com".into(),
+            password: "test_password".into(),
+            requested_user: None,
+            impersonator: None,
+            email_verified: true,
+        })
+        .await?;
+
+        let res = repo
+            .update_profile(tenant_id, UpdateProfile {
+                user_id: user.user_id,
+                impersonator: None,
+                name: "Renamed".into(),
+                email: "other@example.com".into(),
+                version: user.version,
+            })
+            .await;
+        assert!(matches!(res, Err(AppError::Conflict(_))));
+
+        let verified = |user_id: UserId| {
+            sqlx::query_scalar!(
+                r#"SELECT email_verified_at IS NOT NULL AS "verified!" FROM users WHERE user_id = $1"#,
+                user_id as _
+            )
+            .fetch_one(&pool)
+        };
+
+        // 名前だけの変更では確認済みのまま
+        let user = repo
+            .update_profile(tenant_id, UpdateProfile {
+                user_id: user.user_id,
+                impersonator: None,
+                name: "Renamed".into(),
+                email: "test@example.com".into(),
+                version: user.version,
+            })
+            .await?;
+        assert_eq!(user.name, "Renamed");
+        assert!(verified(user.user_id).await?);
+
+        let user = repo
+            .update_profile(tenant_id, UpdateProfile {
+                user_id: user.user_id,
+                impersonator: None,
+                name: "Renamed".into(),
+                email: "new@example.com".into(),
+                version: user.version,
+            })
+            .await?;
+        assert_eq!(user.email, "new@example.com");
+        assert!(!verified(user.user_id).await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_version_tracks_editable_columns(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::local(),
+        );
+        let create = |email: &str| CreateUser {
+            name: "Test User".into(),
+            email: email.into(),
+            password: "test_password".into(),
+            requested_user: None,
+            impersonator: None,
+            email_verified: false,
+        };
+        let admin = repo.create(tenant_id, create("admin@example.com")).await?;
+        let user = repo.create(tenant_id, create("test@example.com")).await?;
+        let version = |user_id: UserId| {
+            sqlx::query_scalar!("SELECT version FROM users WHERE user_id = $1", user_id as _)
+                .fetch_one(&pool)
+        };
+
+        // 確認日時や無効化の記録ではバージョンを上げない
+        sqlx::query!(
+            "UPDATE users SET email_verified_at = CURRENT_TIMESTAMP(3) WHERE user_id = $1",
+            user.user_id as _
+        )
+        .execute(&pool)
+        .await?;
+        repo.deactivate(tenant_id, DeactivateUser {
+            user_id: user.user_id,
+            requested_user: admin.user_id,
+            impersonator: None,
+        })
+        .await?;
+        repo.reactivate(tenant_id, ReactivateUser {
+            user_id: user.user_id,
+            requested_user: admin.user_id,
+            impersonator: None,
+        })
+        .await?;
+        assert_eq!(version(user.user_id).await?, 1);
+
+        // クライアントが変更できる列が変われば上げる
+        repo.update_role(tenant_id, UpdateUserRole {
+            user_id: user.user_id,
+            role: Role::Admin,
+            requested_user: admin.user_id,
+            impersonator: None,
+            version: 1,
+        })
+        .await?;
+        assert_eq!(version(user.user_id).await?, 2);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_password_policy_and_reuse(pool: sqlx::PgPool) -> anyhow::Result<()> {
+        let tenant_id = default_tenant_id(&pool).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO roles(name) VALUES ('Admin'), ('User') ON CONFLICT DO NOTHING;
+            "#
+        )
+        .execute(&pool)
+        .await?;
+
+        let repo = UserRepositoryImpl::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            PasswordHasher::local(),
+            PasswordPolicy::strict(),
+        );
+        let res = repo
+            .create(tenant_id, CreateUser {
+                name: "Test User".into(),
+                email: "reader@example.com".into(),
+                password: "password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await;
+        let Err(AppError::PasswordPolicyViolation(violations)) = res else {
+            panic!("weak password must be rejected");
+        };
+        assert_eq!(violations.len(), 3);
+
+        let user = repo
+            .create(tenant_id, CreateUser {
+                name: "Test User".into(),
+                email: "reader@example.com".into(),
+                password: "Correct-Horse-42".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+        let change = |current: &str, new: &str| UpdateUserPassword {
+            user_id: user.user_id,
+            current_password: current.into(),
+            new_password: new.into(),
+        };
+
+        repo.update_password(tenant_id, change("Correct-Horse-42", "Battery-Staple-7"))
+            .await?;
+        // 直近 2 件のパスワードは再利用できない
+        let res = repo
+            .update_password(tenant_id, change("Battery-Staple-7", "Correct-Horse-42"))
+            .await;
+        assert!(matches!(
+            res,
+            Err(AppError::PasswordPolicyViolation(v)) if v[0].rule == PasswordRule::Reuse
+        ));
+
+        // 履歴から外れたパスワードは再び使える
+        repo.update_password(tenant_id, change("Battery-Staple-7", "Purple-Monkey-99"))
+            .await?;
+        repo.update_password(tenant_id, change("Purple-Monkey-99", "Correct-Horse-42"))
+            .await?;
+
+        Ok(())
     }
-    Ok(())
 }
@@ -1,22 +1,69 @@
 use axum::http::header;
-use axum::{extract::FromRequestParts, http::request::Parts};
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::request::Parts,
+};
+use std::{collections::HashSet, marker::PhantomData, net::SocketAddr, ops::Deref};
 
-use kernel::model::{auth::AccessToken, id::UserId, role::Role, user::User};
+use kernel::model::{
+    api_key::{ApiKeyScope, AuthorizedApiKey},
+    auth::{AccessToken, ClientInfo, TokenSubject},
+    id::{TenantId, UserId},
+    role::Permission,
+    tenant::Tenant,
+    user::User,
+};
 use shared::error::AppError;
 
 use registry::AppRegistry;
+// リクエストの認証に使われた資格情報
+pub enum Credential {
+    AccessToken(AccessToken),
+    ApiKey(AuthorizedApiKey),
+}
+
 pub struct AuthorizedUser {
-    pub access_token: AccessToken,
+    pub credential: Credential,
+    // リクエストが対象とするテナント。利用者は必ずこのテナントに所属している
+    pub tenant_id: TenantId,
     pub user: User,
+    // 管理者が代理ログインしている場合は、実際に操作している管理者
+    pub impersonator: Option<User>,
+    // 利用者のロールに付与された権限
+    pub permissions: HashSet<Permission>,
 }
 
 impl AuthorizedUser {
+    pub fn is_api_key(&self) -> bool {
+        matches!(self.credential, Credential::ApiKey(_))
+    }
+
     pub fn user_id(&self) -> UserId {
         self.user.user_id
     }
 
-    pub fn is_admin(&self) -> bool {
-        self.user.role == Role::Admin
+    pub fn is_impersonating(&self) -> bool {
+        self.impersonator.is_some()
+    }
+
+    // 代理ログイン中の場合は管理者。監査ログに残すためイベントに渡す
+    pub fn impersonator_id(&self) -> Option<UserId> {
+        self.impersonator.as_ref().map(|admin| admin.user_id)
+    }
+
+    // 実際に操作している利用者。代理ログイン中は管理者を返す
+    pub fn actor_id(&self) -> UserId {
+        self.impersonator
+            .as_ref()
+            .map_or(self.user.user_id, |admin| admin.user_id)
+    }
+
+    pub fn tenant_id(&self) -> TenantId {
+        self.tenant_id
+    }
+
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
     }
 }
 
@@ -26,36 +73,291 @@ impl FromRequestParts<AppRegistry> for AuthorizedUser {
         parts: &mut Parts,
         registry: &AppRegistry,
     ) -> Result<Self, Self::Rejection> {
-        // Authorization: Bearer <token> を手動で取り出す（ボディには触らない）
-        let auth = parts
-            .headers
-            .get(header::AUTHORIZATION)
-            .and_then(|h| h.to_str().ok())
-            .ok_or(AppError::UnauthorizedError)?;
-
-        // "Bearer " フレックスを確認
-        let token_str = auth
-            .strip_prefix("Bearer ")
-            .ok_or(AppError::UnauthorizedError)?;
-
-        let access_token = AccessToken(token_str.to_string());
-
-        let user_id = registry
-            .auth_repository()
-            .fetch_user_id_from_token(&access_token)
-            .await?
-            .ok_or(AppError::UnauthenticatedError)?;
+        let CurrentTenant(tenant) = CurrentTenant::from_request_parts(parts, registry).await?;
+        let (subject, credential) = match parts.headers.get(API_KEY_HEADER) {
+            Some(key) => authorize_api_key(parts, registry, key).await?,
+            None => authorize_bearer(parts, registry).await?,
+        };
 
+        // 別のテナントの利用者のトークンは、このテナントでは認証済みとして扱わない
         let user = match registry
             .user_repository()
-            .find_current_user(user_id)
+            .find_current_user(tenant.tenant_id, subject.user_id)
             .await?
         {
             Some(user) => user,
             None => return Err(AppError::UnauthenticatedError),
         };
          //   .ok_or(AppError::UnauthenticatedError)?;
+        // 無効化の前に発行された API キーなども、ここで拒否する
+        if !user.active {
+            return Err(AppError::AccountDeactivated);
+        }
+
+        let impersonator = match subject.impersonator {
+            Some(admin_id) => {
+                let admin = authorize_impersonator(registry, tenant.tenant_id, admin_id).await?;
+                tracing::info!(
+                    impersonator_id = %admin.user_id,
+                    user_id = %user.user_id,
+                    method = %parts.method,
+                    path = %parts.uri.path(),
+                    "Impersonated request"
+                );
+                Some(admin)
+            }
+            None => None,
+        };
+
+        let permissions = registry
+            .role_repository()
+            .find_permissions(user.role)
+            .await?
+            .into_iter()
+            .collect();
+
+        Ok(Self {
+            credential,
+            tenant_id: tenant.tenant_id,
+            user,
+            impersonator,
+            permissions,
+        })
+    }
+}
+
+// ルートが要求する権限を型で表す
+pub trait RequiredPermission {
+    const PERMISSION: Permission;
+}
+
+macro_rules! required_permissions {
+    ($($name:ident),*) => {
+        pub mod permission {
+            use kernel::model::role::Permission;
+
+            $(
+                pub struct $name;
+
+                impl super::RequiredPermission for $name {
+                    const PERMISSION: Permission = Permission::$name;
+                }
+            )*
+        }
+    };
+}
+
+required_permissions!(BookWriteAny, UserManage, CheckoutForceReturn, AuditRead, TenantManage);
+
+// 認証したうえで権限 P を持つことを確認する。持っていなければ 403 を返す
+pub struct RequirePermission<P> {
+    user: AuthorizedUser,
+    _permission: PhantomData<P>,
+}
+
+impl<P> Deref for RequirePermission<P> {
+    type Target = AuthorizedUser;
+    fn deref(&self) -> &Self::Target {
+        &self.user
+    }
+}
+
+impl<P: RequiredPermission + Send> FromRequestParts<AppRegistry> for RequirePermission<P> {
+    type Rejection = AppError;
+    async fn from_request_parts(
+        parts: &mut Parts,
+        registry: &AppRegistry,
+    ) -> Result<Self, Self::Rejection> {
+        let user = AuthorizedUser::from_request_parts(parts, registry).await?;
+        if !user.has_permission(P::PERMISSION) {
+            return Err(AppError::ForbiddenOperationError);
+        }
+        Ok(Self {
+            user,
+            _permission: PhantomData,
+        })
+    }
+}
+
+const API_KEY_HEADER: &str = "x-api-key";
+const TENANT_HEADER: &str = "x-tenant";
+
+// リクエストが対象とするテナント。X-Tenant ヘッダー、サブドメイン、既定のテナントの順に決める
+#[derive(Clone)]
+pub struct CurrentTenant(pub Tenant);
+
+impl FromRequestParts<AppRegistry> for CurrentTenant {
+    type Rejection = AppError;
+    async fn from_request_parts(
+        parts: &mut Parts,
+        registry: &AppRegistry,
+    ) -> Result<Self, Self::Rejection> {
+        // 認証と権限の確認で何度も参照されるため、一度決めたテナントはリクエストに保持しておく
+        if let Some(tenant) = parts.extensions.get::<CurrentTenant>() {
+            return Ok(tenant.clone());
+        }
+
+        let config = registry.tenant_config();
+        let host = parts
+            .uri
+            .host()
+            .or_else(|| parts.headers.get(header::HOST).and_then(|h| h.to_str().ok()));
+        let slug = parts
+            .headers
+            .get(TENANT_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .map(|v| v.trim().to_lowercase())
+            .or_else(|| host.and_then(|host| config.slug_from_host(host)))
+            .or_else(|| config.default_slug.clone())
+            .ok_or_else(|| AppError::EntityNotFound("Tenant is not specified".into()))?;
+
+        let tenant = registry
+            .tenant_repository()
+            .find_by_slug(&slug)
+            .await?
+            .map(Self)
+            .ok_or_else(|| AppError::EntityNotFound(format!("Tenant {} not found", slug)))?;
+        parts.extensions.insert(tenant.clone());
+
+        Ok(tenant)
+    }
+}
+
+// 代理ログインの間に無効化されたり権限を失ったりした管理者のトークンは使わせない
+async fn authorize_impersonator(
+    registry: &AppRegistry,
+    tenant_id: TenantId,
+    admin_id: UserId,
+) -> Result<User, AppError> {
+    let admin = registry
+        .user_repository()
+        .find_current_user(tenant_id, admin_id)
+        .await?
+        .filter(|admin| admin.active)
+        .ok_or(AppError::UnauthenticatedError)?;
+    let permissions = registry.role_repository().find_permissions(admin.role).await?;
+    if !permissions.contains(&Permission::UserManage) {
+        return Err(AppError::UnauthenticatedError);
+    }
+    Ok(admin)
+}
+
+async fn authorize_api_key(
+    parts: &Parts,
+    registry: &AppRegistry,
+    key: &header::HeaderValue,
+) -> Result<(TokenSubject, Credential), AppError> {
+    let key = key.to_str().map_err(|_| AppError::UnauthenticatedError)?;
+    let api_key = registry
+        .api_key_repository()
+        .authenticate(key)
+        .await?
+        .ok_or(AppError::UnauthenticatedError)?;
+
+    // 参照系のリクエストかどうかで必要なスコープを決める
+    let required = if parts.method.is_safe() {
+        ApiKeyScope::Read
+    } else {
+        ApiKeyScope::Write
+    };
+    if !api_key.allows(required) {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
+    let subject = TokenSubject {
+        user_id: api_key.user_id,
+        impersonator: None,
+    };
+    Ok((subject, Credential::ApiKey(api_key)))
+}
+
+async fn authorize_bearer(
+    parts: &Parts,
+    registry: &AppRegistry,
+) -> Result<(TokenSubject, Credential), AppError> {
+    // Authorization: Bearer <token> を手動で取り出す（ボディには触らない）
+    let auth = parts
+        .headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .ok_or(AppError::UnauthorizedError)?;
+
+    // "Bearer " フレックスを確認
+    let token_str = auth
+        .strip_prefix("Bearer ")
+        .ok_or(AppError::UnauthorizedError)?;
+
+    let access_token = AccessToken(token_str.to_string());
+
+    let subject = registry
+        .auth_repository()
+        .fetch_token_subject(&access_token)
+        .await?
+        .ok_or(AppError::UnauthenticatedError)?;
+
+    Ok((subject, Credential::AccessToken(access_token)))
+}
+
+// If-Match: "<version>" をリソースのバージョンとして取り出す
+pub struct IfMatch(pub i64);
+
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = AppError;
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let value = parts
+            .headers
+            .get(header::IF_MATCH)
+            .ok_or(AppError::PreconditionRequired)?
+            .to_str()
+            .map_err(|_| AppError::PreconditionFailed("Invalid If-Match header".into()))?;
+
+        value
+            .trim()
+            .trim_start_matches("W/")
+            .trim_matches('"')
+            .parse::<i64>()
+            .map(Self)
+            .map_err(|_| AppError::PreconditionFailed("Invalid If-Match header".into()))
+    }
+}
+
+pub fn etag(version: i64) -> String {
+    format!("\"{version}\"")
+}
+
+// セッションに記録するためのリクエスト元の情報
+pub struct RequestMetadata(pub ClientInfo);
+
+impl FromRequestParts<AppRegistry> for RequestMetadata {
+    type Rejection = AppError;
+    async fn from_request_parts(
+        parts: &mut Parts,
+        registry: &AppRegistry,
+    ) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|h| h.to_str().ok())
+            .map(String::from);
+
+        // X-Forwarded-For は信頼するリバースプロキシから接続された場合だけ参照する
+        let forwarded_for = parts
+            .headers
+            .get("x-forwarded-for")
+            .and_then(|h| h.to_str().ok());
+        let ip_address = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| {
+                registry
+                    .proxy_config()
+                    .client_ip(addr.ip(), forwarded_for)
+                    .to_string()
+            });
 
-        Ok(Self { access_token, user })
+        Ok(Self(ClientInfo {
+            user_agent,
+            ip_address,
+        }))
     }
 }
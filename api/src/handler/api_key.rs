@@ -0,0 +1,74 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use garde::Validate;
+use kernel::model::{api_key::event::RevokeApiKey, id::ApiKeyId};
+use registry::AppRegistry;
+use shared::error::{AppError, AppResult};
+
+use crate::{
+    extractor::AuthorizedUser,
+    model::api_key::{
+        ApiKeyResponse, ApiKeysResponse, CreateApiKeyRequest, CreateApiKeyRequestWithUserId,
+        CreatedApiKeyResponse,
+    },
+};
+
+#[utoipa::path(post, path = "/users/me/api-keys")]
+pub async fn create_api_key(
+    user: AuthorizedUser,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<CreateApiKeyRequest>,
+) -> AppResult<(StatusCode, Json<CreatedApiKeyResponse>)> {
+    // API キーで新しい API キーを発行することはできない。代理ログイン中も同様
+    if user.is_api_key() || user.is_impersonating() {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
+    req.validate()?;
+
+    if req.expires_at.is_some_and(|at| at <= chrono::Utc::now()) {
+        return Err(AppError::UnprocessableEntity(
+            "expiresAt must be in the future".into(),
+        ));
+    }
+
+    let created = registry
+        .api_key_repository()
+        .create(CreateApiKeyRequestWithUserId::new(user.user_id(), req).into())
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(created.into())))
+}
+
+#[utoipa::path(get, path = "/users/me/api-keys")]
+pub async fn list_api_keys(
+    user: AuthorizedUser,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Json<ApiKeysResponse>> {
+    let items = registry
+        .api_key_repository()
+        .find_by_user_id(user.user_id())
+        .await?
+        .into_iter()
+        .map(ApiKeyResponse::from)
+        .collect();
+
+    Ok(Json(ApiKeysResponse { items }))
+}
+
+#[utoipa::path(delete, path = "/users/me/api-keys/{api_key_id}")]
+pub async fn revoke_api_key(
+    user: AuthorizedUser,
+    Path(api_key_id): Path<ApiKeyId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    registry
+        .api_key_repository()
+        .revoke(RevokeApiKey::new(api_key_id, user.user_id(), user.impersonator_id()))
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
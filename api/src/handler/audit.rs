@@ -0,0 +1,56 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use garde::Validate;
+use registry::AppRegistry;
+use shared::error::AppResult;
+
+use crate::{
+    extractor::{RequirePermission, permission::AuditRead},
+    model::audit::{AuditLogListQuery, PaginatedAuditLogResponse},
+};
+
+#[cfg_attr(
+    debug_assertions,
+    utoipa::path(
+        get,
+        path = "/api/v1/audit-log",
+        responses(
+            (status = 200, description = "監査ログの取得に成功した場合", body = PaginatedAuditLogResponse),
+            (status = 400, description = "指定されたクエリの値に不備があった場合"),
+            (status = 401, description = "認証されていないユーザがアクセスした場合"),
+            (status = 403, description = "管理者以外のユーザがアクセスした場合")
+        ),
+        params(
+            ("actorId" = Option<String>, Query, description = "操作を行ったユーザの ID"),
+            ("action" = Option<String>, Query, description = "操作の種類 (Create, Update, Delete)"),
+            ("targetType" = Option<String>, Query, description = "操作対象の種類"),
+            ("targetId" = Option<String>, Query, description = "操作対象の ID"),
+            ("from" = Option<String>, Query, description = "この日時以降の操作に絞り込む"),
+            ("to" = Option<String>, Query, description = "この日時より前の操作に絞り込む"),
+            ("limit" = i64, Query, description = "一度に取得する件数の上限値"),
+            ("offset" = i64, Query, description = "取得対象とする一覧の開始位置")
+        )
+    )
+)]
+#[tracing::instrument(
+    skip(user, registry),
+    fields(
+        user_id = %user.user_id().to_string()
+    )
+)]
+pub async fn show_audit_log(
+    user: RequirePermission<AuditRead>,
+    Query(query): Query<AuditLogListQuery>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Json<PaginatedAuditLogResponse>> {
+    query.validate()?;
+
+    registry
+        .audit_log_repository()
+        .find_all(user.tenant_id(), query.into())
+        .await
+        .map(PaginatedAuditLogResponse::from)
+        .map(Json)
+}
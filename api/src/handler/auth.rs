@@ -1,31 +1,175 @@
-use axum::{Json, extract::State, http::StatusCode};
-use kernel::model::auth::event::CreateToken;
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+};
+use garde::Validate;
+use kernel::mailer::Mail;
+use kernel::model::{
+    auth::{
+        event::{CreateEmailVerification, CreatePasswordReset, CreateToken},
+        EmailVerificationToken, LoginAttempt, RefreshToken,
+    },
+    oidc::event::CompleteOidcLogin,
+    two_factor::{event::CreateLoginChallenge, LoginChallengeToken},
+    user::User,
+};
 use registry::AppRegistry;
-use shared::error::AppResult;
+use shared::error::{AppError, AppResult};
 
 use crate::{
-    extractor::AuthorizedUser,
-    model::auth::{AccessTokenResponse, LoginRequest},
+    extractor::{AuthorizedUser, Credential, CurrentTenant, RequestMetadata},
+    model::{
+        auth::{
+            AccessTokenResponse, ConfirmPasswordResetRequest, LoginChallengeResponse,
+            LoginRequest, LoginTwoFactorRequest, OidcCallbackQuery, PasswordResetRequest,
+            RefreshTokenRequest, SignUpRequest, VerifyEmailRequest,
+        },
+        user::UserResponse,
+    },
 };
 
 #[utoipa::path(post, path = "/login")]
 pub async fn login(
+    CurrentTenant(tenant): CurrentTenant,
+    RequestMetadata(client): RequestMetadata,
     State(registry): State<AppRegistry>,
     Json(req): Json<LoginRequest>,
-) -> AppResult<Json<AccessTokenResponse>> {
-    let user_id = registry
+) -> AppResult<Response> {
+    let attempt = LoginAttempt::new(tenant.tenant_id, &req.email, client.ip_address.clone());
+    let login_attempts = registry.login_attempt_repository();
+    login_attempts.check(&attempt).await?;
+
+    let user_id = match registry
         .auth_repository()
-        .verify_user(&req.email, &req.password)
+        .verify_user(tenant.tenant_id, &req.email, &req.password)
+        .await
+    {
+        Ok(user_id) => user_id,
+        Err(e @ AppError::UnauthenticatedError) => {
+            login_attempts.record_failure(&attempt).await?;
+            return Err(e);
+        }
+        Err(e) => return Err(e),
+    };
+
+    // 二要素認証が有効な場合は、コードの確認が済むまでトークンを発行せず、失敗回数も消さない
+    let two_factor = registry.two_factor_repository();
+    if two_factor.is_enabled(user_id).await? {
+        let challenge = two_factor
+            .create_login_challenge(CreateLoginChallenge::new(
+                user_id,
+                tenant.tenant_id,
+                attempt.email,
+            ))
+            .await?;
+        let res = LoginChallengeResponse {
+            challenge_token: challenge.0,
+        };
+        return Ok((StatusCode::ACCEPTED, Json(res)).into_response());
+    }
+
+    let tokens = registry
+        .auth_repository()
+        .create_token(CreateToken::new(user_id, client))
         .await?;
-    let access_token = registry
+    login_attempts.record_success(&attempt).await?;
+
+    Ok(Json(AccessTokenResponse::from(tokens)).into_response())
+}
+
+#[utoipa::path(post, path = "/login/two-factor")]
+pub async fn login_two_factor(
+    RequestMetadata(client): RequestMetadata,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<LoginTwoFactorRequest>,
+) -> AppResult<Json<AccessTokenResponse>> {
+    // チャレンジは一度しか使えないため、コードを誤った場合はパスワードからやり直す
+    let two_factor = registry.two_factor_repository();
+    let challenge = two_factor
+        .take_login_challenge(&LoginChallengeToken(req.challenge_token))
+        .await?
+        .ok_or(AppError::UnauthenticatedError)?;
+
+    // コードの誤りもパスワードの誤りと同じく数え、ロック中はコードを確認しない
+    let attempt = LoginAttempt::new(
+        challenge.tenant_id,
+        &challenge.email,
+        client.ip_address.clone(),
+    );
+    let login_attempts = registry.login_attempt_repository();
+    login_attempts.check(&attempt).await?;
+    if !two_factor.verify_code(challenge.user_id, &req.code).await? {
+        login_attempts.record_failure(&attempt).await?;
+        return Err(AppError::UnauthenticatedError);
+    }
+
+    let tokens = registry
         .auth_repository()
-        .create_token(CreateToken::new(user_id))
+        .create_token(CreateToken::new(challenge.user_id, client))
+        .await?;
+    login_attempts.record_success(&attempt).await?;
+
+    Ok(Json(AccessTokenResponse::from(tokens)))
+}
+
+#[utoipa::path(get, path = "/oidc/login")]
+pub async fn oidc_login(
+    CurrentTenant(tenant): CurrentTenant,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Redirect> {
+    let oidc = registry
+        .oidc_repository()
+        .ok_or(AppError::ForbiddenOperationError)?;
+    let authorization = oidc.start_login(tenant.tenant_id).await?;
+    Ok(Redirect::to(&authorization.authorization_url))
+}
+
+#[utoipa::path(get, path = "/oidc/callback")]
+pub async fn oidc_callback(
+    CurrentTenant(tenant): CurrentTenant,
+    RequestMetadata(client): RequestMetadata,
+    State(registry): State<AppRegistry>,
+    Query(query): Query<OidcCallbackQuery>,
+) -> AppResult<Json<AccessTokenResponse>> {
+    let oidc = registry
+        .oidc_repository()
+        .ok_or(AppError::ForbiddenOperationError)?;
+    // ID プロバイダ側で認証を拒否された場合は code が付かない
+    let code = match (query.code, query.error) {
+        (Some(code), None) => code,
+        (_, error) => {
+            tracing::info!(error = ?error, "OIDC authorization was not granted");
+            return Err(AppError::UnauthenticatedError);
+        }
+    };
+
+    // ID プロバイダで認証済みのため、パスワードの試行制限と二要素認証は経由しない
+    let user_id = oidc
+        .complete_login(tenant.tenant_id, CompleteOidcLogin::new(code, query.state))
         .await?;
 
-    Ok(Json(AccessTokenResponse {
-        user_id,
-        access_token: access_token.0,
-    }))
+    registry
+        .auth_repository()
+        .create_token(CreateToken::new(user_id, client))
+        .await
+        .map(AccessTokenResponse::from)
+        .map(Json)
+}
+
+#[utoipa::path(post, path = "/refresh")]
+pub async fn refresh(
+    RequestMetadata(client): RequestMetadata,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<RefreshTokenRequest>,
+) -> AppResult<Json<AccessTokenResponse>> {
+    registry
+        .auth_repository()
+        .refresh_token(RefreshToken(req.refresh_token), client)
+        .await
+        .map(AccessTokenResponse::from)
+        .map(Json)
 }
 
 #[utoipa::path(post, path = "/logout")]
@@ -33,9 +177,117 @@ pub async fn logout(
     user: AuthorizedUser,
     State(registry): State<AppRegistry>,
 ) -> AppResult<StatusCode> {
+    // API キーはログアウトでは失効させない
+    if let Credential::AccessToken(access_token) = user.credential {
+        registry
+            .auth_repository()
+            .delete_token(access_token)
+            .await?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+
+#[utoipa::path(post, path = "/signup")]
+pub async fn sign_up(
+    CurrentTenant(tenant): CurrentTenant,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<SignUpRequest>,
+) -> AppResult<(StatusCode, Json<UserResponse>)> {
+    let signup_config = registry.signup_config();
+    if !signup_config.enabled {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
+    req.validate()?;
+
+    if !signup_config.is_allowed_email(&req.email) {
+        return Err(AppError::UnprocessableEntity(
+            "Email domain is not allowed to sign up".into(),
+        ));
+    }
+
+    let user = registry
+        .user_repository()
+        .create(tenant.tenant_id, req.into())
+        .await?;
+
+    send_email_verification(&registry, &user).await?;
+
+    Ok((StatusCode::CREATED, Json(user.into())))
+}
+
+// 確認用トークンを発行し、ユーザーのメールアドレス宛てに送る
+pub(crate) async fn send_email_verification(registry: &AppRegistry, user: &User) -> AppResult<()> {
+    let token = registry
+        .auth_repository()
+        .create_email_verification(CreateEmailVerification::new(
+            user.user_id,
+            user.email.clone(),
+        ))
+        .await?;
+
+    registry
+        .mailer()
+        .send(Mail::new(
+            user.email.clone(),
+            "メールアドレスの確認".into(),
+            format!("以下の確認トークンを使ってメールアドレスを確認してください。\n{}", token.0),
+        ))
+        .await
+}
+
+#[utoipa::path(post, path = "/email-verification")]
+pub async fn verify_email(
+    State(registry): State<AppRegistry>,
+    Json(req): Json<VerifyEmailRequest>,
+) -> AppResult<StatusCode> {
+    registry
+        .auth_repository()
+        .verify_email(&EmailVerificationToken(req.token))
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// メールアドレスが登録されているかどうかは応答から分からないようにする
+#[utoipa::path(post, path = "/password-reset")]
+pub async fn request_password_reset(
+    CurrentTenant(tenant): CurrentTenant,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<PasswordResetRequest>,
+) -> AppResult<StatusCode> {
+    req.validate()?;
+
+    let token = registry
+        .auth_repository()
+        .create_password_reset(tenant.tenant_id, CreatePasswordReset::new(req.email.clone()))
+        .await?;
+
+    if let Some(token) = token {
+        registry
+            .mailer()
+            .send(Mail::new(
+                req.email,
+                "パスワードの再設定".into(),
+                format!("以下のトークンを使ってパスワードを再設定してください。\n{}", token.0),
+            ))
+            .await?;
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[utoipa::path(put, path = "/password-reset/confirmed")]
+pub async fn confirm_password_reset(
+    State(registry): State<AppRegistry>,
+    Json(req): Json<ConfirmPasswordResetRequest>,
+) -> AppResult<StatusCode> {
+    req.validate()?;
+
     registry
         .auth_repository()
-        .delete_token(user.access_token)
+        .reset_password(req.into())
         .await?;
+
     Ok(StatusCode::NO_CONTENT)
 }
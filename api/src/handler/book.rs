@@ -1,15 +1,26 @@
 use axum::{
     Json,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{StatusCode, header},
 };
 use garde::Validate;
-use kernel::model::{book::event::DeleteBook, id::BookId};
+use kernel::model::{
+    book::event::{
+        AcceptBookTransfer, CancelBookTransfer, ChangeBookGroup, CreateBook, DeleteBook, ForceBookTransfer,
+        RequestBookTransfer, RestoreBook,
+    },
+    id::{BookId, BookTransferId},
+    role::Permission,
+};
 use registry::AppRegistry;
 use shared::error::{AppError, AppResult};
-use crate::extractor::AuthorizedUser;
-use crate::model::book::{BookListQuery, BookResponse, CreateBookRequest, PaginatedBookResponse,
-                         UpdateBookRequest, UpdateBookRequestWithIds,
+use crate::extractor::{AuthorizedUser, IfMatch, RequirePermission, etag, permission::BookWriteAny};
+use crate::model::book::{BookListQuery, BookOwnershipHistoriesResponse, BookResponse,
+                         BookTransferIdResponse, BookTransfersResponse, CreateBookRequest,
+                         DeletedBooksResponse,
+                         PaginatedBookResponse, PatchBookRequest, PatchBookRequestWithIds,
+                         TransferBookRequest, UpdateBookRequest, UpdateBookRequestWithIds,
+                         ChangeBookGroupRequest,
 };
 
 #[utoipa::path(post, path = "/books")]
@@ -21,7 +32,14 @@ pub async fn register_book(
     req.validate()?;
     registry
         .book_repository()
-        .create(req.into(), user.user_id())
+        .create(
+            user.tenant_id(),
+            CreateBook {
+                impersonator: user.impersonator_id(),
+                ..req.into()
+            },
+            user.user_id(),
+        )
         .await
         .map(|_| StatusCode::CREATED)
 }
@@ -43,13 +61,13 @@ pub async fn register_book(
     )
 )]
 #[tracing::instrument(
-    skip(_user, registry),
+    skip(user, registry),
     fields(
-        user_id = %_user.user_id().to_string()
+        user_id = %user.user_id().to_string()
     )
 )]
 pub async fn show_book_list(
-    _user: AuthorizedUser,
+    user: AuthorizedUser,
     Query(query): Query<BookListQuery>,
     State(registry): State<AppRegistry>,
 ) -> AppResult<Json<PaginatedBookResponse>> {
@@ -57,7 +75,7 @@ pub async fn show_book_list(
 
     registry
         .book_repository()
-        .find_all(query.into())
+        .find_all(user.tenant_id(), query.into())
         .await
         .map(PaginatedBookResponse::from)
         .map(Json)
@@ -65,16 +83,16 @@ pub async fn show_book_list(
 
 #[utoipa::path(get, path = "/books/{book_id}")]
 pub async fn show_book(
-    _user: AuthorizedUser,
+    user: AuthorizedUser,
     Path(book_id): Path<BookId>,
     State(registry): State<AppRegistry>,
-) -> AppResult<Json<BookResponse>> {
+) -> AppResult<([(header::HeaderName, String); 1], Json<BookResponse>)> {
     registry
         .book_repository()
-        .find_by_id(book_id)
+        .find_by_id(user.tenant_id(), book_id)
         .await
         .and_then(|bc| match bc {
-            Some(bc) => Ok(Json(bc.into())),
+            Some(bc) => Ok(([(header::ETAG, etag(bc.version))], Json(bc.into()))),
             None => Err(AppError::EntityNotFound("Book not found".into())),
         })
 }
@@ -83,16 +101,50 @@ pub async fn show_book(
 pub async fn update_book(
     user: AuthorizedUser,
     Path(book_id): Path<BookId>,
+    IfMatch(version): IfMatch,
     State(registry): State<AppRegistry>,
     Json(req): Json<UpdateBookRequest>,
 ) -> AppResult<StatusCode> {
     req.validate()?;
 
-    let update_book = UpdateBookRequestWithIds::new(book_id, user.user_id(), req);
+    let update_book = UpdateBookRequestWithIds::new(
+        book_id,
+        user.user_id(),
+        user.impersonator_id(),
+        user.has_permission(Permission::BookWriteAny),
+        version,
+        req,
+    );
 
     registry
         .book_repository()
-        .update(update_book.into())
+        .update(user.tenant_id(), update_book.into())
+        .await
+        .map(|_| StatusCode::OK)
+}
+
+#[utoipa::path(patch, path = "/books/{book_id}")]
+pub async fn patch_book(
+    user: AuthorizedUser,
+    Path(book_id): Path<BookId>,
+    IfMatch(version): IfMatch,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<PatchBookRequest>,
+) -> AppResult<StatusCode> {
+    req.validate()?;
+
+    let patch_book = PatchBookRequestWithIds::new(
+        book_id,
+        user.user_id(),
+        user.impersonator_id(),
+        user.has_permission(Permission::BookWriteAny),
+        version,
+        req,
+    );
+
+    registry
+        .book_repository()
+        .patch(user.tenant_id(), patch_book.into())
         .await
         .map(|_| StatusCode::OK)
 }
@@ -101,16 +153,177 @@ pub async fn update_book(
 pub async fn delete_book(
     user: AuthorizedUser,
     Path(book_id): Path<BookId>,
+    IfMatch(version): IfMatch,
     State(registry): State<AppRegistry>,
 ) -> AppResult<StatusCode> {
     let delete_book = DeleteBook {
         book_id,
         requested_user: user.user_id(),
+        impersonator: user.impersonator_id(),
+        any_owner: user.has_permission(Permission::BookWriteAny),
+        version,
     };
 
     registry
         .book_repository()
-        .delete(delete_book)
+        .delete(user.tenant_id(), delete_book)
+        .await
+        .map(|_| StatusCode::OK)
+}
+
+#[utoipa::path(put, path = "/books/{book_id}/group")]
+pub async fn change_book_group(
+    user: AuthorizedUser,
+    Path(book_id): Path<BookId>,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<ChangeBookGroupRequest>,
+) -> AppResult<StatusCode> {
+    let change_group = ChangeBookGroup::new(
+        book_id,
+        req.group_id,
+        user.user_id(),
+        user.impersonator_id(),
+        user.has_permission(Permission::BookWriteAny),
+    );
+
+    registry
+        .book_repository()
+        .change_group(user.tenant_id(), change_group)
+        .await
+        .map(|_| StatusCode::OK)
+}
+
+#[utoipa::path(post, path = "/books/{book_id}/transfers")]
+pub async fn request_book_transfer(
+    user: AuthorizedUser,
+    Path(book_id): Path<BookId>,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<TransferBookRequest>,
+) -> AppResult<(StatusCode, Json<BookTransferIdResponse>)> {
+    let request_transfer = RequestBookTransfer::new(
+        book_id,
+        req.transfer_to,
+        user.user_id(),
+        user.impersonator_id(),
+        chrono::Utc::now(),
+    );
+
+    let transfer_id = registry
+        .book_repository()
+        .request_transfer(user.tenant_id(), request_transfer)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(BookTransferIdResponse { transfer_id })))
+}
+
+#[utoipa::path(put, path = "/books/{book_id}/transfers/{transfer_id}/accepted")]
+pub async fn accept_book_transfer(
+    user: AuthorizedUser,
+    Path((book_id, transfer_id)): Path<(BookId, BookTransferId)>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    let accept_transfer = AcceptBookTransfer::new(
+        transfer_id,
+        book_id,
+        user.user_id(),
+        user.impersonator_id(),
+        chrono::Utc::now(),
+    );
+
+    registry
+        .book_repository()
+        .accept_transfer(user.tenant_id(), accept_transfer)
+        .await
+        .map(|_| StatusCode::OK)
+}
+
+#[utoipa::path(delete, path = "/books/{book_id}/transfers/{transfer_id}")]
+pub async fn cancel_book_transfer(
+    user: AuthorizedUser,
+    Path((book_id, transfer_id)): Path<(BookId, BookTransferId)>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    registry
+        .book_repository()
+        .cancel_transfer(
+            user.tenant_id(),
+            CancelBookTransfer::new(transfer_id, book_id, user.user_id(), user.impersonator_id()),
+        )
         .await
         .map(|_| StatusCode::OK)
-}
\ No newline at end of file
+}
+
+#[utoipa::path(put, path = "/books/{book_id}/owner")]
+pub async fn force_book_transfer(
+    user: RequirePermission<BookWriteAny>,
+    Path(book_id): Path<BookId>,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<TransferBookRequest>,
+) -> AppResult<StatusCode> {
+    let force_transfer = ForceBookTransfer::new(
+        book_id,
+        req.transfer_to,
+        user.user_id(),
+        user.impersonator_id(),
+        chrono::Utc::now(),
+    );
+
+    registry
+        .book_repository()
+        .force_transfer(user.tenant_id(), force_transfer)
+        .await
+        .map(|_| StatusCode::OK)
+}
+
+#[utoipa::path(get, path = "/books/transfers/me")]
+pub async fn show_my_book_transfers(
+    user: AuthorizedUser,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Json<BookTransfersResponse>> {
+    registry
+        .book_repository()
+        .find_transfers_by_user_id(user.tenant_id(), user.user_id())
+        .await
+        .map(BookTransfersResponse::from)
+        .map(Json)
+}
+
+#[utoipa::path(get, path = "/books/{book_id}/ownership-history")]
+pub async fn ownership_history(
+    user: AuthorizedUser,
+    Path(book_id): Path<BookId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Json<BookOwnershipHistoriesResponse>> {
+    registry
+        .book_repository()
+        .find_ownership_history(user.tenant_id(), book_id)
+        .await
+        .map(BookOwnershipHistoriesResponse::from)
+        .map(Json)
+}
+
+#[utoipa::path(get, path = "/books/deleted")]
+pub async fn show_deleted_book_list(
+    user: RequirePermission<BookWriteAny>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Json<DeletedBooksResponse>> {
+    registry
+        .book_repository()
+        .find_all_deleted(user.tenant_id())
+        .await
+        .map(DeletedBooksResponse::from)
+        .map(Json)
+}
+
+#[utoipa::path(put, path = "/books/{book_id}/restored")]
+pub async fn restore_book(
+    user: RequirePermission<BookWriteAny>,
+    Path(book_id): Path<BookId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    registry
+        .book_repository()
+        .restore(user.tenant_id(), RestoreBook::new(book_id, user.user_id(), user.impersonator_id()))
+        .await
+        .map(|_| StatusCode::OK)
+}
@@ -6,6 +6,7 @@ use axum::{
 use kernel::model::{
     checkout::event::{CreateCheckout, UpdateReturned},
     id::{BookId, CheckoutId},
+    role::Permission,
 };
 use registry::AppRegistry;
 use shared::error::AppResult;
@@ -18,11 +19,16 @@ pub async fn checkout_book(
     Path(book_id): Path<BookId>,
     State(registry): State<AppRegistry>,
 ) -> AppResult<StatusCode> {
-    let create_checkout_history = CreateCheckout::new(book_id, user.user_id(), chrono::Utc::now());
+    let create_checkout_history = CreateCheckout::new(
+        book_id,
+        user.user_id(),
+        user.impersonator_id(),
+        chrono::Utc::now(),
+    );
 
     registry
         .checkout_repository()
-        .create(create_checkout_history)
+        .create(user.tenant_id(), create_checkout_history)
         .await
         .map(|_| StatusCode::CREATED)
 }
@@ -33,24 +39,30 @@ pub async fn return_book(
     Path((book_id, checkout_id)): Path<(BookId, CheckoutId)>,
     State(registry): State<AppRegistry>,
 ) -> AppResult<StatusCode> {
-    let update_returned =
-        UpdateReturned::new(checkout_id, book_id, user.user_id(), chrono::Utc::now());
+    let update_returned = UpdateReturned::new(
+        checkout_id,
+        book_id,
+        user.user_id(),
+        user.impersonator_id(),
+        chrono::Utc::now(),
+        user.has_permission(Permission::CheckoutForceReturn),
+    );
 
     registry
         .checkout_repository()
-        .update_returned(update_returned)
+        .update_returned(user.tenant_id(), update_returned)
         .await
         .map(|_| StatusCode::OK)
 }
 
 #[utoipa::path(get, path = "/checkouts")]
 pub async fn show_checked_out_list(
-    _user: AuthorizedUser,
+    user: AuthorizedUser,
     State(registry): State<AppRegistry>,
 ) -> AppResult<Json<CheckoutsResponse>> {
     registry
         .checkout_repository()
-        .find_unreturned_all()
+        .find_unreturned_all(user.tenant_id())
         .await
         .map(CheckoutsResponse::from)
         .map(Json)
@@ -58,14 +70,14 @@ pub async fn show_checked_out_list(
 
 #[utoipa::path(get, path = "/books/{book_id}/checkouts")]
 pub async fn checkout_history(
-    _user: AuthorizedUser,
+    user: AuthorizedUser,
     Path(book_id): Path<BookId>,
     State(registry): State<AppRegistry>,
 ) -> AppResult<Json<CheckoutsResponse>> {
 
     registry
         .checkout_repository()
-        .find_history_by_book_id(book_id)
+        .find_history_by_book_id(user.tenant_id(), book_id)
     .await
     .map(CheckoutsResponse::from)
     .map(Json)
@@ -79,7 +91,7 @@ pub async fn get_checkouts(
     
     registry
         .checkout_repository()
-        .find_unreturned_by_user_id(user.user_id())
+        .find_unreturned_by_user_id(user.tenant_id(), user.user_id())
     .await
     .map(CheckoutsResponse::from)
     .map(Json)
@@ -0,0 +1,134 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use garde::Validate;
+use kernel::model::{
+    group::event::{DeleteGroup, RemoveGroupMember, UpsertGroupMember},
+    id::{GroupId, UserId},
+};
+use registry::AppRegistry;
+use shared::error::{AppError, AppResult};
+
+use crate::{
+    extractor::AuthorizedUser,
+    model::group::{
+        CreateGroupRequest, CreateGroupRequestWithUserId, GroupIdResponse, GroupMemberRequest,
+        GroupResponse, GroupsResponse, UpdateGroupRequest, UpdateGroupRequestWithIds,
+    },
+};
+
+#[utoipa::path(post, path = "/groups")]
+pub async fn create_group(
+    user: AuthorizedUser,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<CreateGroupRequest>,
+) -> AppResult<(StatusCode, Json<GroupIdResponse>)> {
+    req.validate()?;
+
+    let group_id = registry
+        .group_repository()
+        .create(user.tenant_id(), CreateGroupRequestWithUserId::new(user.user_id(), user.impersonator_id(), req).into())
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(GroupIdResponse { group_id })))
+}
+
+#[utoipa::path(get, path = "/groups/me")]
+pub async fn show_my_groups(
+    user: AuthorizedUser,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Json<GroupsResponse>> {
+    registry
+        .group_repository()
+        .find_by_user_id(user.tenant_id(), user.user_id())
+        .await
+        .map(GroupsResponse::from)
+        .map(Json)
+}
+
+#[utoipa::path(get, path = "/groups/{group_id}")]
+pub async fn show_group(
+    user: AuthorizedUser,
+    Path(group_id): Path<GroupId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Json<GroupResponse>> {
+    registry
+        .group_repository()
+        .find_by_id(user.tenant_id(), group_id)
+        .await?
+        .map(GroupResponse::from)
+        .map(Json)
+        .ok_or_else(|| AppError::EntityNotFound("Group not found".into()))
+}
+
+#[utoipa::path(put, path = "/groups/{group_id}")]
+pub async fn update_group(
+    user: AuthorizedUser,
+    Path(group_id): Path<GroupId>,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<UpdateGroupRequest>,
+) -> AppResult<StatusCode> {
+    req.validate()?;
+
+    registry
+        .group_repository()
+        .update(
+            user.tenant_id(),
+            UpdateGroupRequestWithIds::new(group_id, user.user_id(), user.impersonator_id(), req).into(),
+        )
+        .await
+        .map(|_| StatusCode::OK)
+}
+
+#[utoipa::path(delete, path = "/groups/{group_id}")]
+pub async fn delete_group(
+    user: AuthorizedUser,
+    Path(group_id): Path<GroupId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    registry
+        .group_repository()
+        .delete(user.tenant_id(), DeleteGroup::new(group_id, user.user_id(), user.impersonator_id()))
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(put, path = "/groups/{group_id}/members/{user_id}")]
+pub async fn put_group_member(
+    user: AuthorizedUser,
+    Path((group_id, user_id)): Path<(GroupId, UserId)>,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<GroupMemberRequest>,
+) -> AppResult<StatusCode> {
+    registry
+        .group_repository()
+        .upsert_member(user.tenant_id(), UpsertGroupMember::new(
+            group_id,
+            user_id,
+            req.role.into(),
+            user.user_id(),
+            user.impersonator_id(),
+        ))
+        .await
+        .map(|_| StatusCode::OK)
+}
+
+#[utoipa::path(delete, path = "/groups/{group_id}/members/{user_id}")]
+pub async fn remove_group_member(
+    user: AuthorizedUser,
+    Path((group_id, user_id)): Path<(GroupId, UserId)>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    registry
+        .group_repository()
+        .remove_member(user.tenant_id(), RemoveGroupMember::new(
+            group_id,
+            user_id,
+            user.user_id(),
+            user.impersonator_id(),
+        ))
+        .await
+        .map(|_| StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,40 @@
+use axum::{Json, extract::State, http::StatusCode};
+use garde::Validate;
+use registry::AppRegistry;
+use shared::error::AppResult;
+
+use crate::{
+    extractor::{RequirePermission, permission::TenantManage},
+    model::tenant::{
+        CreateTenantRequest, CreateTenantRequestWithUserId, TenantResponse, TenantsResponse,
+    },
+};
+
+#[utoipa::path(post, path = "/tenants")]
+pub async fn create_tenant(
+    user: RequirePermission<TenantManage>,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<CreateTenantRequest>,
+) -> AppResult<(StatusCode, Json<TenantResponse>)> {
+    req.validate()?;
+
+    let tenant = registry
+        .tenant_repository()
+        .create(CreateTenantRequestWithUserId::new(user.user_id(), user.impersonator_id(), req).into())
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(tenant.into())))
+}
+
+#[utoipa::path(get, path = "/tenants")]
+pub async fn show_tenant_list(
+    _user: RequirePermission<TenantManage>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Json<TenantsResponse>> {
+    registry
+        .tenant_repository()
+        .find_all()
+        .await
+        .map(TenantsResponse::from)
+        .map(Json)
+}
@@ -0,0 +1,122 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use garde::Validate;
+use kernel::model::{
+    id::UserId,
+    two_factor::event::{DisableTwoFactor, StartTotpEnrollment},
+};
+use registry::AppRegistry;
+use shared::error::{AppError, AppResult};
+
+use crate::{
+    extractor::{AuthorizedUser, RequirePermission, permission::UserManage},
+    model::two_factor::{
+        RecoveryCodesResponse, TotpEnrollmentResponse, TwoFactorCodeRequest,
+        TwoFactorCodeRequestWithUserId, TwoFactorStatusResponse,
+    },
+};
+
+#[utoipa::path(get, path = "/users/me/two-factor")]
+pub async fn show_two_factor_status(
+    user: AuthorizedUser,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Json<TwoFactorStatusResponse>> {
+    let enabled = registry
+        .two_factor_repository()
+        .is_enabled(user.user_id())
+        .await?;
+
+    Ok(Json(TwoFactorStatusResponse { enabled }))
+}
+
+#[utoipa::path(post, path = "/users/me/two-factor")]
+pub async fn start_totp_enrollment(
+    user: AuthorizedUser,
+    State(registry): State<AppRegistry>,
+) -> AppResult<(StatusCode, Json<TotpEnrollmentResponse>)> {
+    if user.is_api_key() || user.is_impersonating() {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
+    let enrollment = registry
+        .two_factor_repository()
+        .start_enrollment(StartTotpEnrollment::new(
+            user.user_id(),
+            user.user.email.clone(),
+        ))
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(enrollment.into())))
+}
+
+// 認証アプリのコードを確認できたら有効にし、リカバリーコードを返す
+#[utoipa::path(put, path = "/users/me/two-factor/activated")]
+pub async fn activate_totp(
+    user: AuthorizedUser,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<TwoFactorCodeRequest>,
+) -> AppResult<Json<RecoveryCodesResponse>> {
+    if user.is_api_key() || user.is_impersonating() {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
+    req.validate()?;
+
+    registry
+        .two_factor_repository()
+        .activate(TwoFactorCodeRequestWithUserId::new(user.user_id(), user.impersonator_id(), req).into())
+        .await
+        .map(RecoveryCodesResponse::from)
+        .map(Json)
+}
+
+// 本人が無効にする場合は、現在のコードかリカバリーコードの入力を求める
+#[utoipa::path(delete, path = "/users/me/two-factor")]
+pub async fn disable_my_two_factor(
+    user: AuthorizedUser,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<TwoFactorCodeRequest>,
+) -> AppResult<StatusCode> {
+    if user.is_api_key() || user.is_impersonating() {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
+    req.validate()?;
+
+    let repository = registry.two_factor_repository();
+    if !repository.verify_code(user.user_id(), &req.code).await? {
+        return Err(AppError::UnprocessableEntity(
+            "Invalid verification code".into(),
+        ));
+    }
+
+    repository
+        .disable(DisableTwoFactor::new(user.user_id(), user.user_id(), user.impersonator_id()))
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// 端末とリカバリーコードの両方を失くしたユーザーのために、管理者が解除する
+#[utoipa::path(delete, path = "/users/{user_id}/two-factor")]
+pub async fn disable_user_two_factor(
+    user: RequirePermission<UserManage>,
+    Path(user_id): Path<UserId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    registry
+        .user_repository()
+        .find_current_user(user.tenant_id(), user_id)
+        .await?
+        .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+
+    registry
+        .two_factor_repository()
+        .disable(DisableTwoFactor::new(user_id, user.user_id(), user.impersonator_id()))
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
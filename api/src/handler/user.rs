@@ -1,35 +1,52 @@
 use axum::{
     Json,
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
 };
 use garde::Validate;
-use kernel::model::{id::UserId, user::event::DeleteUser};
+use kernel::model::{
+    auth::event::CreateImpersonationToken,
+    id::{SessionId, UserId},
+    role::{Permission, Role},
+    user::{
+        event::{
+            CreateUser, DeactivateUser, DeleteUser, PatchUser, ReactivateUser, RestoreUser,
+            UpdateProfile, UpdateUserRole,
+        },
+        User,
+    },
+};
 use registry::AppRegistry;
 use shared::error::{AppError, AppResult};
 
 use crate::{
-    extractor::AuthorizedUser,
+    extractor::{AuthorizedUser, IfMatch, RequirePermission, etag, permission::UserManage},
+    handler::auth::send_email_verification,
+    model::auth::{ImpersonationTokenResponse, SessionResponse, SessionsResponse},
     model::user::{
-        CreateUserRequest, UpdateUserPasswordRequest, UpdateUserPasswordRequestWithUserId,
-        UpdateUserRoleRequest, UpdateUserRoleRequestWithUserId, UserResponse, UsersResponse,
+        CreateUserRequest, DeletedUsersResponse, PatchUserRequest, PatchUserRequestWithUserId,
+        UpdateProfileRequest, UpdateProfileRequestWithUserId, UpdateUserPasswordRequest,
+        UpdateUserPasswordRequestWithUserId,
+        UpdateUserRoleRequest, UpdateUserRoleRequestWithIds, UserListQuery,
+        UserListQueryWithVisibility, UserProfileResponse, UserResponse, PaginatedUserResponse,
     },
 };
 
 #[utoipa::path(post, path = "/users")]
 pub async fn register_user(
-    user: AuthorizedUser,
+    user: RequirePermission<UserManage>,
     State(registry): State<AppRegistry>,
     Json(req): Json<CreateUserRequest>,
 ) -> AppResult<Json<UserResponse>> {
 
-    if !user.is_admin() {
-        return Err(AppError::ForbiddenOperationError);
-    }
-    
     req.validate()?;
     
-    let registered_user = registry.user_repository().create(req.into()).await?;
+    let event = CreateUser {
+        requested_user: Some(user.user_id()),
+        impersonator: user.impersonator_id(),
+        ..req.into()
+    };
+    let registered_user = registry.user_repository().create(user.tenant_id(), event).await?;
     
     Ok(Json(registered_user.into()))
 }
@@ -37,63 +54,313 @@ pub async fn register_user(
 
 #[utoipa::path(get, path = "/users")]
 pub async fn list_users(
-    _user: AuthorizedUser,
+    user: AuthorizedUser,
+    Query(query): Query<UserListQuery>,
     State(registry): State<AppRegistry>,
-) -> AppResult<Json<UsersResponse>> {
-    
-    let items = registry
+) -> AppResult<Json<PaginatedUserResponse>> {
+    query.validate()?;
+
+    // メールアドレスで検索できると、表示しなくても登録の有無が分かってしまう
+    let show_email = user.has_permission(Permission::UserManage);
+    registry
         .user_repository()
-        .find_all()
+        .find_all(
+            user.tenant_id(),
+            UserListQueryWithVisibility::new(show_email, query).into(),
+        )
+        .await
+        .map(|list| PaginatedUserResponse::new(list, show_email))
+        .map(Json)
+}
+
+#[utoipa::path(get, path = "/users/{user_id}")]
+pub async fn show_user(
+    user: AuthorizedUser,
+    Path(user_id): Path<UserId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Json<UserProfileResponse>> {
+    let show_email = user.has_permission(Permission::UserManage) || user.user_id() == user_id;
+    registry
+        .user_repository()
+        .find_profile(user.tenant_id(), user_id)
         .await?
-        .into_iter()
-        .map(UserResponse::from)
-        .collect();
-    
-    Ok(Json(UsersResponse{ items}))
+        .map(|profile| UserProfileResponse::new(profile, show_email))
+        .map(Json)
+        .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))
 }
 
 #[utoipa::path(delete, path = "/users/{user_id}")]
 pub async fn delete_user(
-    user: AuthorizedUser,
+    user: RequirePermission<UserManage>,
     Path(user_id): Path<UserId>,
     State(registry): State<AppRegistry>,    
 ) -> AppResult<StatusCode> {
-    
-    if !user.is_admin() {
-        return Err(AppError::ForbiddenOperationError);
+    // 削除した利用者は後で完全に消去されるため、SuperAdmin はテナントを管理できる利用者しか削除できない
+    if !user.has_permission(Permission::TenantManage) {
+        let target = registry
+            .user_repository()
+            .find_current_user(user.tenant_id(), user_id)
+            .await?
+            .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+        if target.role == Role::SuperAdmin {
+            return Err(AppError::ForbiddenOperationError);
+        }
     }
-    
+
     registry
         .user_repository()
-        .delete(DeleteUser { user_id })
+        .delete(user.tenant_id(), DeleteUser {
+            user_id,
+            requested_user: user.user_id(),
+            impersonator: user.impersonator_id(),
+        })
         .await?;
     
     Ok(StatusCode::OK)
 }
 
+#[utoipa::path(get, path = "/users/deleted")]
+pub async fn list_deleted_users(
+    user: RequirePermission<UserManage>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Json<DeletedUsersResponse>> {
+
+    registry
+        .user_repository()
+        .find_all_deleted(user.tenant_id())
+        .await
+        .map(DeletedUsersResponse::from)
+        .map(Json)
+}
+
+#[utoipa::path(put, path = "/users/{user_id}/restored")]
+pub async fn restore_user(
+    user: RequirePermission<UserManage>,
+    Path(user_id): Path<UserId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+
+    registry
+        .user_repository()
+        .restore(user.tenant_id(), RestoreUser {
+            user_id,
+            requested_user: user.user_id(),
+            impersonator: user.impersonator_id(),
+        })
+        .await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(put, path = "/users/{user_id}/deactivated")]
+pub async fn deactivate_user(
+    user: RequirePermission<UserManage>,
+    Path(user_id): Path<UserId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    // 自分自身を無効化すると、再び有効にする管理者がいなくなるおそれがある
+    if user_id == user.user_id() {
+        return Err(AppError::UnprocessableEntity(
+            "You cannot deactivate your own account".into(),
+        ));
+    }
+    if !user.has_permission(Permission::TenantManage) {
+        let target = registry
+            .user_repository()
+            .find_current_user(user.tenant_id(), user_id)
+            .await?
+            .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+        if target.role == Role::SuperAdmin {
+            return Err(AppError::ForbiddenOperationError);
+        }
+    }
+
+    registry
+        .user_repository()
+        .deactivate(user.tenant_id(), DeactivateUser {
+            user_id,
+            requested_user: user.user_id(),
+            impersonator: user.impersonator_id(),
+        })
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(delete, path = "/users/{user_id}/deactivated")]
+pub async fn reactivate_user(
+    user: RequirePermission<UserManage>,
+    Path(user_id): Path<UserId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    registry
+        .user_repository()
+        .reactivate(user.tenant_id(), ReactivateUser {
+            user_id,
+            requested_user: user.user_id(),
+            impersonator: user.impersonator_id(),
+        })
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// 問い合わせの調査のため、管理者が利用者として操作できる短命のトークンを発行する
+#[utoipa::path(post, path = "/users/{user_id}/impersonation")]
+pub async fn impersonate_user(
+    user: RequirePermission<UserManage>,
+    Path(user_id): Path<UserId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<(StatusCode, Json<ImpersonationTokenResponse>)> {
+    if user.is_api_key() || user.is_impersonating() {
+        return Err(AppError::ForbiddenOperationError);
+    }
+    if user_id == user.user_id() {
+        return Err(AppError::UnprocessableEntity(
+            "You cannot impersonate yourself".into(),
+        ));
+    }
+
+    let target = registry
+        .user_repository()
+        .find_current_user(user.tenant_id(), user_id)
+        .await?
+        .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+    if !target.active {
+        return Err(AppError::UnprocessableEntity(
+            "Specified user is deactivated".into(),
+        ));
+    }
+    if target.role == Role::SuperAdmin && !user.has_permission(Permission::TenantManage) {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
+    let token = registry
+        .auth_repository()
+        .create_impersonation_token(CreateImpersonationToken::new(user.user_id(), user_id))
+        .await?;
+    tracing::info!(
+        impersonator_id = %user.user_id(),
+        %user_id,
+        "Started impersonation"
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ImpersonationTokenResponse::new(user_id, user.user_id(), token)),
+    ))
+}
+
 #[utoipa::path(put, path = "/users/{user_id}/role")]
 pub async fn change_role (
-    user: AuthorizedUser,
+    user: RequirePermission<UserManage>,
     Path(user_id): Path<UserId>,
+    IfMatch(version): IfMatch,
     State(registry): State<AppRegistry>,
     Json(req): Json<UpdateUserRoleRequest>,
 ) -> AppResult<StatusCode> {
-    
-    if !user.is_admin() {
-        return Err(AppError::ForbiddenOperationError);
+    let event: UpdateUserRole =
+        UpdateUserRoleRequestWithIds::new(
+            user_id,
+            user.user_id(),
+            user.impersonator_id(),
+            version,
+            req,
+        ).into();
+
+    // SuperAdmin の付与と剥奪は、テナントを管理できる利用者にしかできない
+    if !user.has_permission(Permission::TenantManage) {
+        let target = registry
+            .user_repository()
+            .find_current_user(user.tenant_id(), user_id)
+            .await?
+            .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+        if event.role == Role::SuperAdmin || target.role == Role::SuperAdmin {
+            return Err(AppError::ForbiddenOperationError);
+        }
     }
-    
+
     registry
         .user_repository()
-        .update_role(UpdateUserRoleRequestWithUserId::new(user_id, req).into())
+        .update_role(user.tenant_id(), event)
         .await?;
     
     Ok(StatusCode::OK)
 }
 
 #[utoipa::path(get, path = "/users/me")]
-pub async fn get_current_user(user: AuthorizedUser) -> Json<UserResponse> {
-    Json(UserResponse::from(user.user))
+pub async fn get_current_user(
+    user: AuthorizedUser,
+) -> ([(header::HeaderName, String); 1], Json<UserResponse>) {
+    ([(header::ETAG, etag(user.user.version))], Json(UserResponse::from(user.user)))
+}
+
+#[utoipa::path(put, path = "/users/me")]
+pub async fn update_current_user(
+    user: AuthorizedUser,
+    IfMatch(version): IfMatch,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<UpdateProfileRequest>,
+) -> AppResult<([(header::HeaderName, String); 1], Json<UserResponse>)> {
+    req.validate()?;
+
+    let event: UpdateProfile =
+        UpdateProfileRequestWithUserId::new(
+            user.user_id(),
+            user.impersonator_id(),
+            version,
+            req,
+        ).into();
+    // メールアドレスを変えるとパスワードの再設定で乗っ取れるため、代理ログイン中は変えさせない
+    if user.is_impersonating() && event.email != user.user.email {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
+    let updated = registry
+        .user_repository()
+        .update_profile(user.tenant_id(), event)
+        .await?;
+
+    profile_updated(&registry, user.user, updated).await
+}
+
+#[utoipa::path(patch, path = "/users/me")]
+pub async fn patch_current_user(
+    user: AuthorizedUser,
+    IfMatch(version): IfMatch,
+    State(registry): State<AppRegistry>,
+    Json(req): Json<PatchUserRequest>,
+) -> AppResult<([(header::HeaderName, String); 1], Json<UserResponse>)> {
+    req.validate()?;
+
+    let event: PatchUser = PatchUserRequestWithUserId::new(
+        user.user_id(),
+        user.impersonator_id(),
+        version,
+        req,
+    ).into();
+    if user.is_impersonating() && event.email.as_ref().is_some_and(|e| *e != user.user.email) {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
+    let updated = registry
+        .user_repository()
+        .patch(user.tenant_id(), event)
+        .await?;
+
+    profile_updated(&registry, user.user, updated).await
+}
+
+// メールアドレスが変わった場合は、新しいアドレス宛てに確認メールを送る
+async fn profile_updated(
+    registry: &AppRegistry,
+    before: User,
+    after: User,
+) -> AppResult<([(header::HeaderName, String); 1], Json<UserResponse>)> {
+    if before.email != after.email {
+        send_email_verification(registry, &after).await?;
+    }
+    Ok(([(header::ETAG, etag(after.version))], Json(after.into())))
 }
 
 #[utoipa::path(put, path = "/users/me/password")]
@@ -102,13 +369,119 @@ pub async fn change_password (
     State(registry): State<AppRegistry>,
     Json(req): Json<UpdateUserPasswordRequest>,
 ) -> AppResult<StatusCode> {
-    
+    // 代理ログイン中の管理者に本人のパスワードを変えさせない
+    if user.is_impersonating() {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
     req.validate()?;
-    
+
     registry
         .user_repository()
-        .update_password(UpdateUserPasswordRequestWithUserId::new(user.user.user_id, req).into())
+        .update_password(
+            user.tenant_id(),
+            UpdateUserPasswordRequestWithUserId::new(user.user.user_id, req).into(),
+        )
         .await?;
-    
-    Ok(StatusCode::OK)      
-}
\ No newline at end of file
+
+    Ok(StatusCode::OK)
+}
+
+#[utoipa::path(get, path = "/users/me/sessions")]
+pub async fn list_my_sessions(
+    user: AuthorizedUser,
+    State(registry): State<AppRegistry>,
+) -> AppResult<Json<SessionsResponse>> {
+    let items = registry
+        .auth_repository()
+        .find_sessions(user.user_id())
+        .await?
+        .into_iter()
+        .map(SessionResponse::from)
+        .collect();
+
+    Ok(Json(SessionsResponse { items }))
+}
+
+#[utoipa::path(delete, path = "/users/me/sessions/{session_id}")]
+pub async fn delete_my_session(
+    user: AuthorizedUser,
+    Path(session_id): Path<SessionId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    // 本人のセッションを代理ログイン中の管理者に消させない
+    if user.is_impersonating() {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
+    registry
+        .auth_repository()
+        .delete_session(user.user_id(), session_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// すべての端末からログアウトする
+#[utoipa::path(delete, path = "/users/me/sessions")]
+pub async fn delete_my_sessions(
+    user: AuthorizedUser,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    if user.is_impersonating() {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
+    registry
+        .auth_repository()
+        .delete_all_tokens(user.user_id())
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(delete, path = "/users/{user_id}/sessions")]
+pub async fn delete_user_sessions(
+    user: RequirePermission<UserManage>,
+    Path(user_id): Path<UserId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    let target = registry
+        .user_repository()
+        .find_current_user(user.tenant_id(), user_id)
+        .await?
+        .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+    if target.role == Role::SuperAdmin && !user.has_permission(Permission::TenantManage) {
+        return Err(AppError::ForbiddenOperationError);
+    }
+
+    registry
+        .auth_repository()
+        .delete_all_tokens(user_id)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ログイン失敗によるロックを管理者が解除する
+#[utoipa::path(delete, path = "/users/{user_id}/lockout")]
+pub async fn unlock_user(
+    user: RequirePermission<UserManage>,
+    Path(user_id): Path<UserId>,
+    State(registry): State<AppRegistry>,
+) -> AppResult<StatusCode> {
+    let target = registry
+        .user_repository()
+        .find_current_user(user.tenant_id(), user_id)
+        .await?
+        .ok_or_else(|| AppError::EntityNotFound("Specified user does not exist".into()))?;
+
+    registry
+        .login_attempt_repository()
+        .unlock(user.tenant_id(), &target.email)
+        .await?;
+
+    tracing::info!(%user_id, requested_user = %user.actor_id(), "Unlocked user login");
+
+    Ok(StatusCode::NO_CONTENT)
+}
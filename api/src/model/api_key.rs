@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use derive_new::new;
+use garde::Validate;
+use kernel::model::{
+    api_key::{event::CreateApiKey, ApiKey, ApiKeyScope, CreatedApiKey},
+    id::{ApiKeyId, UserId},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub enum ApiKeyScopeName {
+    Read,
+    Write,
+}
+
+impl From<ApiKeyScope> for ApiKeyScopeName {
+    fn from(scope: ApiKeyScope) -> Self {
+        match scope {
+            ApiKeyScope::Read => ApiKeyScopeName::Read,
+            ApiKeyScope::Write => ApiKeyScopeName::Write,
+        }
+    }
+}
+
+impl From<ApiKeyScopeName> for ApiKeyScope {
+    fn from(scope: ApiKeyScopeName) -> Self {
+        match scope {
+            ApiKeyScopeName::Read => ApiKeyScope::Read,
+            ApiKeyScopeName::Write => ApiKeyScope::Write,
+        }
+    }
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeyRequest {
+    #[garde(length(min = 1))]
+    pub name: String,
+    #[garde(length(min = 1))]
+    pub scopes: Vec<ApiKeyScopeName>,
+    // 省略した場合は無期限
+    #[garde(skip)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(new)]
+pub struct CreateApiKeyRequestWithUserId(UserId, CreateApiKeyRequest);
+
+impl From<CreateApiKeyRequestWithUserId> for CreateApiKey {
+    fn from(value: CreateApiKeyRequestWithUserId) -> Self {
+        let CreateApiKeyRequestWithUserId(
+            user_id,
+            CreateApiKeyRequest {
+                name,
+                scopes,
+                expires_at,
+            },
+        ) = value;
+        Self {
+            user_id,
+            name,
+            scopes: scopes.into_iter().map(ApiKeyScope::from).collect(),
+            expires_at,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeysResponse {
+    pub items: Vec<ApiKeyResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyResponse {
+    pub api_key_id: ApiKeyId,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScopeName>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ApiKey> for ApiKeyResponse {
+    fn from(value: ApiKey) -> Self {
+        let ApiKey {
+            api_key_id,
+            name,
+            prefix,
+            scopes,
+            expires_at,
+            last_used_at,
+            created_at,
+        } = value;
+        Self {
+            api_key_id,
+            name,
+            prefix,
+            scopes: scopes.into_iter().map(ApiKeyScopeName::from).collect(),
+            expires_at,
+            last_used_at,
+            created_at,
+        }
+    }
+}
+
+// キー本体は発行時のレスポンスでしか返さない
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedApiKeyResponse {
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+    pub key: String,
+}
+
+impl From<CreatedApiKey> for CreatedApiKeyResponse {
+    fn from(value: CreatedApiKey) -> Self {
+        Self {
+            api_key: value.api_key.into(),
+            key: value.key,
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use chrono::{DateTime, Utc};
+use garde::Validate;
+use kernel::model::{
+    audit::{AuditAction, AuditLog, AuditLogListOptions, AuditTarget},
+    id::{AuditLogId, UserId},
+    list::PaginatedList,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub enum AuditActionName {
+    Create,
+    Update,
+    Delete,
+}
+
+impl From<AuditAction> for AuditActionName {
+    fn from(action: AuditAction) -> Self {
+        match action {
+            AuditAction::Create => AuditActionName::Create,
+            AuditAction::Update => AuditActionName::Update,
+            AuditAction::Delete => AuditActionName::Delete,
+        }
+    }
+}
+
+impl From<AuditActionName> for AuditAction {
+    fn from(action: AuditActionName) -> Self {
+        match action {
+            AuditActionName::Create => AuditAction::Create,
+            AuditActionName::Update => AuditAction::Update,
+            AuditActionName::Delete => AuditAction::Delete,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub enum AuditTargetName {
+    Book,
+    BookTransfer,
+    User,
+    Checkout,
+    AccessToken,
+    ApiKey,
+    TwoFactor,
+    Group,
+    Tenant,
+}
+
+impl From<AuditTarget> for AuditTargetName {
+    fn from(target: AuditTarget) -> Self {
+        match target {
+            AuditTarget::Book => AuditTargetName::Book,
+            AuditTarget::BookTransfer => AuditTargetName::BookTransfer,
+            AuditTarget::User => AuditTargetName::User,
+            AuditTarget::Checkout => AuditTargetName::Checkout,
+            AuditTarget::AccessToken => AuditTargetName::AccessToken,
+            AuditTarget::ApiKey => AuditTargetName::ApiKey,
+            AuditTarget::TwoFactor => AuditTargetName::TwoFactor,
+            AuditTarget::Group => AuditTargetName::Group,
+            AuditTarget::Tenant => AuditTargetName::Tenant,
+        }
+    }
+}
+
+impl From<AuditTargetName> for AuditTarget {
+    fn from(target: AuditTargetName) -> Self {
+        match target {
+            AuditTargetName::Book => AuditTarget::Book,
+            AuditTargetName::BookTransfer => AuditTarget::BookTransfer,
+            AuditTargetName::User => AuditTarget::User,
+            AuditTargetName::Checkout => AuditTarget::Checkout,
+            AuditTargetName::AccessToken => AuditTarget::AccessToken,
+            AuditTargetName::ApiKey => AuditTarget::ApiKey,
+            AuditTargetName::TwoFactor => AuditTarget::TwoFactor,
+            AuditTargetName::Group => AuditTarget::Group,
+            AuditTargetName::Tenant => AuditTarget::Tenant,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLogListQuery {
+    #[garde(skip)]
+    pub actor_id: Option<Uuid>,
+    #[garde(skip)]
+    pub action: Option<AuditActionName>,
+    #[garde(skip)]
+    pub target_type: Option<AuditTargetName>,
+    #[garde(skip)]
+    pub target_id: Option<Uuid>,
+    #[garde(skip)]
+    pub from: Option<DateTime<Utc>>,
+    #[garde(skip)]
+    pub to: Option<DateTime<Utc>>,
+    #[garde(range(min = 0))]
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[garde(range(min = 0))]
+    #[serde(default)]
+    pub offset: i64,
+}
+
+const DEFAULT_LIMIT: i64 = 20;
+const fn default_limit() -> i64 {
+    DEFAULT_LIMIT
+}
+
+impl From<AuditLogListQuery> for AuditLogListOptions {
+    fn from(value: AuditLogListQuery) -> Self {
+        Self {
+            actor_id: value.actor_id.map(UserId::from),
+            action: value.action.map(AuditAction::from),
+            target_type: value.target_type.map(AuditTarget::from),
+            target_id: value.target_id,
+            from: value.from,
+            to: value.to,
+            limit: value.limit,
+            offset: value.offset,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLogResponse {
+    pub audit_log_id: AuditLogId,
+    pub actor_id: Option<UserId>,
+    // 代理ログイン中の操作の場合、実際に操作した管理者
+    pub impersonator_id: Option<UserId>,
+    pub action: AuditActionName,
+    pub target_type: AuditTargetName,
+    pub target_id: Uuid,
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<Value>,
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<AuditLog> for AuditLogResponse {
+    fn from(value: AuditLog) -> Self {
+        Self {
+            audit_log_id: value.audit_log_id,
+            actor_id: value.actor_id,
+            impersonator_id: value.impersonator_id,
+            action: value.action.into(),
+            target_type: value.target_type.into(),
+            target_id: value.target_id,
+            before: value.before,
+            after: value.after,
+            created_at: value.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaginatedAuditLogResponse {
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    pub items: Vec<AuditLogResponse>,
+}
+
+impl From<PaginatedList<AuditLog>> for PaginatedAuditLogResponse {
+    fn from(value: PaginatedList<AuditLog>) -> Self {
+        Self {
+            total: value.total,
+            limit: value.limit,
+            offset: value.offset,
+            items: value.items.into_iter().map(AuditLogResponse::from).collect(),
+        }
+    }
+}
@@ -1,4 +1,10 @@
-use kernel::model::id::UserId;
+use garde::Validate;
+use kernel::model::{
+    auth::{event::ResetPassword, AuthTokens, ImpersonationToken, PasswordResetToken, Session},
+    id::{SessionId, UserId},
+    user::event::CreateUser,
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
@@ -14,4 +20,156 @@ pub struct LoginRequest {
 pub struct AccessTokenResponse {
     pub user_id: UserId,
     pub access_token: String,
-}
\ No newline at end of file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+impl From<AuthTokens> for AccessTokenResponse {
+    fn from(tokens: AuthTokens) -> Self {
+        Self {
+            user_id: tokens.user_id,
+            access_token: tokens.access_token.0,
+            refresh_token: tokens.refresh_token.map(|t| t.0),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpersonationTokenResponse {
+    pub user_id: UserId,
+    pub impersonator_id: UserId,
+    pub access_token: String,
+    pub expires_in: u64,
+}
+
+impl ImpersonationTokenResponse {
+    pub fn new(user_id: UserId, impersonator_id: UserId, token: ImpersonationToken) -> Self {
+        Self {
+            user_id,
+            impersonator_id,
+            access_token: token.access_token.0,
+            expires_in: token.expires_in,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignUpRequest {
+    #[garde(length(min = 1))]
+    pub name: String,
+    #[garde(email)]
+    pub email: String,
+    #[garde(length(min = 1))]
+    pub password: String,
+}
+
+impl From<SignUpRequest> for CreateUser {
+    fn from(request: SignUpRequest) -> Self {
+        Self {
+            name: request.name,
+            email: request.email,
+            password: request.password,
+            requested_user: None,
+            impersonator: None,
+            email_verified: false,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordResetRequest {
+    #[garde(email)]
+    pub email: String,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfirmPasswordResetRequest {
+    #[garde(length(min = 1))]
+    pub token: String,
+    #[garde(length(min = 1))]
+    pub new_password: String,
+}
+
+impl From<ConfirmPasswordResetRequest> for ResetPassword {
+    fn from(request: ConfirmPasswordResetRequest) -> Self {
+        Self {
+            token: PasswordResetToken(request.token),
+            new_password: request.new_password,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionsResponse {
+    pub items: Vec<SessionResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    pub session_id: SessionId,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl From<Session> for SessionResponse {
+    fn from(value: Session) -> Self {
+        let Session {
+            session_id,
+            created_at,
+            last_used_at,
+            expires_at,
+            client,
+        } = value;
+        Self {
+            session_id,
+            created_at,
+            last_used_at,
+            expires_at,
+            user_agent: client.user_agent,
+            ip_address: client.ip_address,
+        }
+    }
+}
+
+// 二要素認証が有効なユーザーのログインでは、トークンの代わりにこれを返す
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginChallengeResponse {
+    pub challenge_token: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginTwoFactorRequest {
+    pub challenge_token: String,
+    pub code: String,
+}
+
+// ID プロバイダからリダイレクトされたときのクエリ。拒否された場合は code の代わりに error が付く
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: String,
+    pub error: Option<String>,
+}
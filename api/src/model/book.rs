@@ -1,13 +1,17 @@
 use chrono::DateTime;
 use derive_new::new;
 use garde::Validate;
-use kernel::model::book::{Book, event::CreateBook, BookListOptions, Checkout};
-use kernel::model::id::{BookId, CheckoutId, UserId};
+use kernel::model::book::{
+    Book, event::CreateBook, BookListOptions, BookOwnershipHistory, BookTransfer, Checkout,
+    DeletedBook,
+};
+use kernel::model::id::{BookId, BookTransferId, CheckoutId, GroupId, UserId};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
-use kernel::model::book::event::UpdateBook;
+use kernel::model::book::event::{PatchBook, UpdateBook};
 use kernel::model::list::PaginatedList;
 use crate::model::user::{BookOwner, CheckoutUser};
+use crate::model::{not_null, nullable};
 
 #[derive(Debug, Deserialize, Validate, ToSchema)]
 #[serde(rename_all = "camelCase")]
@@ -23,6 +27,10 @@ pub struct CreateBookRequest {
 
     #[garde(skip)]
     pub description: String,
+
+    // 指定した場合はそのグループの蔵書として登録する
+    #[garde(skip)]
+    pub group_id: Option<GroupId>,
 }
 
 impl From<CreateBookRequest> for CreateBook {
@@ -32,12 +40,15 @@ impl From<CreateBookRequest> for CreateBook {
             author,
             isbn,
             description,
+            group_id,
         } = value;
         Self {
             title,
             author,
             isbn,
             description,
+            group_id,
+            impersonator: None,
         }
     }
 }
@@ -59,7 +70,7 @@ pub struct UpdateBookRequest {
 }
 
 #[derive(new)]
-pub struct UpdateBookRequestWithIds(BookId, UserId, UpdateBookRequest);
+pub struct UpdateBookRequestWithIds(BookId, UserId, Option<UserId>, bool, i64, UpdateBookRequest);
 
 impl From<UpdateBookRequestWithIds> for UpdateBook {
 
@@ -68,6 +79,9 @@ impl From<UpdateBookRequestWithIds> for UpdateBook {
         let UpdateBookRequestWithIds(
             book_id,
             user_id,
+            impersonator,
+            any_owner,
+            version,
             UpdateBookRequest {
                 title,
                 author,
@@ -83,11 +97,73 @@ impl From<UpdateBookRequestWithIds> for UpdateBook {
             isbn,
             description,
             requested_user: user_id,
+            impersonator,
+            any_owner,
+            version,
         }
     }
 
 }
 
+// application/merge-patch+json (RFC 7396) として受け取る
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchBookRequest {
+    #[garde(custom(not_null), inner(length(min = 1)))]
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    pub title: Option<Option<String>>,
+
+    #[garde(custom(not_null), inner(length(min = 1)))]
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    pub author: Option<Option<String>>,
+
+    #[garde(custom(not_null), inner(length(min = 1)))]
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    pub isbn: Option<Option<String>>,
+
+    // null が指定された場合は説明を空にする
+    #[garde(skip)]
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    pub description: Option<Option<String>>,
+}
+
+#[derive(new)]
+pub struct PatchBookRequestWithIds(BookId, UserId, Option<UserId>, bool, i64, PatchBookRequest);
+
+impl From<PatchBookRequestWithIds> for PatchBook {
+    fn from(value: PatchBookRequestWithIds) -> Self {
+        let PatchBookRequestWithIds(
+            book_id,
+            user_id,
+            impersonator,
+            any_owner,
+            version,
+            PatchBookRequest {
+                title,
+                author,
+                isbn,
+                description,
+            },
+        ) = value;
+
+        PatchBook {
+            book_id,
+            title: title.flatten(),
+            author: author.flatten(),
+            isbn: isbn.flatten(),
+            description: description.map(Option::unwrap_or_default),
+            requested_user: user_id,
+            impersonator,
+            any_owner,
+            version,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Validate)]
 pub struct BookListQuery {
     #[garde(range(min=0))]
@@ -123,6 +199,7 @@ pub struct BookResponse {
     pub description: String,
     pub owner: BookOwner,
     pub checkout: Option<BookCheckoutResponse>,
+    pub version: i64,
 }
 
 impl From<Book> for BookResponse {
@@ -135,6 +212,7 @@ impl From<Book> for BookResponse {
             description,
             owner,
             checkout,
+            version,
         } = value;
         Self {
             book_id,
@@ -144,6 +222,7 @@ impl From<Book> for BookResponse {
             description,
             owner: owner.into(),
             checkout: checkout.map(BookCheckoutResponse::from),
+            version,
         }
     }
 }
@@ -186,4 +265,138 @@ impl From<Checkout> for BookCheckoutResponse {
             checked_out_at: value.checked_out_at,
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferBookRequest {
+    pub transfer_to: UserId,
+}
+
+// null を指定するとグループの所有から外し、登録者の個人所有に戻す
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeBookGroupRequest {
+    pub group_id: Option<GroupId>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BookTransferIdResponse {
+    pub transfer_id: BookTransferId,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BookTransfersResponse {
+    pub items: Vec<BookTransferResponse>,
+}
+
+impl From<Vec<BookTransfer>> for BookTransfersResponse {
+    fn from(value: Vec<BookTransfer>) -> Self {
+        Self {
+            items: value.into_iter().map(BookTransferResponse::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BookTransferResponse {
+    pub transfer_id: BookTransferId,
+    pub book_id: BookId,
+    pub title: String,
+    pub from_user_id: UserId,
+    pub to_user_id: UserId,
+    pub requested_at: DateTime<chrono::Utc>,
+}
+
+impl From<BookTransfer> for BookTransferResponse {
+    fn from(value: BookTransfer) -> Self {
+        Self {
+            transfer_id: value.transfer_id,
+            book_id: value.book_id,
+            title: value.title,
+            from_user_id: value.from_user_id,
+            to_user_id: value.to_user_id,
+            requested_at: value.requested_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BookOwnershipHistoriesResponse {
+    pub items: Vec<BookOwnershipHistoryResponse>,
+}
+
+impl From<Vec<BookOwnershipHistory>> for BookOwnershipHistoriesResponse {
+    fn from(value: Vec<BookOwnershipHistory>) -> Self {
+        Self {
+            items: value
+                .into_iter()
+                .map(BookOwnershipHistoryResponse::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BookOwnershipHistoryResponse {
+    pub from_user_id: UserId,
+    pub to_user_id: UserId,
+    pub transferred_by: UserId,
+    pub forced: bool,
+    pub transferred_at: DateTime<chrono::Utc>,
+}
+
+impl From<BookOwnershipHistory> for BookOwnershipHistoryResponse {
+    fn from(value: BookOwnershipHistory) -> Self {
+        Self {
+            from_user_id: value.from_user_id,
+            to_user_id: value.to_user_id,
+            transferred_by: value.transferred_by,
+            forced: value.forced,
+            transferred_at: value.transferred_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedBooksResponse {
+    pub items: Vec<DeletedBookResponse>,
+}
+
+impl From<Vec<DeletedBook>> for DeletedBooksResponse {
+    fn from(value: Vec<DeletedBook>) -> Self {
+        Self {
+            items: value.into_iter().map(DeletedBookResponse::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedBookResponse {
+    pub book_id: BookId,
+    pub title: String,
+    pub author: String,
+    pub isbn: String,
+    pub owner: BookOwner,
+    pub deleted_at: DateTime<chrono::Utc>,
+}
+
+impl From<DeletedBook> for DeletedBookResponse {
+    fn from(value: DeletedBook) -> Self {
+        Self {
+            book_id: value.book_id,
+            title: value.title,
+            author: value.author,
+            isbn: value.isbn,
+            owner: value.owner.into(),
+            deleted_at: value.deleted_at,
+        }
+    }
+}
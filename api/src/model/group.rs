@@ -0,0 +1,172 @@
+use chrono::{DateTime, Utc};
+use derive_new::new;
+use garde::Validate;
+use kernel::model::{
+    group::{
+        event::{CreateGroup, UpdateGroup},
+        Group, GroupMember, GroupRole,
+    },
+    id::{GroupId, UserId},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub enum GroupRoleName {
+    Owner,
+    Member,
+}
+
+impl From<GroupRole> for GroupRoleName {
+    fn from(role: GroupRole) -> Self {
+        match role {
+            GroupRole::Owner => GroupRoleName::Owner,
+            GroupRole::Member => GroupRoleName::Member,
+        }
+    }
+}
+
+impl From<GroupRoleName> for GroupRole {
+    fn from(role: GroupRoleName) -> Self {
+        match role {
+            GroupRoleName::Owner => GroupRole::Owner,
+            GroupRoleName::Member => GroupRole::Member,
+        }
+    }
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateGroupRequest {
+    #[garde(length(min = 1))]
+    pub name: String,
+    // 省略した場合は誰にでも貸し出す
+    #[serde(default)]
+    #[garde(skip)]
+    pub members_only_lending: bool,
+}
+
+#[derive(new)]
+pub struct CreateGroupRequestWithUserId(UserId, Option<UserId>, CreateGroupRequest);
+
+impl From<CreateGroupRequestWithUserId> for CreateGroup {
+    fn from(value: CreateGroupRequestWithUserId) -> Self {
+        let CreateGroupRequestWithUserId(
+            user_id,
+            impersonator,
+            CreateGroupRequest {
+                name,
+                members_only_lending,
+            },
+        ) = value;
+        Self::new(name, members_only_lending, user_id, impersonator)
+    }
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateGroupRequest {
+    #[garde(length(min = 1))]
+    pub name: String,
+    #[garde(skip)]
+    pub members_only_lending: bool,
+}
+
+#[derive(new)]
+pub struct UpdateGroupRequestWithIds(GroupId, UserId, Option<UserId>, UpdateGroupRequest);
+
+impl From<UpdateGroupRequestWithIds> for UpdateGroup {
+    fn from(value: UpdateGroupRequestWithIds) -> Self {
+        let UpdateGroupRequestWithIds(
+            group_id,
+            user_id,
+            impersonator,
+            UpdateGroupRequest {
+                name,
+                members_only_lending,
+            },
+        ) = value;
+        Self::new(group_id, name, members_only_lending, user_id, impersonator)
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMemberRequest {
+    pub role: GroupRoleName,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupIdResponse {
+    pub group_id: GroupId,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupsResponse {
+    pub items: Vec<GroupResponse>,
+}
+
+impl From<Vec<Group>> for GroupsResponse {
+    fn from(value: Vec<Group>) -> Self {
+        Self {
+            items: value.into_iter().map(GroupResponse::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupResponse {
+    pub group_id: GroupId,
+    pub name: String,
+    pub members_only_lending: bool,
+    pub members: Vec<GroupMemberResponse>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Group> for GroupResponse {
+    fn from(value: Group) -> Self {
+        let Group {
+            group_id,
+            name,
+            members_only_lending,
+            members,
+            created_at,
+        } = value;
+        Self {
+            group_id,
+            name,
+            members_only_lending,
+            members: members.into_iter().map(GroupMemberResponse::from).collect(),
+            created_at,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMemberResponse {
+    pub user_id: UserId,
+    pub name: String,
+    pub role: GroupRoleName,
+    pub joined_at: DateTime<Utc>,
+}
+
+impl From<GroupMember> for GroupMemberResponse {
+    fn from(value: GroupMember) -> Self {
+        let GroupMember {
+            user_id,
+            name,
+            role,
+            joined_at,
+        } = value;
+        Self {
+            user_id,
+            name,
+            role: role.into(),
+            joined_at,
+        }
+    }
+}
@@ -1,4 +1,29 @@
+pub mod api_key;
+pub mod audit;
 pub mod book;
+pub mod group;
+pub mod tenant;
 pub mod auth;
 pub mod user;
 pub mod checkout;
+pub mod two_factor;
+
+use serde::{Deserialize, Deserializer};
+
+// JSON Merge Patch では null が「値の削除」を表すため、
+// 未指定（None）と null（Some(None)）を区別して受け取る
+pub(crate) fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// 削除できない（必須の）フィールドに null が指定された場合は検証エラーとする
+pub(crate) fn not_null<T>(value: &Option<Option<T>>, _ctx: &()) -> garde::Result {
+    match value {
+        Some(None) => Err(garde::Error::new("must not be null")),
+        _ => Ok(()),
+    }
+}
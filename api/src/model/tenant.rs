@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use derive_new::new;
+use garde::Validate;
+use kernel::model::{
+    id::{TenantId, UserId},
+    tenant::{event::CreateTenant, Tenant},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+// サブドメインとして使えるよう、英小文字・数字・ハイフンのみを許可する
+fn valid_slug(value: &str, _ctx: &()) -> garde::Result {
+    let valid = value
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !value.starts_with('-')
+        && !value.ends_with('-');
+    if valid {
+        Ok(())
+    } else {
+        Err(garde::Error::new(
+            "must consist of lowercase letters, digits and hyphens",
+        ))
+    }
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTenantRequest {
+    #[garde(length(min = 1, max = 63), custom(valid_slug))]
+    pub slug: String,
+    #[garde(length(min = 1))]
+    pub name: String,
+    // テナントの最初の管理者
+    #[garde(length(min = 1))]
+    pub admin_name: String,
+    #[garde(email)]
+    pub admin_email: String,
+    #[garde(length(min = 1))]
+    pub admin_password: String,
+}
+
+#[derive(new)]
+pub struct CreateTenantRequestWithUserId(UserId, Option<UserId>, CreateTenantRequest);
+
+impl From<CreateTenantRequestWithUserId> for CreateTenant {
+    fn from(value: CreateTenantRequestWithUserId) -> Self {
+        let CreateTenantRequestWithUserId(
+            user_id,
+            impersonator,
+            CreateTenantRequest {
+                slug,
+                name,
+                admin_name,
+                admin_email,
+                admin_password,
+            },
+        ) = value;
+        Self::new(
+            slug,
+            name,
+            admin_name,
+            admin_email,
+            admin_password,
+            user_id,
+            impersonator,
+        )
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantsResponse {
+    pub items: Vec<TenantResponse>,
+}
+
+impl From<Vec<Tenant>> for TenantsResponse {
+    fn from(value: Vec<Tenant>) -> Self {
+        Self {
+            items: value.into_iter().map(TenantResponse::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TenantResponse {
+    pub tenant_id: TenantId,
+    pub slug: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Tenant> for TenantResponse {
+    fn from(value: Tenant) -> Self {
+        let Tenant {
+            tenant_id,
+            slug,
+            name,
+            created_at,
+        } = value;
+        Self {
+            tenant_id,
+            slug,
+            name,
+            created_at,
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use derive_new::new;
+use garde::Validate;
+use kernel::model::{
+    id::UserId,
+    two_factor::{event::ActivateTotp, RecoveryCodes, TotpEnrollment},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorStatusResponse {
+    pub enabled: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpEnrollmentResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+impl From<TotpEnrollment> for TotpEnrollmentResponse {
+    fn from(value: TotpEnrollment) -> Self {
+        Self {
+            secret: value.secret,
+            otpauth_uri: value.otpauth_uri,
+        }
+    }
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoFactorCodeRequest {
+    #[garde(length(min = 1))]
+    pub code: String,
+}
+
+#[derive(new)]
+pub struct TwoFactorCodeRequestWithUserId(UserId, Option<UserId>, TwoFactorCodeRequest);
+
+impl From<TwoFactorCodeRequestWithUserId> for ActivateTotp {
+    fn from(value: TwoFactorCodeRequestWithUserId) -> Self {
+        let TwoFactorCodeRequestWithUserId(user_id, impersonator, TwoFactorCodeRequest { code }) =
+            value;
+        Self {
+            user_id,
+            impersonator,
+            code,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+impl From<RecoveryCodes> for RecoveryCodesResponse {
+    fn from(value: RecoveryCodes) -> Self {
+        Self {
+            recovery_codes: value.0,
+        }
+    }
+}
@@ -1,28 +1,36 @@
+use chrono::{DateTime, Utc};
 use derive_new::new;
 use garde::Validate;
 use kernel::model::{
-    id::UserId,
+    id::{GroupId, UserId},
+    list::PaginatedList,
     role::Role,
     user::{
-        event::{CreateUser, UpdateUserPassword, UpdateUserRole},
-        User,
+        event::{CreateUser, PatchUser, UpdateProfile, UpdateUserPassword, UpdateUserRole},
+        DeletedUser, User, UserListOptions, UserProfile,
     }
 };
 use serde::{Deserialize, Serialize};
 use strum::VariantNames;
 use utoipa::ToSchema;
 
+use crate::model::{not_null, nullable};
+
 #[derive(Serialize, Deserialize, VariantNames, ToSchema)]
 #[strum(serialize_all = "kebab-case")]
 pub enum RoleName {
+    SuperAdmin,
     Admin,
+    Librarian,
     User,
 }
 
 impl From<Role> for RoleName {
     fn from(role: Role) -> Self {
         match role {
+            Role::SuperAdmin => RoleName::SuperAdmin,
             Role::Admin => RoleName::Admin,
+            Role::Librarian => RoleName::Librarian,
             Role::User => RoleName::User,
         }
     }
@@ -31,16 +39,129 @@ impl From<Role> for RoleName {
 impl From<RoleName> for Role {
     fn from(role: RoleName) -> Self {
         match role {
+            RoleName::SuperAdmin => Role::SuperAdmin,
             RoleName::Admin => Role::Admin,
+            RoleName::Librarian => Role::Librarian,
             RoleName::User => Role::User,
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct UserListQuery {
+    // 名前の部分一致。管理者の場合はメールアドレスも検索する
+    #[garde(skip)]
+    pub q: Option<String>,
+    #[garde(skip)]
+    pub role: Option<RoleName>,
+    #[garde(skip)]
+    pub active: Option<bool>,
+    #[garde(range(min = 0))]
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    #[garde(range(min = 0))]
+    #[serde(default)]
+    pub offset: i64,
+}
+
+const DEFAULT_LIMIT: i64 = 20;
+const fn default_limit() -> i64 {
+    DEFAULT_LIMIT
+}
+
+// メールアドレスを参照できる利用者かどうかを添えて変換する
+#[derive(new)]
+pub struct UserListQueryWithVisibility(bool, UserListQuery);
+
+impl From<UserListQueryWithVisibility> for UserListOptions {
+    fn from(value: UserListQueryWithVisibility) -> Self {
+        let UserListQueryWithVisibility(
+            show_email,
+            UserListQuery {
+                q,
+                role,
+                active,
+                limit,
+                offset,
+            },
+        ) = value;
+        Self {
+            keyword: q.filter(|q| !q.trim().is_empty()),
+            search_email: show_email,
+            role: role.map(Role::from),
+            active,
+            limit,
+            offset,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PaginatedUserResponse {
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    pub items: Vec<UserSummaryResponse>,
+}
+
+impl PaginatedUserResponse {
+    pub fn new(list: PaginatedList<User>, show_email: bool) -> Self {
+        Self {
+            total: list.total,
+            limit: list.limit,
+            offset: list.offset,
+            items: list
+                .items
+                .into_iter()
+                .map(|user| UserSummaryResponse::new(user, show_email))
+                .collect(),
+        }
+    }
+}
+
+// 他の利用者から見た利用者の情報。メールアドレスは管理者にしか返さない
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSummaryResponse {
+    pub user_id: UserId,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    pub role: RoleName,
+    pub active: bool,
+}
+
+impl UserSummaryResponse {
+    pub fn new(user: User, show_email: bool) -> Self {
+        Self {
+            user_id: user.user_id,
+            name: user.name,
+            email: show_email.then_some(user.email),
+            role: RoleName::from(user.role),
+            active: user.active,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
-pub struct UsersResponse {
-    pub items: Vec<UserResponse>,
+pub struct UserProfileResponse {
+    #[serde(flatten)]
+    pub user: UserSummaryResponse,
+    pub checkout_count: i64,
+    pub returned_count: i64,
+}
+
+impl UserProfileResponse {
+    pub fn new(profile: UserProfile, show_email: bool) -> Self {
+        Self {
+            user: UserSummaryResponse::new(profile.user, show_email),
+            checkout_count: profile.checkout_count,
+            returned_count: profile.returned_count,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -50,6 +171,8 @@ pub struct UserResponse {
     pub name: String,
     pub email: String,
     pub role: RoleName,
+    pub active: bool,
+    pub version: i64,
 }
 
 impl From<User> for UserResponse {
@@ -59,6 +182,42 @@ impl From<User> for UserResponse {
             name: user.name,
             email: user.email,
             role: RoleName::from(user.role),
+            active: user.active,
+            version: user.version,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedUsersResponse {
+    pub items: Vec<DeletedUserResponse>,
+}
+
+impl From<Vec<DeletedUser>> for DeletedUsersResponse {
+    fn from(value: Vec<DeletedUser>) -> Self {
+        Self {
+            items: value.into_iter().map(DeletedUserResponse::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedUserResponse {
+    pub user_id: UserId,
+    pub name: String,
+    pub email: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+impl From<DeletedUser> for DeletedUserResponse {
+    fn from(user: DeletedUser) -> Self {
+        Self {
+            user_id: user.user_id,
+            name: user.name,
+            email: user.email,
+            deleted_at: user.deleted_at,
         }
     }
 }
@@ -74,6 +233,71 @@ pub struct CreateUserRequest {
     password: String,
 }
 
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProfileRequest {
+    #[garde(length(min = 1))]
+    name: String,
+    #[garde(email)]
+    email: String,
+}
+
+#[derive(new)]
+pub struct UpdateProfileRequestWithUserId(UserId, Option<UserId>, i64, UpdateProfileRequest);
+
+impl From<UpdateProfileRequestWithUserId> for UpdateProfile {
+    fn from(value: UpdateProfileRequestWithUserId) -> Self {
+        let UpdateProfileRequestWithUserId(
+            user_id,
+            impersonator,
+            version,
+            UpdateProfileRequest { name, email },
+        ) = value;
+        Self {
+            user_id,
+            impersonator,
+            name,
+            email,
+            version,
+        }
+    }
+}
+
+// application/merge-patch+json (RFC 7396) として受け取る
+#[derive(Deserialize, Validate, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchUserRequest {
+    #[garde(custom(not_null), inner(length(min = 1)))]
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    name: Option<Option<String>>,
+    #[garde(custom(not_null), inner(email))]
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    email: Option<Option<String>>,
+}
+
+#[derive(new)]
+pub struct PatchUserRequestWithUserId(UserId, Option<UserId>, i64, PatchUserRequest);
+
+impl From<PatchUserRequestWithUserId> for PatchUser {
+    fn from(value: PatchUserRequestWithUserId) -> Self {
+        let PatchUserRequestWithUserId(
+            user_id,
+            impersonator,
+            version,
+            PatchUserRequest { name, email },
+        ) = value;
+        Self {
+            user_id,
+            impersonator,
+            name: name.flatten(),
+            email: email.flatten(),
+            version,
+        }
+    }
+}
+
 impl From<CreateUserRequest> for CreateUser {
     
     fn from(request: CreateUserRequest) -> Self {
@@ -81,6 +305,9 @@ impl From<CreateUserRequest> for CreateUser {
             name: request.name,
             email: request.email,
             password: request.password,
+            requested_user: None,
+            impersonator: None,
+            email_verified: true,
         }
     }
 }
@@ -129,16 +356,22 @@ pub struct UpdateUserRoleRequest {
 }
 
 #[derive(new)]
-pub struct UpdateUserRoleRequestWithUserId (
+pub struct UpdateUserRoleRequestWithIds (
     UserId,
+    UserId,
+    Option<UserId>,
+    i64,
     UpdateUserRoleRequest
 );
 
-impl From<UpdateUserRoleRequestWithUserId> for UpdateUserRole {
+impl From<UpdateUserRoleRequestWithIds> for UpdateUserRole {
     
-    fn from(request: UpdateUserRoleRequestWithUserId) -> Self {
-        let UpdateUserRoleRequestWithUserId(
+    fn from(request: UpdateUserRoleRequestWithIds) -> Self {
+        let UpdateUserRoleRequestWithIds(
             user_id,
+            requested_user,
+            impersonator,
+            version,
             UpdateUserRoleRequest {
                 role,
             },
@@ -147,6 +380,9 @@ impl From<UpdateUserRoleRequestWithUserId> for UpdateUserRole {
         Self {
             user_id,
             role: Role::from(role),
+            requested_user,
+            impersonator,
+            version,
         }
     }
 }
@@ -156,6 +392,7 @@ impl From<UpdateUserRoleRequestWithUserId> for UpdateUserRole {
 pub struct BookOwner {
     pub id: UserId,
     pub name: String,
+    pub group: Option<BookOwnerGroup>,
 }
 
 impl From<kernel::model::user::BookOwner> for BookOwner {
@@ -163,10 +400,21 @@ impl From<kernel::model::user::BookOwner> for BookOwner {
         Self {
             id: book_owner.user_id,
             name: book_owner.name,
+            group: book_owner.group.map(|group| BookOwnerGroup {
+                id: group.group_id,
+                name: group.name,
+            }),
         }
     }   
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BookOwnerGroup {
+    pub id: GroupId,
+    pub name: String,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct CheckoutUser {
@@ -17,27 +17,129 @@ use crate::{handler, model};
         handler::book::show_book,
         handler::book::register_book,
         handler::book::update_book,
+        handler::book::patch_book,
         handler::book::delete_book,
+        handler::book::request_book_transfer,
+        handler::book::accept_book_transfer,
+        handler::book::cancel_book_transfer,
+        handler::book::force_book_transfer,
+        handler::book::change_book_group,
+        handler::book::show_my_book_transfers,
+        handler::book::ownership_history,
+        handler::book::show_deleted_book_list,
+        handler::book::restore_book,
+        handler::group::create_group,
+        handler::group::show_my_groups,
+        handler::group::show_group,
+        handler::group::update_group,
+        handler::group::delete_group,
+        handler::group::put_group_member,
+        handler::group::remove_group_member,
+        handler::tenant::create_tenant,
+        handler::tenant::show_tenant_list,
         handler::checkout::checkout_book,
         handler::checkout::return_book,
         handler::checkout::checkout_history,
+        handler::user::list_users,
+        handler::user::show_user,
         handler::user::get_current_user,
+        handler::user::update_current_user,
+        handler::user::patch_current_user,
+        handler::user::list_my_sessions,
+        handler::user::delete_my_session,
+        handler::user::delete_my_sessions,
+        handler::user::delete_user_sessions,
+        handler::user::unlock_user,
+        handler::user::list_deleted_users,
+        handler::user::restore_user,
+        handler::user::deactivate_user,
+        handler::user::reactivate_user,
+        handler::user::impersonate_user,
+        handler::api_key::create_api_key,
+        handler::api_key::list_api_keys,
+        handler::api_key::revoke_api_key,
+        handler::two_factor::show_two_factor_status,
+        handler::two_factor::start_totp_enrollment,
+        handler::two_factor::activate_totp,
+        handler::two_factor::disable_my_two_factor,
+        handler::two_factor::disable_user_two_factor,
+        handler::audit::show_audit_log,
         handler::auth::login,
+        handler::auth::login_two_factor,
         handler::auth::logout,
+        handler::auth::oidc_login,
+        handler::auth::oidc_callback,
+        handler::auth::refresh,
+        handler::auth::sign_up,
+        handler::auth::verify_email,
+        handler::auth::request_password_reset,
+        handler::auth::confirm_password_reset,
     ),
     components(schemas(
         model::book::CreateBookRequest,
         model::book::UpdateBookRequest,
+        model::book::PatchBookRequest,
         model::book::BookResponse,
         model::book::PaginatedBookResponse,
         model::book::BookCheckoutResponse,
+        model::book::TransferBookRequest,
+        model::book::ChangeBookGroupRequest,
+        model::book::BookTransferIdResponse,
+        model::book::BookTransfersResponse,
+        model::book::BookTransferResponse,
+        model::book::BookOwnershipHistoriesResponse,
+        model::book::BookOwnershipHistoryResponse,
+        model::book::DeletedBooksResponse,
+        model::book::DeletedBookResponse,
+        model::group::GroupRoleName,
+        model::group::CreateGroupRequest,
+        model::group::UpdateGroupRequest,
+        model::group::GroupMemberRequest,
+        model::group::GroupIdResponse,
+        model::group::GroupsResponse,
+        model::group::GroupResponse,
+        model::group::GroupMemberResponse,
+        model::tenant::CreateTenantRequest,
+        model::tenant::TenantsResponse,
+        model::tenant::TenantResponse,
         model::checkout::CheckoutsResponse,
         model::checkout::CheckoutResponse,
         model::checkout::CheckoutBookResponse,
+        model::user::UpdateProfileRequest,
+        model::user::PatchUserRequest,
+        model::user::DeletedUsersResponse,
+        model::user::DeletedUserResponse,
+        model::user::PaginatedUserResponse,
+        model::user::UserSummaryResponse,
+        model::user::UserProfileResponse,
+        model::auth::ImpersonationTokenResponse,
         model::user::BookOwner,
+        model::user::BookOwnerGroup,
         model::user::CheckoutUser,
+        model::api_key::ApiKeyScopeName,
+        model::api_key::CreateApiKeyRequest,
+        model::api_key::ApiKeysResponse,
+        model::api_key::ApiKeyResponse,
+        model::api_key::CreatedApiKeyResponse,
+        model::two_factor::TwoFactorStatusResponse,
+        model::two_factor::TotpEnrollmentResponse,
+        model::two_factor::TwoFactorCodeRequest,
+        model::two_factor::RecoveryCodesResponse,
+        model::audit::AuditActionName,
+        model::audit::AuditTargetName,
+        model::audit::AuditLogResponse,
+        model::audit::PaginatedAuditLogResponse,
         model::auth::LoginRequest,
         model::auth::AccessTokenResponse,
+        model::auth::RefreshTokenRequest,
+        model::auth::LoginChallengeResponse,
+        model::auth::LoginTwoFactorRequest,
+        model::auth::SignUpRequest,
+        model::auth::VerifyEmailRequest,
+        model::auth::PasswordResetRequest,
+        model::auth::ConfirmPasswordResetRequest,
+        model::auth::SessionsResponse,
+        model::auth::SessionResponse,
     ))
 )]
 pub struct ApiDoc;
\ No newline at end of file
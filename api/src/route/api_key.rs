@@ -0,0 +1,12 @@
+use crate::handler::api_key::{create_api_key, list_api_keys, revoke_api_key};
+use axum::{
+    Router,
+    routing::{delete, get},
+};
+use registry::AppRegistry;
+
+pub fn build_api_key_routers() -> Router<AppRegistry> {
+    Router::new()
+        .route("/users/me/api-keys", get(list_api_keys).post(create_api_key))
+        .route("/users/me/api-keys/{api_key_id}", delete(revoke_api_key))
+}
@@ -0,0 +1,7 @@
+use crate::handler::audit::show_audit_log;
+use axum::{Router, routing::get};
+use registry::AppRegistry;
+
+pub fn build_audit_log_routers() -> Router<AppRegistry> {
+    Router::new().route("/audit-log", get(show_audit_log))
+}
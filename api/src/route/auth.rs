@@ -1,11 +1,22 @@
-use axum::{routing::post, Router};
+use axum::{routing::{get, post, put}, Router};
 use registry::AppRegistry;
 
-use crate::handler::auth::{login, logout};
+use crate::handler::auth::{
+    confirm_password_reset, login, login_two_factor, logout, oidc_callback, oidc_login, refresh,
+    request_password_reset, sign_up, verify_email,
+};
 
 pub fn routes() -> Router<AppRegistry> {
     let auth_router = Router::new()
         .route("/login", post(login))
-        .route("/logout", post(logout));
+        .route("/login/two-factor", post(login_two_factor))
+        .route("/logout", post(logout))
+        .route("/oidc/login", get(oidc_login))
+        .route("/oidc/callback", get(oidc_callback))
+        .route("/refresh", post(refresh))
+        .route("/signup", post(sign_up))
+        .route("/email-verification", post(verify_email))
+        .route("/password-reset", post(request_password_reset))
+        .route("/password-reset/confirmed", put(confirm_password_reset));
     Router::new().nest("/auth", auth_router)
 }
\ No newline at end of file
@@ -1,7 +1,11 @@
-use crate::handler::book::{delete_book, register_book, show_book, show_book_list, update_book};
+use crate::handler::book::{
+    accept_book_transfer, cancel_book_transfer, change_book_group, delete_book, force_book_transfer,
+    ownership_history, register_book, request_book_transfer, restore_book, show_book,
+    patch_book, show_book_list, show_deleted_book_list, show_my_book_transfers, update_book,
+};
 use axum::{
     Router,
-    routing::{delete, get, post, put},
+    routing::{delete, get, patch, post, put},
 };
 use registry::AppRegistry;
 use crate::handler::checkout::
@@ -14,7 +18,10 @@ pub fn build_book_routers() -> Router<AppRegistry> {
         .route("/", get(show_book_list))
         .route("/{id}", get(show_book))
         .route("/{id}", put(update_book))
-        .route("/{id}", delete(delete_book));
+        .route("/{id}", patch(patch_book))
+        .route("/{id}", delete(delete_book))
+        .route("/deleted", get(show_deleted_book_list))
+        .route("/{id}/restored", put(restore_book));
 
     let checkout_routers = Router::new()
         .route("/checkouts", get(show_checked_out_list))
@@ -23,5 +30,17 @@ pub fn build_book_routers() -> Router<AppRegistry> {
         .route("/{book_id}/checkouts/{checkout_id}/returned", put(return_book))
         .route("/{book_id}/checkout-history", get(checkout_history));
 
-    Router::new().nest("/books", book_routers.merge(checkout_routers))
+    let transfer_routers = Router::new()
+        .route("/transfers/me", get(show_my_book_transfers))
+        .route("/{book_id}/transfers", post(request_book_transfer))
+        .route("/{book_id}/transfers/{transfer_id}", delete(cancel_book_transfer))
+        .route("/{book_id}/transfers/{transfer_id}/accepted", put(accept_book_transfer))
+        .route("/{book_id}/owner", put(force_book_transfer))
+        .route("/{book_id}/group", put(change_book_group))
+        .route("/{book_id}/ownership-history", get(ownership_history));
+
+    Router::new().nest(
+        "/books",
+        book_routers.merge(checkout_routers).merge(transfer_routers),
+    )
 }
@@ -0,0 +1,20 @@
+use crate::handler::group::{
+    create_group, delete_group, put_group_member, remove_group_member, show_group,
+    show_my_groups, update_group,
+};
+use axum::{
+    Router,
+    routing::{get, post, put},
+};
+use registry::AppRegistry;
+
+pub fn build_group_routers() -> Router<AppRegistry> {
+    Router::new()
+        .route("/groups", post(create_group))
+        .route("/groups/me", get(show_my_groups))
+        .route("/groups/{group_id}", get(show_group).put(update_group).delete(delete_group))
+        .route(
+            "/groups/{group_id}/members/{user_id}",
+            put(put_group_member).delete(remove_group_member),
+        )
+}
@@ -1,5 +1,10 @@
+pub mod api_key;
+pub mod audit;
 pub mod book;
+pub mod group;
 pub mod health;
+pub mod tenant;
 pub mod auth;
+pub mod two_factor;
 pub mod user;
 pub mod v1;
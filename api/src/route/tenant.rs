@@ -0,0 +1,7 @@
+use crate::handler::tenant::{create_tenant, show_tenant_list};
+use axum::{Router, routing::get};
+use registry::AppRegistry;
+
+pub fn build_tenant_routers() -> Router<AppRegistry> {
+    Router::new().route("/tenants", get(show_tenant_list).post(create_tenant))
+}
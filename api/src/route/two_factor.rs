@@ -0,0 +1,21 @@
+use crate::handler::two_factor::{
+    activate_totp, disable_my_two_factor, disable_user_two_factor, show_two_factor_status,
+    start_totp_enrollment,
+};
+use axum::{
+    Router,
+    routing::{delete, get, put},
+};
+use registry::AppRegistry;
+
+pub fn build_two_factor_routers() -> Router<AppRegistry> {
+    Router::new()
+        .route(
+            "/users/me/two-factor",
+            get(show_two_factor_status)
+                .post(start_totp_enrollment)
+                .delete(disable_my_two_factor),
+        )
+        .route("/users/me/two-factor/activated", put(activate_totp))
+        .route("/users/{user_id}/two-factor", delete(disable_user_two_factor))
+}
@@ -1,17 +1,28 @@
 use crate::handler::user::{
-    change_password, change_role, list_users, delete_user, get_current_user, register_user,
+    change_password, change_role, deactivate_user, delete_my_session, delete_my_sessions,
+    delete_user, delete_user_sessions, get_current_user, impersonate_user, list_deleted_users,
+    list_my_sessions, list_users, patch_current_user, reactivate_user, register_user,
+    restore_user, show_user, unlock_user, update_current_user,
 };
 use axum::{
     Router,
-    routing::{delete, get, put},
+    routing::{delete, get, post, put},
 };
 use registry::AppRegistry;
 
 pub fn build_user_routers() -> Router<AppRegistry> {
     Router::new()
-        .route("/users/me", get(get_current_user))
+        .route("/users/me", get(get_current_user).put(update_current_user).patch(patch_current_user))
         .route("/users/me/password", put(change_password))
+        .route("/users/me/sessions", get(list_my_sessions).delete(delete_my_sessions))
+        .route("/users/me/sessions/{session_id}", delete(delete_my_session))
         .route("/users", get(list_users).post(register_user))
-        .route("/users/{user_id}", delete(delete_user))
+        .route("/users/deleted", get(list_deleted_users))
+        .route("/users/{user_id}", get(show_user).delete(delete_user))
+        .route("/users/{user_id}/restored", put(restore_user))
+        .route("/users/{user_id}/deactivated", put(deactivate_user).delete(reactivate_user))
+        .route("/users/{user_id}/impersonation", post(impersonate_user))
         .route("/users/{user_id}/role", put(change_role))
+        .route("/users/{user_id}/sessions", delete(delete_user_sessions))
+        .route("/users/{user_id}/lockout", delete(unlock_user))
 }
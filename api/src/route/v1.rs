@@ -1,7 +1,12 @@
 use axum::Router;
 use registry::AppRegistry;
+use crate::route::api_key::build_api_key_routers;
+use crate::route::audit::build_audit_log_routers;
 use crate::route::book::build_book_routers;
+use crate::route::group::build_group_routers;
 use crate::route::health::build_health_check_routers;
+use crate::route::tenant::build_tenant_routers;
+use crate::route::two_factor::build_two_factor_routers;
 use crate::route::user::build_user_routers;
 
 pub fn routes() -> Router<AppRegistry> {
@@ -9,7 +14,12 @@ pub fn routes() -> Router<AppRegistry> {
     let router = Router::new()
         .merge(build_health_check_routers())
         .merge(build_book_routers())
-        .merge(build_user_routers());
+        .merge(build_group_routers())
+        .merge(build_tenant_routers())
+        .merge(build_user_routers())
+        .merge(build_api_key_routers())
+        .merge(build_two_factor_routers())
+        .merge(build_audit_log_routers());
 
     Router::new().nest("/api/v1", router)
 }
\ No newline at end of file
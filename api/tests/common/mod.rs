@@ -0,0 +1,213 @@
+// 結合テストで共有する、ルーターとテスト用の設定の組み立て
+#![allow(dead_code)]
+
+use adapter::{database::ConnectionPool, redis::RedisClient};
+use api::route::{auth, v1};
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use kernel::model::{
+    auth::{event::CreateToken, ClientInfo},
+    id::{TenantId, UserId},
+    user::event::CreateUser,
+};
+use registry::AppRegistry;
+use serde_json::Value;
+use shared::config::{
+    AppConfig, AuthConfig, DatabaseConfig, LoginThrottleConfig, MailConfig, PasswordHashAlgorithm,
+    PasswordHashConfig, PasswordPolicyConfig, ProxyConfig, PurgeConfig, RedisConfig, SignupConfig,
+    TenantConfig, TwoFactorConfig,
+};
+use tower::ServiceExt;
+
+pub fn app_config() -> AppConfig {
+    AppConfig {
+        database: DatabaseConfig {
+            host: "localhost".into(),
+            port: 5432,
+            username: "app".into(),
+            password: "passwd".into(),
+            database: "app".into(),
+        },
+        redis: RedisConfig {
+            host: "localhost".into(),
+            port: 6379,
+        },
+        auth: AuthConfig {
+            ttl: 3600,
+            password_reset_ttl: 3600,
+            sliding_expiration: false,
+            max_lifetime: 604800,
+            impersonation_ttl: 900,
+            jwt: None,
+        },
+        purge: PurgeConfig {
+            retention_days: 30,
+            interval_secs: 3600,
+        },
+        signup: SignupConfig {
+            enabled: false,
+            allowed_domains: Vec::new(),
+            verification_ttl: 86400,
+        },
+        two_factor: TwoFactorConfig {
+            issuer: "Book API".into(),
+            challenge_ttl: 300,
+        },
+        login_throttle: LoginThrottleConfig {
+            max_attempts: 5,
+            ip_max_attempts: 50,
+            attempt_window: 900,
+            lockout_duration: 900,
+            max_delay: 30,
+        },
+        oidc: None,
+        ldap: None,
+        password_hash: PasswordHashConfig {
+            algorithm: PasswordHashAlgorithm::Bcrypt,
+            argon2_memory_kib: 19456,
+            argon2_iterations: 2,
+            argon2_parallelism: 1,
+            bcrypt_cost: 4,
+        },
+        password_policy: PasswordPolicyConfig {
+            min_length: 1,
+            min_character_classes: 0,
+            disallow_personal_info: false,
+            history_size: 0,
+            reject_common: false,
+        },
+        tenant: TenantConfig {
+            base_domain: None,
+            default_slug: Some("default".into()),
+        },
+        mail: MailConfig {
+            smtp: None,
+            log_body: false,
+        },
+        proxy: ProxyConfig {
+            trusted_proxies: Vec::new(),
+        },
+    }
+}
+
+pub struct TestApp {
+    pub router: Router,
+    pub registry: AppRegistry,
+    pub pool: sqlx::PgPool,
+    pub tenant_id: TenantId,
+}
+
+impl TestApp {
+    pub async fn new(pool: sqlx::PgPool) -> anyhow::Result<Self> {
+        Self::with_config(pool, app_config()).await
+    }
+
+    pub async fn with_config(pool: sqlx::PgPool, config: AppConfig) -> anyhow::Result<Self> {
+        let registry = AppRegistry::new(
+            ConnectionPool::new(pool.clone()),
+            RedisClient::local(),
+            config,
+        );
+        let router = Router::new()
+            .merge(v1::routes())
+            .merge(auth::routes())
+            .with_state(registry.clone());
+        let tenant_id = registry
+            .tenant_repository()
+            .find_by_slug("default")
+            .await?
+            .unwrap()
+            .tenant_id;
+        Ok(Self {
+            router,
+            registry,
+            pool,
+            tenant_id,
+        })
+    }
+
+    // 指定したロールの利用者を作り、ログインした状態のアクセストークンを返す
+    pub async fn login_as(&self, email: &str, role: &str) -> anyhow::Result<(UserId, String)> {
+        let user = self
+            .registry
+            .user_repository()
+            .create(self.tenant_id, CreateUser {
+                name: "Test User".into(),
+                email: email.into(),
+                password: "test_password".into(),
+                requested_user: None,
+                impersonator: None,
+                email_verified: true,
+            })
+            .await?;
+        self.set_role(user.user_id, role).await?;
+        let tokens = self
+            .registry
+            .auth_repository()
+            .create_token(CreateToken::new(user.user_id, ClientInfo::default()))
+            .await?;
+        Ok((user.user_id, tokens.access_token.0))
+    }
+
+    // ロールを直接書き換える。リポジトリを通さないため、発行済みのトークンは失効しない
+    pub async fn set_role(&self, user_id: UserId, role: &str) -> anyhow::Result<()> {
+        sqlx::query!(
+            "UPDATE users SET role_id = (SELECT role_id FROM roles WHERE name = $1) WHERE user_id = $2",
+            role,
+            user_id as _
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn send(
+        &self,
+        method: Method,
+        uri: &str,
+        token: &str,
+        body: Option<Value>,
+    ) -> anyhow::Result<(StatusCode, Value)> {
+        self.request(method, uri, Some(token), body).await
+    }
+
+    // ログインなど、トークンを持たずに呼ぶエンドポイント用
+    pub async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> anyhow::Result<(StatusCode, Value)> {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(header::IF_MATCH, "\"1\"")
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+        let res = self.router.clone().oneshot(request.body(body)?).await?;
+        let status = res.status();
+        let bytes = axum::body::to_bytes(res.into_body(), usize::MAX).await?;
+        let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        Ok((status, body))
+    }
+
+    pub async fn impersonate(&self, admin_token: &str, user_id: UserId) -> anyhow::Result<String> {
+        let (status, body) = self
+            .send(
+                Method::POST,
+                &format!("/api/v1/users/{user_id}/impersonation"),
+                admin_token,
+                None,
+            )
+            .await?;
+        assert_eq!(status, StatusCode::CREATED);
+        Ok(body["accessToken"].as_str().unwrap().to_string())
+    }
+}
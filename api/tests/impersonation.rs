@@ -0,0 +1,150 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+use serde_json::json;
+
+#[sqlx::test(migrations = "../adapter/migrations")]
+async fn test_only_admins_can_impersonate(pool: sqlx::PgPool) -> anyhow::Result<()> {
+    let app = TestApp::new(pool).await?;
+    let (admin_id, admin_token) = app.login_as("admin@example.com", "Admin").await?;
+    let (user_id, user_token) = app.login_as("user@example.com", "User").await?;
+    let (_, librarian_token) = app.login_as("librarian@example.com", "Librarian").await?;
+
+    for token in [&user_token, &librarian_token] {
+        let (status, _) = app
+            .send(
+                Method::POST,
+                &format!("/api/v1/users/{admin_id}/impersonation"),
+                token,
+                None,
+            )
+            .await?;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    // 代理ログイン中の管理者は、さらに別の利用者として振る舞えない
+    let token = app.impersonate(&admin_token, user_id).await?;
+    let (status, body) = app.send(Method::GET, "/api/v1/users/me", &token, None).await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["userId"], json!(user_id));
+    let (status, _) = app
+        .send(
+            Method::POST,
+            &format!("/api/v1/users/{admin_id}/impersonation"),
+            &token,
+            None,
+        )
+        .await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../adapter/migrations")]
+async fn test_impersonation_cannot_take_over_account(pool: sqlx::PgPool) -> anyhow::Result<()> {
+    let app = TestApp::new(pool).await?;
+    let (_, admin_token) = app.login_as("admin@example.com", "Admin").await?;
+    let (user_id, _) = app.login_as("user@example.com", "User").await?;
+    let token = app.impersonate(&admin_token, user_id).await?;
+
+    let forbidden = [
+        (
+            Method::PUT,
+            "/api/v1/users/me/password",
+            Some(json!({ "currentPassword": "test_password", "newPassword": "new_password" })),
+        ),
+        (
+            Method::POST,
+            "/api/v1/users/me/api-keys",
+            Some(json!({ "name": "takeover", "scopes": ["Read", "Write"] })),
+        ),
+        (
+            Method::PATCH,
+            "/api/v1/users/me",
+            Some(json!({ "email": "attacker@example.com" })),
+        ),
+        (
+            Method::PUT,
+            "/api/v1/users/me",
+            Some(json!({ "name": "Test User", "email": "attacker@example.com" })),
+        ),
+        (Method::DELETE, "/api/v1/users/me/sessions", None),
+    ];
+    for (method, uri, body) in forbidden {
+        let (status, _) = app.send(method.clone(), uri, &token, body).await?;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{method} {uri}");
+    }
+
+    // メールアドレスを変えない更新はできる
+    let (status, body) = app
+        .send(
+            Method::PATCH,
+            "/api/v1/users/me",
+            &token,
+            Some(json!({ "name": "Renamed User" })),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["email"], "user@example.com");
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../adapter/migrations")]
+async fn test_impersonation_ends_when_admin_loses_access(pool: sqlx::PgPool) -> anyhow::Result<()> {
+    let app = TestApp::new(pool).await?;
+    let (admin_id, admin_token) = app.login_as("admin@example.com", "Admin").await?;
+    let (user_id, _) = app.login_as("user@example.com", "User").await?;
+
+    // 権限を失った管理者のトークンは使えない
+    let token = app.impersonate(&admin_token, user_id).await?;
+    app.set_role(admin_id, "Librarian").await?;
+    let (status, _) = app.send(Method::GET, "/api/v1/users/me", &token, None).await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    app.set_role(admin_id, "Admin").await?;
+    let (status, _) = app.send(Method::GET, "/api/v1/users/me", &token, None).await?;
+    assert_eq!(status, StatusCode::OK);
+
+    // 無効化された管理者のトークンも使えない
+    sqlx::query!("UPDATE users SET active = FALSE WHERE user_id = $1", admin_id as _)
+        .execute(&app.pool)
+        .await?;
+    let (status, _) = app.send(Method::GET, "/api/v1/users/me", &token, None).await?;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "../adapter/migrations")]
+async fn test_impersonated_changes_are_audited(pool: sqlx::PgPool) -> anyhow::Result<()> {
+    let app = TestApp::new(pool).await?;
+    let (admin_id, admin_token) = app.login_as("admin@example.com", "Admin").await?;
+    let (user_id, _) = app.login_as("user@example.com", "User").await?;
+    let token = app.impersonate(&admin_token, user_id).await?;
+
+    let (status, _) = app
+        .send(
+            Method::PATCH,
+            "/api/v1/users/me",
+            &token,
+            Some(json!({ "name": "Renamed User" })),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK);
+
+    // 操作者は代理された利用者、実際に操作した管理者は impersonator_id に残る
+    let row = sqlx::query!(
+        r#"
+            SELECT actor_id, impersonator_id FROM audit_logs
+            WHERE target_type = 'User' AND target_id = $1 AND action = 'Update'
+        "#,
+        user_id as _
+    )
+    .fetch_one(&app.pool)
+    .await?;
+    assert_eq!(row.actor_id, Some(user_id.raw()));
+    assert_eq!(row.impersonator_id, Some(admin_id.raw()));
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use std::time::Duration;
+
+use axum::http::{Method, StatusCode};
+use common::{app_config, TestApp};
+use serde_json::json;
+
+#[sqlx::test(migrations = "../adapter/migrations")]
+async fn test_two_factor_failures_lock_account(pool: sqlx::PgPool) -> anyhow::Result<()> {
+    let mut config = app_config();
+    config.login_throttle.max_attempts = 3;
+    config.login_throttle.max_delay = 1;
+    let app = TestApp::with_config(pool, config).await?;
+    let (user_id, _) = app.login_as("user@example.com", "User").await?;
+    sqlx::query!(
+        "INSERT INTO user_totp (user_id, secret, enabled_at) VALUES ($1, $2, CURRENT_TIMESTAMP(3))",
+        user_id as _,
+        "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP"
+    )
+    .execute(&app.pool)
+    .await?;
+
+    // パスワードが正しくても、二要素目を誤り続ければロックされる
+    for _ in 0..3 {
+        let (status, body) = app
+            .request(
+                Method::POST,
+                "/auth/login",
+                None,
+                Some(json!({ "email": "user@example.com", "password": "test_password" })),
+            )
+            .await?;
+        assert_eq!(status, StatusCode::ACCEPTED);
+
+        let (status, _) = app
+            .request(
+                Method::POST,
+                "/auth/login/two-factor",
+                None,
+                Some(json!({ "challengeToken": body["challengeToken"], "code": "wrong-code" })),
+            )
+            .await?;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // 失敗ごとの待ち時間が過ぎるのを待つ
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+    }
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/auth/login",
+            None,
+            Some(json!({ "email": "user@example.com", "password": "test_password" })),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::LOCKED);
+
+    Ok(())
+}
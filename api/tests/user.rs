@@ -0,0 +1,48 @@
+mod common;
+
+use axum::http::{Method, StatusCode};
+use common::TestApp;
+
+#[sqlx::test(migrations = "../adapter/migrations")]
+async fn test_admin_cannot_remove_super_admin(pool: sqlx::PgPool) -> anyhow::Result<()> {
+    let app = TestApp::new(pool).await?;
+    let (_, admin_token) = app.login_as("admin@example.com", "Admin").await?;
+    let (super_admin_id, super_admin_token) =
+        app.login_as("super@example.com", "SuperAdmin").await?;
+    let (user_id, _) = app.login_as("user@example.com", "User").await?;
+
+    // テナントの管理者は SuperAdmin をサインアウトさせたり削除したりできない
+    for uri in [
+        format!("/api/v1/users/{super_admin_id}/sessions"),
+        format!("/api/v1/users/{super_admin_id}"),
+    ] {
+        let (status, _) = app.send(Method::DELETE, &uri, &admin_token, None).await?;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{uri}");
+    }
+    let (status, _) = app
+        .send(Method::GET, "/api/v1/users/me", &super_admin_token, None)
+        .await?;
+    assert_eq!(status, StatusCode::OK);
+
+    // 一般の利用者に対してはこれまでどおり操作できる
+    let (status, _) = app
+        .send(
+            Method::DELETE,
+            &format!("/api/v1/users/{user_id}/sessions"),
+            &admin_token,
+            None,
+        )
+        .await?;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = app
+        .send(
+            Method::DELETE,
+            &format!("/api/v1/users/{user_id}"),
+            &admin_token,
+            None,
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK);
+
+    Ok(())
+}
@@ -1,18 +1,29 @@
 INSERT INTO
     roles (name)
 VALUES
+    ('SuperAdmin'),
     ('Admin'),
+    ('Librarian'),
     ('User')
     ON CONFLICT DO NOTHING;
 
 INSERT INTO
-    users (name, email, password_hash, role_id)
+    tenants (slug, name)
+VALUES
+    ('default', 'Default')
+    ON CONFLICT DO NOTHING;
+
+INSERT INTO
+    users (tenant_id, name, email, password_hash, role_id)
 SELECT
+    t.tenant_id,
     'Eleazar Fig',
     'eleazar.fig@example.com',
     '$2b$12$GFf.eB7OpIcB3hpCr/JhoOOVPHQ0YE9oLnDA0KyHq7oGBvAFospLK',
     r.role_id
 FROM
-    roles as r
+    roles as r,
+    tenants as t
 WHERE
-    r.name = 'Admin';
\ No newline at end of file
+    r.name = 'SuperAdmin'
+    AND t.slug = 'default';
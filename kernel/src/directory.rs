@@ -0,0 +1,13 @@
+use async_trait::async_trait;
+use shared::error::AppResult;
+
+use crate::model::identity::ExternalIdentity;
+
+#[async_trait]
+pub trait Directory: Send + Sync {
+    // ユーザーとの紐づけに使う、ディレクトリを識別する名前
+    fn provider(&self) -> &str;
+    // 資格情報が誤っている場合は None を返す
+    async fn authenticate(&self, login: &str, password: &str)
+        -> AppResult<Option<ExternalIdentity>>;
+}
@@ -1,2 +1,4 @@
+pub mod directory;
+pub mod mailer;
 pub mod model;
 pub mod repository;
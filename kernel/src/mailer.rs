@@ -0,0 +1,15 @@
+use async_trait::async_trait;
+use derive_new::new;
+use shared::error::AppResult;
+
+#[derive(Debug, new)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, mail: Mail) -> AppResult<()>;
+}
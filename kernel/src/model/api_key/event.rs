@@ -0,0 +1,22 @@
+use chrono::{DateTime, Utc};
+use derive_new::new;
+
+use crate::model::{
+    api_key::ApiKeyScope,
+    id::{ApiKeyId, UserId},
+};
+
+#[derive(new)]
+pub struct CreateApiKey {
+    pub user_id: UserId,
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(new)]
+pub struct RevokeApiKey {
+    pub api_key_id: ApiKeyId,
+    pub user_id: UserId,
+    pub impersonator: Option<UserId>,
+}
@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use strum::{AsRefStr, EnumIter, EnumString};
+
+use crate::model::id::{ApiKeyId, UserId};
+
+pub mod event;
+
+// Read は参照系（GET / HEAD）のリクエストのみ、Write は更新系のリクエストも許可する
+#[derive(Debug, Clone, Copy, EnumString, AsRefStr, EnumIter, PartialEq, Eq)]
+pub enum ApiKeyScope {
+    Read,
+    Write,
+}
+
+#[derive(Debug)]
+pub struct ApiKey {
+    pub api_key_id: ApiKeyId,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// 発行直後のみ、平文のキーを呼び出し元に返す
+#[derive(Debug)]
+pub struct CreatedApiKey {
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+// API キーで認証されたリクエストの情報
+#[derive(Debug)]
+pub struct AuthorizedApiKey {
+    pub api_key_id: ApiKeyId,
+    pub user_id: UserId,
+    pub scopes: Vec<ApiKeyScope>,
+}
+
+impl AuthorizedApiKey {
+    // Write を持つキーは参照系のリクエストも許可する
+    pub fn allows(&self, scope: ApiKeyScope) -> bool {
+        self.scopes
+            .iter()
+            .any(|s| *s == scope || *s == ApiKeyScope::Write)
+    }
+}
@@ -0,0 +1,20 @@
+use derive_new::new;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::model::{
+    audit::{AuditAction, AuditTarget},
+    id::UserId,
+};
+
+#[derive(Debug, new)]
+pub struct CreateAuditLog {
+    pub actor_id: Option<UserId>,
+    // 代理ログイン中の操作の場合、実際に操作した管理者
+    pub impersonator_id: Option<UserId>,
+    pub action: AuditAction,
+    pub target_type: AuditTarget,
+    pub target_id: Uuid,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
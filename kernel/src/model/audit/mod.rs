@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use strum::{AsRefStr, EnumIter, EnumString};
+use uuid::Uuid;
+
+use crate::model::id::{AuditLogId, UserId};
+
+pub mod event;
+
+#[derive(Debug, Clone, Copy, EnumString, AsRefStr, EnumIter, PartialEq, Eq)]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(Debug, Clone, Copy, EnumString, AsRefStr, EnumIter, PartialEq, Eq)]
+pub enum AuditTarget {
+    Book,
+    BookTransfer,
+    User,
+    Checkout,
+    AccessToken,
+    ApiKey,
+    TwoFactor,
+    Group,
+    Tenant,
+}
+
+#[derive(Debug)]
+pub struct AuditLog {
+    pub audit_log_id: AuditLogId,
+    pub actor_id: Option<UserId>,
+    // 代理ログイン中の操作の場合、実際に操作した管理者
+    pub impersonator_id: Option<UserId>,
+    pub action: AuditAction,
+    pub target_type: AuditTarget,
+    pub target_id: Uuid,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct AuditLogListOptions {
+    pub actor_id: Option<UserId>,
+    pub action: Option<AuditAction>,
+    pub target_type: Option<AuditTarget>,
+    pub target_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: i64,
+    pub offset: i64,
+}
@@ -1,14 +1,61 @@
-use crate::model::id::UserId;
+use crate::model::{
+    auth::{ClientInfo, PasswordResetToken},
+    id::UserId,
+};
 use uuid::Uuid;
 
 pub struct CreateToken {
     pub id: UserId,
     pub access_token: String,
+    pub client: ClientInfo,
 }
 
 impl CreateToken {
-    pub fn new(id: UserId) -> Self {
+    pub fn new(id: UserId, client: ClientInfo) -> Self {
         let access_token = Uuid::new_v4().simple().to_string();
-        Self {id, access_token }
+        Self {id, access_token, client }
     }   
 }
+
+pub struct CreateImpersonationToken {
+    pub impersonator: UserId,
+    pub user_id: UserId,
+    pub access_token: String,
+}
+
+impl CreateImpersonationToken {
+    pub fn new(impersonator: UserId, user_id: UserId) -> Self {
+        let access_token = Uuid::new_v4().simple().to_string();
+        Self { impersonator, user_id, access_token }
+    }
+}
+
+pub struct CreateEmailVerification {
+    pub user_id: UserId,
+    pub email: String,
+    pub token: String,
+}
+
+impl CreateEmailVerification {
+    pub fn new(user_id: UserId, email: String) -> Self {
+        let token = Uuid::new_v4().simple().to_string();
+        Self { user_id, email, token }
+    }
+}
+
+pub struct CreatePasswordReset {
+    pub email: String,
+    pub token: String,
+}
+
+impl CreatePasswordReset {
+    pub fn new(email: String) -> Self {
+        let token = Uuid::new_v4().simple().to_string();
+        Self { email, token }
+    }
+}
+
+pub struct ResetPassword {
+    pub token: PasswordResetToken,
+    pub new_password: String,
+}
\ No newline at end of file
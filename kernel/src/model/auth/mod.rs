@@ -1,3 +1,66 @@
 pub mod event;
 
-pub struct AccessToken(pub String);
\ No newline at end of file
+use chrono::{DateTime, Utc};
+
+use crate::model::id::{SessionId, TenantId, UserId};
+
+pub struct AccessToken(pub String);
+
+pub struct RefreshToken(pub String);
+
+// アクセストークンの持ち主。代理ログインのトークンでは、実際に操作している管理者も持つ
+pub struct TokenSubject {
+    pub user_id: UserId,
+    pub impersonator: Option<UserId>,
+}
+
+// 管理者が利用者として操作するための短命のアクセストークン
+pub struct ImpersonationToken {
+    pub access_token: AccessToken,
+    pub expires_in: u64,
+}
+
+// JWT モードの場合のみリフレッシュトークンを発行する
+pub struct AuthTokens {
+    pub user_id: UserId,
+    pub access_token: AccessToken,
+    pub refresh_token: Option<RefreshToken>,
+}
+
+// ログイン元の端末を識別するための情報
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+// ログインごとに作られるセッション。JWT モードではリフレッシュトークンの系列に対応する
+#[derive(Debug)]
+pub struct Session {
+    pub session_id: SessionId,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub client: ClientInfo,
+}
+
+pub struct EmailVerificationToken(pub String);
+
+pub struct PasswordResetToken(pub String);
+
+// ログインの試行。失敗回数はメールアドレスと接続元 IP アドレスのそれぞれで数える
+pub struct LoginAttempt {
+    pub tenant_id: TenantId,
+    pub email: String,
+    pub ip_address: Option<String>,
+}
+
+impl LoginAttempt {
+    pub fn new(tenant_id: TenantId, email: &str, ip_address: Option<String>) -> Self {
+        Self {
+            tenant_id,
+            email: email.trim().to_lowercase(),
+            ip_address,
+        }
+    }
+}
@@ -1,10 +1,16 @@
-use crate::model::id::{BookId, UserId};
+use chrono::{DateTime, Utc};
+use derive_new::new;
+
+use crate::model::id::{BookId, BookTransferId, GroupId, UserId};
 
 pub struct CreateBook {
     pub title: String,
     pub author: String,
     pub isbn: String,
     pub description: String,
+    // 指定した場合はそのグループの所有になる。登録者がメンバーである必要がある
+    pub group_id: Option<GroupId>,
+    pub impersonator: Option<UserId>,
 }
 
 #[derive(Debug)]
@@ -14,11 +20,96 @@ pub struct UpdateBook {
     pub author: String,
     pub isbn: String,
     pub description: String,   
-    pub requested_user: UserId
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+    // 所有者以外でも操作できる権限を持つ
+    pub any_owner: bool,
+    pub version: i64,
+}
+
+// 指定されたフィールドのみを更新する（None は変更なし）
+#[derive(Debug)]
+pub struct PatchBook {
+    pub book_id: BookId,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub isbn: Option<String>,
+    pub description: Option<String>,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+    pub any_owner: bool,
+    pub version: i64,
 }
 
 #[derive(Debug)]
 pub struct DeleteBook {
     pub book_id: BookId,
-    pub requested_user: UserId
-}
\ No newline at end of file
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+    pub any_owner: bool,
+    pub version: i64,
+}
+
+// 操作対象の蔵書に求める所有者。any_owner の場合は所有者を問わないため None を返す
+macro_rules! impl_owner {
+    ($($event:ty),*) => {
+        $(impl $event {
+            pub fn owner(&self) -> Option<UserId> {
+                (!self.any_owner).then_some(self.requested_user)
+            }
+        })*
+    };
+}
+
+// group_id が None の場合は個人の所有に戻す
+#[derive(Debug, new)]
+pub struct ChangeBookGroup {
+    pub book_id: BookId,
+    pub group_id: Option<GroupId>,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+    pub any_owner: bool,
+}
+
+impl_owner!(UpdateBook, PatchBook, DeleteBook, ChangeBookGroup);
+
+#[derive(Debug, new)]
+pub struct RestoreBook {
+    pub book_id: BookId,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+}
+#[derive(Debug, new)]
+pub struct RequestBookTransfer {
+    pub book_id: BookId,
+    pub transfer_to: UserId,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+    pub requested_at: DateTime<Utc>,
+}
+
+#[derive(Debug, new)]
+pub struct AcceptBookTransfer {
+    pub transfer_id: BookTransferId,
+    pub book_id: BookId,
+    pub accepted_user: UserId,
+    pub impersonator: Option<UserId>,
+    pub accepted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, new)]
+pub struct CancelBookTransfer {
+    pub transfer_id: BookTransferId,
+    pub book_id: BookId,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+}
+
+#[derive(Debug, new)]
+pub struct ForceBookTransfer {
+    pub book_id: BookId,
+    pub transfer_to: UserId,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+    pub transferred_at: DateTime<Utc>,
+}
@@ -1,6 +1,6 @@
 
 use chrono::{DateTime, Utc};
-use crate::model::id::{BookId, CheckoutId};
+use crate::model::id::{BookId, BookTransferId, CheckoutId, UserId};
 use crate::model::user::{BookOwner, CheckoutUser};
 
 pub mod event;
@@ -14,6 +14,17 @@ pub struct Book {
     pub description: String,
     pub owner: BookOwner,
     pub checkout: Option<Checkout>,
+    pub version: i64,
+}
+
+#[derive(Debug)]
+pub struct DeletedBook {
+    pub book_id: BookId,
+    pub title: String,
+    pub author: String,
+    pub isbn: String,
+    pub owner: BookOwner,
+    pub deleted_at: DateTime<Utc>,
 }
 
 #[derive(Debug)]
@@ -27,4 +38,24 @@ pub struct Checkout {
     pub checkout_id: CheckoutId,
     pub checked_out_by: CheckoutUser,
     pub checked_out_at: DateTime<Utc>,
-}
\ No newline at end of file
+}
+
+#[derive(Debug)]
+pub struct BookTransfer {
+    pub transfer_id: BookTransferId,
+    pub book_id: BookId,
+    pub title: String,
+    pub from_user_id: UserId,
+    pub to_user_id: UserId,
+    pub requested_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct BookOwnershipHistory {
+    pub book_id: BookId,
+    pub from_user_id: UserId,
+    pub to_user_id: UserId,
+    pub transferred_by: UserId,
+    pub forced: bool,
+    pub transferred_at: DateTime<Utc>,
+}
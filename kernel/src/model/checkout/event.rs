@@ -7,6 +7,7 @@ use crate::model::id::{BookId, CheckoutId, UserId};
 pub struct CreateCheckout {
     pub book_id: BookId,
     pub checked_out_by: UserId,
+    pub impersonator: Option<UserId>,
     pub checked_out_at: DateTime<Utc>,
 }
 
@@ -15,5 +16,8 @@ pub struct UpdateReturned {
     pub checkout_id: CheckoutId,
     pub book_id: BookId,
     pub returned_by: UserId,
+    pub impersonator: Option<UserId>,
     pub returned_at: DateTime<Utc>,
+    // 借りた本人以外による返却を認める
+    pub force: bool,
 }
\ No newline at end of file
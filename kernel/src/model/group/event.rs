@@ -0,0 +1,50 @@
+use derive_new::new;
+
+use crate::model::{
+    group::GroupRole,
+    id::{GroupId, UserId},
+};
+
+// 作成した利用者が最初の Owner になる
+#[derive(new)]
+pub struct CreateGroup {
+    pub name: String,
+    pub members_only_lending: bool,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+}
+
+#[derive(new)]
+pub struct UpdateGroup {
+    pub group_id: GroupId,
+    pub name: String,
+    pub members_only_lending: bool,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+}
+
+#[derive(new)]
+pub struct DeleteGroup {
+    pub group_id: GroupId,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+}
+
+// メンバーでなければ追加し、メンバーであればロールを変更する
+#[derive(new)]
+pub struct UpsertGroupMember {
+    pub group_id: GroupId,
+    pub user_id: UserId,
+    pub role: GroupRole,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+}
+
+// Owner は任意のメンバーを、メンバーは自分自身を外せる
+#[derive(new)]
+pub struct RemoveGroupMember {
+    pub group_id: GroupId,
+    pub user_id: UserId,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+}
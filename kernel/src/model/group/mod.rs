@@ -0,0 +1,37 @@
+use chrono::{DateTime, Utc};
+use strum::{AsRefStr, EnumIter, EnumString};
+
+use crate::model::id::{GroupId, UserId};
+
+pub mod event;
+
+// Owner はグループの設定とメンバーを管理できる。蔵書の編集はどちらのロールでもできる
+#[derive(Debug, Clone, Copy, EnumString, AsRefStr, EnumIter, PartialEq, Eq)]
+pub enum GroupRole {
+    Owner,
+    Member,
+}
+
+#[derive(Debug)]
+pub struct Group {
+    pub group_id: GroupId,
+    pub name: String,
+    pub members_only_lending: bool,
+    pub members: Vec<GroupMember>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct GroupMember {
+    pub user_id: UserId,
+    pub name: String,
+    pub role: GroupRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+// 蔵書を所有するグループ
+#[derive(Debug)]
+pub struct BookGroup {
+    pub group_id: GroupId,
+    pub name: String,
+}
@@ -62,3 +62,9 @@ macro_rules! define_id {
 define_id!(UserId);
 define_id!(BookId);
 define_id!(CheckoutId);
+define_id!(BookTransferId);
+define_id!(AuditLogId);
+define_id!(SessionId);
+define_id!(ApiKeyId);
+define_id!(GroupId);
+define_id!(TenantId);
@@ -0,0 +1,9 @@
+// 外部の ID プロバイダやディレクトリで認証された利用者の情報。subject はプロバイダ内で一意な識別子
+#[derive(Debug, Clone)]
+pub struct ExternalIdentity {
+    pub subject: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub groups: Vec<String>,
+}
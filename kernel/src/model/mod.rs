@@ -1,8 +1,15 @@
+pub mod api_key;
+pub mod audit;
 pub mod book;
+pub mod group;
 pub mod id;
+pub mod identity;
 pub mod auth;
 pub mod role;
+pub mod tenant;
 pub mod user;
 pub mod list;
 pub mod checkout;
+pub mod oidc;
+pub mod two_factor;
 pub mod util;
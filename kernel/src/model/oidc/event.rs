@@ -0,0 +1,7 @@
+use derive_new::new;
+
+#[derive(new)]
+pub struct CompleteOidcLogin {
+    pub code: String,
+    pub state: String,
+}
@@ -0,0 +1,7 @@
+pub mod event;
+
+// ID プロバイダへ誘導するための URL。state は同じ URL で一度しか使えない
+#[derive(Debug)]
+pub struct OidcAuthorization {
+    pub authorization_url: String,
+}
@@ -1,8 +1,29 @@
 use strum::{AsRefStr, EnumIter, EnumString};
 
-#[derive(Debug, EnumString, AsRefStr, EnumIter, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, EnumString, AsRefStr, EnumIter, Default, PartialEq, Eq, Hash)]
 pub enum Role {
+    // テナントの作成ができる。テナントをまたいだ操作はできない
+    SuperAdmin,
     Admin,
+    Librarian,
     #[default]
     User,
 }
+
+// ロールに付与される権限。role_permissions テーブルには文字列表現で保存する
+#[derive(Debug, Clone, Copy, EnumString, AsRefStr, EnumIter, PartialEq, Eq, Hash)]
+pub enum Permission {
+    // 他人が所有する蔵書の更新・削除・復元と強制的な所有権の移転
+    #[strum(serialize = "book:write:any")]
+    BookWriteAny,
+    // 利用者の登録・削除・ロール変更などの管理操作
+    #[strum(serialize = "user:manage")]
+    UserManage,
+    // 他人が借りている蔵書の返却
+    #[strum(serialize = "checkout:force-return")]
+    CheckoutForceReturn,
+    #[strum(serialize = "audit:read")]
+    AuditRead,
+    #[strum(serialize = "tenant:manage")]
+    TenantManage,
+}
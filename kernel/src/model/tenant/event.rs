@@ -0,0 +1,15 @@
+use derive_new::new;
+
+use crate::model::id::UserId;
+
+// テナントと同時に、そのテナントの最初の管理者を作成する
+#[derive(new)]
+pub struct CreateTenant {
+    pub slug: String,
+    pub name: String,
+    pub admin_name: String,
+    pub admin_email: String,
+    pub admin_password: String,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+}
@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+
+use crate::model::id::TenantId;
+
+pub mod event;
+
+// 図書館ごとのテナント。利用者・蔵書・貸出はいずれか一つのテナントに所属する
+#[derive(Debug, Clone)]
+pub struct Tenant {
+    pub tenant_id: TenantId,
+    pub slug: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
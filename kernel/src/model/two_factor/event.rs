@@ -0,0 +1,30 @@
+use derive_new::new;
+
+use crate::model::id::{TenantId, UserId};
+
+#[derive(new)]
+pub struct StartTotpEnrollment {
+    pub user_id: UserId,
+    pub account_name: String,
+}
+
+#[derive(new)]
+pub struct CreateLoginChallenge {
+    pub user_id: UserId,
+    pub tenant_id: TenantId,
+    pub email: String,
+}
+
+#[derive(new)]
+pub struct ActivateTotp {
+    pub user_id: UserId,
+    pub impersonator: Option<UserId>,
+    pub code: String,
+}
+
+#[derive(new)]
+pub struct DisableTwoFactor {
+    pub user_id: UserId,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+}
@@ -0,0 +1,25 @@
+pub mod event;
+
+use crate::model::id::{TenantId, UserId};
+
+// 認証アプリに登録するための情報
+#[derive(Debug)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+// 有効化時に一度だけ平文で返すリカバリーコード
+#[derive(Debug)]
+pub struct RecoveryCodes(pub Vec<String>);
+
+// パスワード認証に成功した後、二要素目の確認を待つ間だけ有効なトークン
+pub struct LoginChallengeToken(pub String);
+
+// 二要素目の確認を待っているログイン。コードの誤りはパスワードと同じメールアドレスで数える
+#[derive(Debug)]
+pub struct LoginChallenge {
+    pub user_id: UserId,
+    pub tenant_id: TenantId,
+    pub email: String,
+}
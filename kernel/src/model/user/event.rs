@@ -5,12 +5,19 @@ pub struct CreateUser {
     pub name: String,
     pub email: String,
     pub password: String,
+    pub requested_user: Option<UserId>,
+    pub impersonator: Option<UserId>,
+    // 管理者による登録は確認済み、セルフサインアップは未確認として作成する
+    pub email_verified: bool,
 }
 
 #[derive(Debug)]
 pub struct UpdateUserRole {
     pub user_id: UserId,
     pub role: Role,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+    pub version: i64,
 }
 
 #[derive(Debug)]
@@ -20,7 +27,49 @@ pub struct UpdateUserPassword {
     pub new_password: String,
 }
 
+#[derive(Debug)]
+pub struct UpdateProfile {
+    pub user_id: UserId,
+    pub impersonator: Option<UserId>,
+    pub name: String,
+    pub email: String,
+    pub version: i64,
+}
+
+// 指定されたフィールドのみを更新する（None は変更なし）
+#[derive(Debug)]
+pub struct PatchUser {
+    pub user_id: UserId,
+    pub impersonator: Option<UserId>,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub version: i64,
+}
+
 #[derive(Debug)]
 pub struct DeleteUser {
     pub user_id: UserId,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+}
+
+#[derive(Debug)]
+pub struct RestoreUser {
+    pub user_id: UserId,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+}
+
+#[derive(Debug)]
+pub struct DeactivateUser {
+    pub user_id: UserId,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
+}
+
+#[derive(Debug)]
+pub struct ReactivateUser {
+    pub user_id: UserId,
+    pub requested_user: UserId,
+    pub impersonator: Option<UserId>,
 }
\ No newline at end of file
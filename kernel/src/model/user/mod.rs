@@ -1,4 +1,6 @@
-use crate::model::{id::UserId, role::Role};
+use chrono::{DateTime, Utc};
+
+use crate::model::{group::BookGroup, id::UserId, role::Role};
 
 pub mod event;
 
@@ -8,12 +10,44 @@ pub struct User {
     pub name: String,
     pub email: String,
     pub role: Role,
+    // 無効化された利用者はログインできない
+    pub active: bool,
+    pub version: i64,
+}
+
+#[derive(Debug)]
+pub struct UserListOptions {
+    // 名前の部分一致で絞り込む。search_email の場合はメールアドレスも対象にする
+    pub keyword: Option<String>,
+    pub search_email: bool,
+    pub role: Option<Role>,
+    pub active: Option<bool>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+#[derive(Debug)]
+pub struct UserProfile {
+    pub user: User,
+    // 貸出中の冊数と、これまでに返却した冊数
+    pub checkout_count: i64,
+    pub returned_count: i64,
+}
+
+#[derive(Debug)]
+pub struct DeletedUser {
+    pub user_id: UserId,
+    pub name: String,
+    pub email: String,
+    pub deleted_at: DateTime<Utc>,
 }
 
 #[derive(Debug)]
 pub struct BookOwner {
     pub user_id: UserId,
     pub name: String,
+    // グループが所有する蔵書の場合はそのグループ
+    pub group: Option<BookGroup>,
 }
 
 #[derive(Debug)]
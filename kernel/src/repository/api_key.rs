@@ -0,0 +1,19 @@
+use async_trait::async_trait;
+use shared::error::AppResult;
+
+use crate::model::{
+    api_key::{
+        event::{CreateApiKey, RevokeApiKey},
+        ApiKey, AuthorizedApiKey, CreatedApiKey,
+    },
+    id::UserId,
+};
+
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn create(&self, event: CreateApiKey) -> AppResult<CreatedApiKey>;
+    async fn find_by_user_id(&self, user_id: UserId) -> AppResult<Vec<ApiKey>>;
+    async fn revoke(&self, event: RevokeApiKey) -> AppResult<()>;
+    // 有効なキーであれば最終利用日時を更新して返す
+    async fn authenticate(&self, key: &str) -> AppResult<Option<AuthorizedApiKey>>;
+}
@@ -0,0 +1,13 @@
+use async_trait::async_trait;
+use shared::error::AppResult;
+
+use crate::model::{
+    audit::{AuditLog, AuditLogListOptions},
+    id::TenantId,
+    list::PaginatedList,
+};
+
+#[async_trait]
+pub trait AuditLogRepository: Send + Sync {
+    async fn find_all(&self, tenant_id: TenantId, options: AuditLogListOptions) -> AppResult<PaginatedList<AuditLog>>;
+}
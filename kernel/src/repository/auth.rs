@@ -2,20 +2,28 @@ use async_trait::async_trait;
 use shared::error::AppResult;
 
 use crate::model::{
-    auth::{event::CreateToken, AccessToken},
-    id::UserId,
+    auth::{
+        event::{
+            CreateEmailVerification, CreateImpersonationToken, CreatePasswordReset, CreateToken,
+            ResetPassword,
+        },
+        AccessToken, AuthTokens, ClientInfo, EmailVerificationToken, ImpersonationToken,
+        PasswordResetToken, RefreshToken, Session, TokenSubject,
+    },
+    id::{SessionId, TenantId, UserId},
 };
 
 #[async_trait]
 pub trait AuthRepository: Send + Sync {
 
-    async fn fetch_user_id_from_token(
+    async fn fetch_token_subject(
         &self,
         access_token: &AccessToken
-    ) -> AppResult<Option<UserId>>;
+    ) -> AppResult<Option<TokenSubject>>;
 
     async fn verify_user (
         &self,
+        tenant_id: TenantId,
         email: &str,
         password: &str
     ) -> AppResult<UserId>;
@@ -23,11 +31,61 @@ pub trait AuthRepository: Send + Sync {
     async fn create_token(
         &self,
         event: CreateToken
-    ) -> AppResult<AccessToken>;
+    ) -> AppResult<AuthTokens>;
+
+    // 代理ログインのトークンはリフレッシュできず、有効期限が来たら使えなくなる
+    async fn create_impersonation_token(
+        &self,
+        event: CreateImpersonationToken
+    ) -> AppResult<ImpersonationToken>;
+
+    async fn refresh_token(
+        &self,
+        refresh_token: RefreshToken,
+        client: ClientInfo
+    ) -> AppResult<AuthTokens>;
 
     async fn delete_token(
         &self,
         access_token: AccessToken
     ) -> AppResult<()>;
 
+    async fn create_email_verification(
+        &self,
+        event: CreateEmailVerification
+    ) -> AppResult<EmailVerificationToken>;
+
+    async fn verify_email(
+        &self,
+        token: &EmailVerificationToken
+    ) -> AppResult<UserId>;
+
+    // 該当するユーザーがいない場合は None を返す
+    async fn create_password_reset(
+        &self,
+        tenant_id: TenantId,
+        event: CreatePasswordReset
+    ) -> AppResult<Option<PasswordResetToken>>;
+
+    async fn reset_password(
+        &self,
+        event: ResetPassword
+    ) -> AppResult<()>;
+
+    async fn delete_all_tokens(
+        &self,
+        user_id: UserId
+    ) -> AppResult<()>;
+
+    async fn find_sessions(
+        &self,
+        user_id: UserId
+    ) -> AppResult<Vec<Session>>;
+
+    async fn delete_session(
+        &self,
+        user_id: UserId,
+        session_id: SessionId
+    ) -> AppResult<()>;
+
 }
\ No newline at end of file
@@ -1,18 +1,39 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use shared::error::AppResult;
 
-use crate::model::book::{Book, event::CreateBook, BookListOptions};
-use crate::model::book::event::{DeleteBook, UpdateBook};
-use crate::model::id::{BookId, UserId};
+use crate::model::book::{
+    Book, event::CreateBook, BookListOptions, BookOwnershipHistory, BookTransfer, DeletedBook,
+};
+use crate::model::book::event::{
+    AcceptBookTransfer, CancelBookTransfer, ChangeBookGroup, DeleteBook, ForceBookTransfer, PatchBook,
+    RequestBookTransfer, RestoreBook, UpdateBook,
+};
+use crate::model::id::{BookId, BookTransferId, TenantId, UserId};
 use crate::model::list::PaginatedList;
 
 #[async_trait]
 pub trait BookRepository: Send + Sync {
-    async fn create(&self, event: CreateBook, user_id: UserId) -> AppResult<()>;
-    async fn find_all(&self, options: BookListOptions) -> AppResult<PaginatedList<Book>>;
-    async fn find_by_id(&self, book_id: BookId) -> AppResult<Option<Book>>;
+    async fn create(&self, tenant_id: TenantId, event: CreateBook, user_id: UserId) -> AppResult<()>;
+    async fn find_all(&self, tenant_id: TenantId, options: BookListOptions) -> AppResult<PaginatedList<Book>>;
+    async fn find_by_id(&self, tenant_id: TenantId, book_id: BookId) -> AppResult<Option<Book>>;
 
-    async fn update(&self, event: UpdateBook) -> AppResult<()>;
+    async fn update(&self, tenant_id: TenantId, event: UpdateBook) -> AppResult<()>;
 
-    async fn delete(&self, event: DeleteBook) -> AppResult<()>;
+    async fn patch(&self, tenant_id: TenantId, event: PatchBook) -> AppResult<()>;
+
+    async fn delete(&self, tenant_id: TenantId, event: DeleteBook) -> AppResult<()>;
+
+    async fn change_group(&self, tenant_id: TenantId, event: ChangeBookGroup) -> AppResult<()>;
+
+    async fn find_all_deleted(&self, tenant_id: TenantId) -> AppResult<Vec<DeletedBook>>;
+    async fn restore(&self, tenant_id: TenantId, event: RestoreBook) -> AppResult<()>;
+    async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> AppResult<u64>;
+
+    async fn request_transfer(&self, tenant_id: TenantId, event: RequestBookTransfer) -> AppResult<BookTransferId>;
+    async fn accept_transfer(&self, tenant_id: TenantId, event: AcceptBookTransfer) -> AppResult<()>;
+    async fn cancel_transfer(&self, tenant_id: TenantId, event: CancelBookTransfer) -> AppResult<()>;
+    async fn force_transfer(&self, tenant_id: TenantId, event: ForceBookTransfer) -> AppResult<()>;
+    async fn find_transfers_by_user_id(&self, tenant_id: TenantId, user_id: UserId) -> AppResult<Vec<BookTransfer>>;
+    async fn find_ownership_history(&self, tenant_id: TenantId, book_id: BookId) -> AppResult<Vec<BookOwnershipHistory>>;
 }
@@ -3,16 +3,16 @@ use crate::model::{
         event::{CreateCheckout, UpdateReturned},
         Checkout,
     },
-    id::{BookId, UserId},
+    id::{BookId, TenantId, UserId},
 };
 use async_trait::async_trait;
 use shared::error::AppResult;
 
 #[async_trait]
 pub trait CheckoutRepository: Send + Sync {
-    async fn create(&self, event: CreateCheckout) -> AppResult<()>;
-    async fn update_returned(&self, event: UpdateReturned) -> AppResult<()>;
-    async fn find_unreturned_all(&self) -> AppResult<Vec<Checkout>>;
-    async fn find_unreturned_by_user_id(&self, user_id: UserId) -> AppResult<Vec<Checkout>>;
-    async fn find_history_by_book_id(&self, book_id: BookId) -> AppResult<Vec<Checkout>>;
+    async fn create(&self, tenant_id: TenantId, event: CreateCheckout) -> AppResult<()>;
+    async fn update_returned(&self, tenant_id: TenantId, event: UpdateReturned) -> AppResult<()>;
+    async fn find_unreturned_all(&self, tenant_id: TenantId) -> AppResult<Vec<Checkout>>;
+    async fn find_unreturned_by_user_id(&self, tenant_id: TenantId, user_id: UserId) -> AppResult<Vec<Checkout>>;
+    async fn find_history_by_book_id(&self, tenant_id: TenantId, book_id: BookId) -> AppResult<Vec<Checkout>>;
 }
\ No newline at end of file
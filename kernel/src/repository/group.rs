@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+use shared::error::AppResult;
+
+use crate::model::{
+    group::{
+        event::{CreateGroup, DeleteGroup, RemoveGroupMember, UpdateGroup, UpsertGroupMember},
+        Group,
+    },
+    id::{GroupId, TenantId, UserId},
+};
+
+#[async_trait]
+pub trait GroupRepository: Send + Sync {
+    async fn create(&self, tenant_id: TenantId, event: CreateGroup) -> AppResult<GroupId>;
+    async fn find_by_id(&self, tenant_id: TenantId, group_id: GroupId) -> AppResult<Option<Group>>;
+    // 利用者が所属するグループの一覧
+    async fn find_by_user_id(&self, tenant_id: TenantId, user_id: UserId) -> AppResult<Vec<Group>>;
+    async fn update(&self, tenant_id: TenantId, event: UpdateGroup) -> AppResult<()>;
+    async fn delete(&self, tenant_id: TenantId, event: DeleteGroup) -> AppResult<()>;
+    async fn upsert_member(&self, tenant_id: TenantId, event: UpsertGroupMember) -> AppResult<()>;
+    async fn remove_member(&self, tenant_id: TenantId, event: RemoveGroupMember) -> AppResult<()>;
+}
@@ -0,0 +1,13 @@
+use async_trait::async_trait;
+use shared::error::AppResult;
+
+use crate::model::{auth::LoginAttempt, id::TenantId};
+
+#[async_trait]
+pub trait LoginAttemptRepository: Send + Sync {
+    // ロック中または待ち時間中であればエラーを返す
+    async fn check(&self, attempt: &LoginAttempt) -> AppResult<()>;
+    async fn record_failure(&self, attempt: &LoginAttempt) -> AppResult<()>;
+    async fn record_success(&self, attempt: &LoginAttempt) -> AppResult<()>;
+    async fn unlock(&self, tenant_id: TenantId, email: &str) -> AppResult<()>;
+}
@@ -1,5 +1,13 @@
+pub mod api_key;
+pub mod audit;
 pub mod book;
+pub mod group;
 pub mod health;
+pub mod login_attempt;
 pub mod auth;
 pub mod user;
-pub mod checkout;
\ No newline at end of file
+pub mod checkout;
+pub mod oidc;
+pub mod role;
+pub mod tenant;
+pub mod two_factor;
\ No newline at end of file
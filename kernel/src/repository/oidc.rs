@@ -0,0 +1,15 @@
+use async_trait::async_trait;
+use shared::error::AppResult;
+
+use crate::model::{
+    id::{TenantId, UserId},
+    oidc::{event::CompleteOidcLogin, OidcAuthorization},
+};
+
+#[async_trait]
+pub trait OidcRepository: Send + Sync {
+    // PKCE の検証値と nonce を保存し、認可エンドポイントの URL を返す
+    async fn start_login(&self, tenant_id: TenantId) -> AppResult<OidcAuthorization>;
+    // 認可コードを ID トークンと交換し、対応するユーザーを返す。未登録の場合は作成する
+    async fn complete_login(&self, tenant_id: TenantId, event: CompleteOidcLogin) -> AppResult<UserId>;
+}
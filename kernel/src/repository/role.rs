@@ -0,0 +1,9 @@
+use async_trait::async_trait;
+use shared::error::AppResult;
+
+use crate::model::role::{Permission, Role};
+
+#[async_trait]
+pub trait RoleRepository: Send + Sync {
+    async fn find_permissions(&self, role: Role) -> AppResult<Vec<Permission>>;
+}
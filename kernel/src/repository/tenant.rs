@@ -0,0 +1,15 @@
+use async_trait::async_trait;
+use shared::error::AppResult;
+
+use crate::model::{
+    id::TenantId,
+    tenant::{event::CreateTenant, Tenant},
+};
+
+#[async_trait]
+pub trait TenantRepository: Send + Sync {
+    async fn create(&self, event: CreateTenant) -> AppResult<Tenant>;
+    async fn find_by_slug(&self, slug: &str) -> AppResult<Option<Tenant>>;
+    async fn find_by_id(&self, tenant_id: TenantId) -> AppResult<Option<Tenant>>;
+    async fn find_all(&self) -> AppResult<Vec<Tenant>>;
+}
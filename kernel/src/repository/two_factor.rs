@@ -0,0 +1,30 @@
+use async_trait::async_trait;
+use shared::error::AppResult;
+
+use crate::model::{
+    id::UserId,
+    two_factor::{
+        event::{ActivateTotp, CreateLoginChallenge, DisableTwoFactor, StartTotpEnrollment},
+        LoginChallenge, LoginChallengeToken, RecoveryCodes, TotpEnrollment,
+    },
+};
+
+#[async_trait]
+pub trait TwoFactorRepository: Send + Sync {
+    async fn is_enabled(&self, user_id: UserId) -> AppResult<bool>;
+    async fn start_enrollment(&self, event: StartTotpEnrollment) -> AppResult<TotpEnrollment>;
+    async fn activate(&self, event: ActivateTotp) -> AppResult<RecoveryCodes>;
+    // TOTP コードまたは未使用のリカバリーコードであれば true を返す
+    async fn verify_code(&self, user_id: UserId, code: &str) -> AppResult<bool>;
+    async fn disable(&self, event: DisableTwoFactor) -> AppResult<()>;
+
+    async fn create_login_challenge(
+        &self,
+        event: CreateLoginChallenge,
+    ) -> AppResult<LoginChallengeToken>;
+    // 取得と同時に削除する
+    async fn take_login_challenge(
+        &self,
+        token: &LoginChallengeToken,
+    ) -> AppResult<Option<LoginChallenge>>;
+}
@@ -1,20 +1,33 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use shared::error::AppResult;
 
 use crate::model::{
-    id::UserId,
+    id::{TenantId, UserId},
+    list::PaginatedList,
     user::{
-        User,
-        event::{CreateUser, DeleteUser, UpdateUserPassword, UpdateUserRole},
+        DeletedUser, User, UserListOptions, UserProfile,
+        event::{
+            CreateUser, DeactivateUser, DeleteUser, PatchUser, ReactivateUser, RestoreUser,
+            UpdateProfile, UpdateUserPassword, UpdateUserRole,
+        },
     },
 };
 
 #[async_trait]
 pub trait UserRepository: Send + Sync {
-    async fn find_current_user(&self, current_user_id: UserId) -> AppResult<Option<User>>;
-    async fn find_all(&self) -> AppResult<Vec<User>>;
-    async fn create(&self, event: CreateUser) -> AppResult<User>;
-    async fn update_password(&self, event: UpdateUserPassword) -> AppResult<()>;
-    async fn update_role(&self, event: UpdateUserRole) -> AppResult<()>;
-    async fn delete(&self, event: DeleteUser) -> AppResult<()>;
+    async fn find_current_user(&self, tenant_id: TenantId, current_user_id: UserId) -> AppResult<Option<User>>;
+    async fn find_all(&self, tenant_id: TenantId, options: UserListOptions) -> AppResult<PaginatedList<User>>;
+    async fn find_profile(&self, tenant_id: TenantId, user_id: UserId) -> AppResult<Option<UserProfile>>;
+    async fn create(&self, tenant_id: TenantId, event: CreateUser) -> AppResult<User>;
+    async fn update_password(&self, tenant_id: TenantId, event: UpdateUserPassword) -> AppResult<()>;
+    async fn update_role(&self, tenant_id: TenantId, event: UpdateUserRole) -> AppResult<()>;
+    async fn update_profile(&self, tenant_id: TenantId, event: UpdateProfile) -> AppResult<User>;
+    async fn patch(&self, tenant_id: TenantId, event: PatchUser) -> AppResult<User>;
+    async fn delete(&self, tenant_id: TenantId, event: DeleteUser) -> AppResult<()>;
+    async fn find_all_deleted(&self, tenant_id: TenantId) -> AppResult<Vec<DeletedUser>>;
+    async fn restore(&self, tenant_id: TenantId, event: RestoreUser) -> AppResult<()>;
+    async fn deactivate(&self, tenant_id: TenantId, event: DeactivateUser) -> AppResult<()>;
+    async fn reactivate(&self, tenant_id: TenantId, event: ReactivateUser) -> AppResult<()>;
+    async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> AppResult<u64>;
 }
\ No newline at end of file
@@ -1,20 +1,41 @@
 use std::sync::Arc;
 
+use adapter::repository::api_key::ApiKeyRepositoryImpl;
+use adapter::repository::audit::AuditLogRepositoryImpl;
+use adapter::repository::role::RoleRepositoryImpl;
+use adapter::repository::tenant::TenantRepositoryImpl;
+use adapter::repository::group::GroupRepositoryImpl;
 use adapter::repository::checkout::CheckoutRepositoryImpl;
+use adapter::repository::login_attempt::LoginAttemptRepositoryImpl;
+use adapter::repository::oidc::OidcRepositoryImpl;
+use adapter::repository::two_factor::TwoFactorRepositoryImpl;
 use adapter::repository::user::UserRepositoryImpl;
 use adapter::{
     database::ConnectionPool,
+    ldap::LdapDirectory,
+    password::{policy::PasswordPolicy, PasswordHasher},
+    mailer::build_mailer,
     redis::RedisClient,
     repository::{
         auth::AuthRepositoryImpl, book::BookRepositoryImpl, health::HealthCheckRepositoryImpl,
     },
 };
+use kernel::directory::Directory;
+use kernel::mailer::Mailer;
+use kernel::repository::api_key::ApiKeyRepository;
+use kernel::repository::audit::AuditLogRepository;
+use kernel::repository::role::RoleRepository;
+use kernel::repository::tenant::TenantRepository;
+use kernel::repository::group::GroupRepository;
 use kernel::repository::auth::AuthRepository;
 use kernel::repository::book::BookRepository;
 use kernel::repository::checkout::CheckoutRepository;
 use kernel::repository::health::HealthCheckRepository;
+use kernel::repository::login_attempt::LoginAttemptRepository;
+use kernel::repository::oidc::OidcRepository;
+use kernel::repository::two_factor::TwoFactorRepository;
 use kernel::repository::user::UserRepository;
-use shared::config::AppConfig;
+use shared::config::{AppConfig, ProxyConfig, SignupConfig, TenantConfig};
 
 #[derive(Clone)]
 pub struct AppRegistry {
@@ -23,6 +44,19 @@ pub struct AppRegistry {
     auth_repository: Arc<dyn AuthRepository>,
     user_repository: Arc<dyn UserRepository>,
     checkout_repository: Arc<dyn CheckoutRepository>,
+    audit_log_repository: Arc<dyn AuditLogRepository>,
+    role_repository: Arc<dyn RoleRepository>,
+    tenant_repository: Arc<dyn TenantRepository>,
+    group_repository: Arc<dyn GroupRepository>,
+    api_key_repository: Arc<dyn ApiKeyRepository>,
+    two_factor_repository: Arc<dyn TwoFactorRepository>,
+    login_attempt_repository: Arc<dyn LoginAttemptRepository>,
+    // OIDC が設定されていない場合は None
+    oidc_repository: Option<Arc<dyn OidcRepository>>,
+    mailer: Arc<dyn Mailer>,
+    signup_config: Arc<SignupConfig>,
+    tenant_config: Arc<TenantConfig>,
+    proxy_config: Arc<ProxyConfig>,
 }
 
 impl AppRegistry {
@@ -33,13 +67,57 @@ impl AppRegistry {
     ) -> Self {
         let health_check_repository = Arc::new(HealthCheckRepositoryImpl::new(pool.clone()));
         let book_repository = Arc::new(BookRepositoryImpl::new(pool.clone()));
+        let hasher = PasswordHasher::new(app_config.password_hash);
+        let policy = PasswordPolicy::new(app_config.password_policy);
+        let directory = app_config
+            .ldap
+            .map(|config| Arc::new(LdapDirectory::new(config)) as Arc<dyn Directory>);
         let auth_repository = Arc::new(AuthRepositoryImpl::new(
             pool.clone(),
             redis_client.clone(),
-            app_config.auth.ttl,
+            app_config.auth,
+            app_config.signup.verification_ttl,
+            directory,
+            hasher.clone(),
+            policy.clone(),
+        ));
+        let user_repository = Arc::new(UserRepositoryImpl::new(
+            pool.clone(),
+            redis_client.clone(),
+            hasher.clone(),
+            policy.clone(),
         ));
-        let user_repository = Arc::new(UserRepositoryImpl::new(pool.clone()));
         let checkout_repository = Arc::new(CheckoutRepositoryImpl::new(pool.clone()));
+        let audit_log_repository = Arc::new(AuditLogRepositoryImpl::new(pool.clone()));
+        let role_repository = Arc::new(RoleRepositoryImpl::new(pool.clone()));
+        let tenant_repository = Arc::new(TenantRepositoryImpl::new(
+            pool.clone(),
+            hasher.clone(),
+            policy,
+        ));
+        let group_repository = Arc::new(GroupRepositoryImpl::new(pool.clone()));
+        let api_key_repository = Arc::new(ApiKeyRepositoryImpl::new(pool.clone()));
+        let two_factor_repository = Arc::new(TwoFactorRepositoryImpl::new(
+            pool.clone(),
+            redis_client.clone(),
+            app_config.two_factor,
+        ));
+        let login_attempt_repository = Arc::new(LoginAttemptRepositoryImpl::new(
+            redis_client.clone(),
+            app_config.login_throttle,
+        ));
+        let oidc_repository = app_config.oidc.map(|config| {
+            Arc::new(OidcRepositoryImpl::new(
+                pool.clone(),
+                redis_client.clone(),
+                config,
+                hasher.clone(),
+            )) as Arc<dyn OidcRepository>
+        });
+        let mailer = build_mailer(app_config.mail).expect("invalid mail configuration");
+        let signup_config = Arc::new(app_config.signup);
+        let tenant_config = Arc::new(app_config.tenant);
+        let proxy_config = Arc::new(app_config.proxy);
 
         Self {
             health_check_repository,
@@ -47,6 +125,18 @@ impl AppRegistry {
             auth_repository,
             user_repository,
             checkout_repository,
+            audit_log_repository,
+            role_repository,
+            tenant_repository,
+            group_repository,
+            api_key_repository,
+            two_factor_repository,
+            login_attempt_repository,
+            oidc_repository,
+            mailer,
+            signup_config,
+            tenant_config,
+            proxy_config,
         }
     }
 
@@ -69,4 +159,52 @@ impl AppRegistry {
     pub fn checkout_repository(&self) -> Arc<dyn CheckoutRepository> {
         self.checkout_repository.clone()
     }
+
+    pub fn audit_log_repository(&self) -> Arc<dyn AuditLogRepository> {
+        self.audit_log_repository.clone()
+    }
+
+    pub fn role_repository(&self) -> Arc<dyn RoleRepository> {
+        self.role_repository.clone()
+    }
+
+    pub fn tenant_repository(&self) -> Arc<dyn TenantRepository> {
+        self.tenant_repository.clone()
+    }
+
+    pub fn group_repository(&self) -> Arc<dyn GroupRepository> {
+        self.group_repository.clone()
+    }
+
+    pub fn api_key_repository(&self) -> Arc<dyn ApiKeyRepository> {
+        self.api_key_repository.clone()
+    }
+
+    pub fn two_factor_repository(&self) -> Arc<dyn TwoFactorRepository> {
+        self.two_factor_repository.clone()
+    }
+
+    pub fn login_attempt_repository(&self) -> Arc<dyn LoginAttemptRepository> {
+        self.login_attempt_repository.clone()
+    }
+
+    pub fn oidc_repository(&self) -> Option<Arc<dyn OidcRepository>> {
+        self.oidc_repository.clone()
+    }
+
+    pub fn mailer(&self) -> Arc<dyn Mailer> {
+        self.mailer.clone()
+    }
+
+    pub fn signup_config(&self) -> Arc<SignupConfig> {
+        self.signup_config.clone()
+    }
+
+    pub fn tenant_config(&self) -> Arc<TenantConfig> {
+        self.tenant_config.clone()
+    }
+
+    pub fn proxy_config(&self) -> Arc<ProxyConfig> {
+        self.proxy_config.clone()
+    }
 }
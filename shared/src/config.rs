@@ -1,9 +1,21 @@
 use anyhow::Result;
+use std::net::IpAddr;
 
 pub struct AppConfig {
     pub database: DatabaseConfig,
     pub redis: RedisConfig,
     pub auth: AuthConfig,
+    pub purge: PurgeConfig,
+    pub signup: SignupConfig,
+    pub two_factor: TwoFactorConfig,
+    pub login_throttle: LoginThrottleConfig,
+    pub oidc: Option<OidcConfig>,
+    pub ldap: Option<LdapConfig>,
+    pub password_hash: PasswordHashConfig,
+    pub password_policy: PasswordPolicyConfig,
+    pub tenant: TenantConfig,
+    pub mail: MailConfig,
+    pub proxy: ProxyConfig,
 }
 
 impl AppConfig {
@@ -19,13 +31,176 @@ impl AppConfig {
             host: std::env::var("REDIS_HOST")?,
             port: std::env::var("REDIS_PORT")?.parse::<u16>()?,
         };
+        let jwt = match std::env::var("AUTH_TOKEN_MODE").as_deref() {
+            Ok("jwt") => Some(JwtConfig {
+                secret: std::env::var("AUTH_JWT_SECRET")?,
+                ttl: env_or("AUTH_JWT_TTL", 900)?,
+                refresh_ttl: env_or("AUTH_REFRESH_TTL", 2592000)?,
+            }),
+            Ok("opaque") | Err(_) => None,
+            Ok(mode) => anyhow::bail!("unknown AUTH_TOKEN_MODE: {mode}"),
+        };
         let auth = AuthConfig {
             ttl: std::env::var("AUTH_TTL")?.parse::<u64>()?,
+            password_reset_ttl: env_or("PASSWORD_RESET_TTL", 3600)?,
+            sliding_expiration: env_or("AUTH_SLIDING_EXPIRATION", false)?,
+            max_lifetime: env_or("AUTH_MAX_LIFETIME", 604800)?,
+            impersonation_ttl: env_or("AUTH_IMPERSONATION_TTL", 900)?,
+            jwt,
+        };
+        let purge = PurgeConfig {
+            retention_days: env_or("PURGE_RETENTION_DAYS", 30)?,
+            interval_secs: env_or("PURGE_INTERVAL_SECS", 3600)?,
+        };
+        if !(1..=MAX_PURGE_RETENTION_DAYS).contains(&purge.retention_days) {
+            anyhow::bail!(
+                "PURGE_RETENTION_DAYS must be between 1 and {MAX_PURGE_RETENTION_DAYS}: {}",
+                purge.retention_days
+            );
+        }
+        if purge.interval_secs == 0 {
+            anyhow::bail!("PURGE_INTERVAL_SECS must be greater than 0");
+        }
+        let signup = SignupConfig {
+            enabled: env_or("SIGNUP_ENABLED", false)?,
+            allowed_domains: std::env::var("SIGNUP_ALLOWED_DOMAINS")
+                .map(|v| {
+                    v.split(',')
+                        .map(|d| d.trim().to_lowercase())
+                        .filter(|d| !d.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            verification_ttl: env_or("SIGNUP_VERIFICATION_TTL", 86400)?,
+        };
+        let two_factor = TwoFactorConfig {
+            issuer: env_or("TOTP_ISSUER", "Book API".to_string())?,
+            challenge_ttl: env_or("LOGIN_CHALLENGE_TTL", 300)?,
+        };
+        let login_throttle = LoginThrottleConfig {
+            max_attempts: env_or("LOGIN_MAX_ATTEMPTS", 5)?,
+            ip_max_attempts: env_or("LOGIN_IP_MAX_ATTEMPTS", 50)?,
+            attempt_window: env_or("LOGIN_ATTEMPT_WINDOW", 900)?,
+            lockout_duration: env_or("LOGIN_LOCKOUT_DURATION", 900)?,
+            max_delay: env_or("LOGIN_MAX_DELAY", 30)?,
+        };
+        let oidc = match std::env::var("OIDC_ISSUER") {
+            Ok(issuer) => Some(OidcConfig {
+                issuer: issuer.trim_end_matches('/').to_string(),
+                client_id: std::env::var("OIDC_CLIENT_ID")?,
+                client_secret: std::env::var("OIDC_CLIENT_SECRET").ok(),
+                redirect_uri: std::env::var("OIDC_REDIRECT_URI")?,
+                scopes: env_or("OIDC_SCOPES", "openid email profile".to_string())?,
+                groups_claim: env_or("OIDC_GROUPS_CLAIM", "groups".to_string())?,
+                admin_groups: std::env::var("OIDC_ADMIN_GROUPS")
+                    .map(|v| {
+                        v.split(',')
+                            .map(|g| g.trim().to_string())
+                            .filter(|g| !g.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                state_ttl: env_or("OIDC_STATE_TTL", 600)?,
+                signing_algorithms: env_or("OIDC_SIGNING_ALGORITHMS", "RS256".to_string())?
+                    .split(',')
+                    .map(|a| a.trim().to_string())
+                    .filter(|a| !a.is_empty())
+                    .collect(),
+            }),
+            Err(_) => None,
+        };
+        let ldap = match std::env::var("LDAP_URL") {
+            Ok(url) => Some(LdapConfig {
+                url,
+                bind_dn_template: std::env::var("LDAP_BIND_DN_TEMPLATE")?,
+                search_base: std::env::var("LDAP_SEARCH_BASE")?,
+                search_filter: env_or("LDAP_SEARCH_FILTER", "(mail={username})".to_string())?,
+                name_attribute: env_or("LDAP_NAME_ATTRIBUTE", "cn".to_string())?,
+                email_attribute: env_or("LDAP_EMAIL_ATTRIBUTE", "mail".to_string())?,
+                starttls: env_or("LDAP_STARTTLS", false)?,
+                timeout: env_or("LDAP_TIMEOUT", 5)?,
+            }),
+            Err(_) => None,
+        };
+        let algorithm = match std::env::var("PASSWORD_HASH_ALGORITHM").as_deref() {
+            Ok("argon2id") | Err(_) => PasswordHashAlgorithm::Argon2id,
+            Ok("bcrypt") => PasswordHashAlgorithm::Bcrypt,
+            Ok(algorithm) => anyhow::bail!("unknown PASSWORD_HASH_ALGORITHM: {algorithm}"),
+        };
+        let password_hash = PasswordHashConfig {
+            algorithm,
+            argon2_memory_kib: env_or("ARGON2_MEMORY_KIB", 19456)?,
+            argon2_iterations: env_or("ARGON2_ITERATIONS", 2)?,
+            argon2_parallelism: env_or("ARGON2_PARALLELISM", 1)?,
+            bcrypt_cost: env_or("BCRYPT_COST", 12)?,
+        };
+        let password_policy = PasswordPolicyConfig {
+            min_length: env_or("PASSWORD_MIN_LENGTH", 8)?,
+            min_character_classes: env_or("PASSWORD_MIN_CHARACTER_CLASSES", 2)?,
+            disallow_personal_info: env_or("PASSWORD_DISALLOW_PERSONAL_INFO", true)?,
+            history_size: env_or("PASSWORD_HISTORY_SIZE", 5)?,
+            reject_common: env_or("PASSWORD_REJECT_COMMON", true)?,
+        };
+        let tenant = TenantConfig {
+            base_domain: std::env::var("TENANT_BASE_DOMAIN")
+                .ok()
+                .map(|d| d.trim().to_lowercase())
+                .filter(|d| !d.is_empty()),
+            default_slug: env_or("TENANT_DEFAULT", "default".to_string())
+                .map(|slug| Some(slug).filter(|s| !s.is_empty()))?,
+        };
+        let security = match std::env::var("SMTP_SECURITY").as_deref() {
+            Ok("starttls") | Err(_) => SmtpSecurity::StartTls,
+            Ok("tls") => SmtpSecurity::Tls,
+            Ok("none") => SmtpSecurity::None,
+            Ok(security) => anyhow::bail!("unknown SMTP_SECURITY: {security}"),
+        };
+        let smtp = match std::env::var("SMTP_HOST") {
+            Ok(host) => Some(SmtpConfig {
+                port: env_or(
+                    "SMTP_PORT",
+                    match security {
+                        SmtpSecurity::Tls => 465,
+                        SmtpSecurity::StartTls => 587,
+                        SmtpSecurity::None => 25,
+                    },
+                )?,
+                host,
+                username: std::env::var("SMTP_USERNAME").ok(),
+                password: std::env::var("SMTP_PASSWORD").ok(),
+                from: std::env::var("MAIL_FROM")?,
+                security,
+            }),
+            Err(_) => None,
+        };
+        let mail = MailConfig {
+            smtp,
+            log_body: env_or("MAIL_LOG_BODY", false)?,
+        };
+        let proxy = ProxyConfig {
+            trusted_proxies: std::env::var("TRUSTED_PROXIES")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()?,
         };
         Ok(Self {
             database,
             redis,
             auth,
+            purge,
+            signup,
+            two_factor,
+            login_throttle,
+            oidc,
+            ldap,
+            password_hash,
+            password_policy,
+            tenant,
+            mail,
+            proxy,
         })
     }
 }
@@ -42,6 +217,261 @@ pub struct RedisConfig {
     pub port: u16,
 }
 
+#[derive(Clone)]
 pub struct AuthConfig {
     pub ttl: u64,
+    pub password_reset_ttl: u64,
+    /// 有効にするとトークンを使うたびに有効期限を延長する。延長は `max_lifetime` 秒までに制限する
+    pub sliding_expiration: bool,
+    pub max_lifetime: u64,
+    /// 管理者による代理ログインのトークンの有効期限（秒）。延長はしない
+    pub impersonation_ttl: u64,
+    /// 設定されている場合は Redis に保存するトークンの代わりに JWT を発行する
+    pub jwt: Option<JwtConfig>,
+}
+
+/// JWT アクセストークンとリフレッシュトークンの設定。
+/// アクセストークンはリクエストごとに Redis を参照せずに検証するため、ログアウトやセッションの失効は
+/// 次のリフレッシュまで反映されない。失効までの猶予が ttl 秒になるよう、ttl は短く保つ
+#[derive(Clone)]
+pub struct JwtConfig {
+    pub secret: String,
+    pub ttl: u64,
+    pub refresh_ttl: u64,
+}
+
+/// 保持期間の上限。日時の計算があふれないよう 100 年までとする
+const MAX_PURGE_RETENTION_DAYS: i64 = 36500;
+
+/// 論理削除したデータを物理削除するまでの保持期間と、削除ジョブの実行間隔
+#[derive(Clone)]
+pub struct PurgeConfig {
+    pub retention_days: i64,
+    pub interval_secs: u64,
+}
+
+/// セルフサインアップの設定。許可ドメインが空の場合はドメインを制限しない
+pub struct SignupConfig {
+    pub enabled: bool,
+    pub allowed_domains: Vec<String>,
+    pub verification_ttl: u64,
+}
+
+/// TOTP による二要素認証の設定。challenge_ttl はパスワード認証後に二要素目を待つ秒数
+pub struct TwoFactorConfig {
+    pub issuer: String,
+    pub challenge_ttl: u64,
+}
+
+/// ログイン失敗時の制限。attempt_window 秒以内の失敗回数を数え、しきい値に達したらロックする。
+/// それまでの失敗には 1 秒から max_delay 秒まで倍々に増える待ち時間を課す
+pub struct LoginThrottleConfig {
+    pub max_attempts: u64,
+    pub ip_max_attempts: u64,
+    pub attempt_window: u64,
+    pub lockout_duration: u64,
+    pub max_delay: u64,
+}
+
+/// OpenID Connect によるシングルサインオンの設定。OIDC_ISSUER が未設定の場合は無効になる。
+/// admin_groups が空の場合はグループによるロールの割り当てを行わない。
+/// signing_algorithms は ID トークンの署名方式として受け付けるもので、トークンのヘッダーの指定は信用しない
+#[derive(Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+    pub groups_claim: String,
+    pub admin_groups: Vec<String>,
+    pub state_ttl: u64,
+    pub signing_algorithms: Vec<String>,
+}
+
+/// LDAP のシンプルバインドによる認証の設定。LDAP_URL が未設定の場合は無効になる。
+/// bind_dn_template と search_filter の `{username}` はログイン時に入力された値に置き換える
+pub struct LdapConfig {
+    pub url: String,
+    pub bind_dn_template: String,
+    pub search_base: String,
+    pub search_filter: String,
+    pub name_attribute: String,
+    pub email_attribute: String,
+    pub starttls: bool,
+    pub timeout: u64,
+}
+
+/// パスワードのハッシュ方式。保存済みのハッシュが設定と異なる場合はログイン時に作り直す
+#[derive(Clone)]
+pub struct PasswordHashConfig {
+    pub algorithm: PasswordHashAlgorithm,
+    pub argon2_memory_kib: u32,
+    pub argon2_iterations: u32,
+    pub argon2_parallelism: u32,
+    pub bcrypt_cost: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasswordHashAlgorithm {
+    Argon2id,
+    Bcrypt,
+}
+
+/// パスワードの規則。文字種は英小文字・英大文字・数字・記号の 4 種類から数える。
+/// history_size は再利用を禁止する直近のパスワードの数で、0 の場合は確認しない
+#[derive(Clone)]
+pub struct PasswordPolicyConfig {
+    pub min_length: usize,
+    pub min_character_classes: usize,
+    pub disallow_personal_info: bool,
+    pub history_size: i64,
+    pub reject_common: bool,
+}
+
+impl SignupConfig {
+    pub fn is_allowed_email(&self, email: &str) -> bool {
+        if self.allowed_domains.is_empty() {
+            return true;
+        }
+        email
+            .rsplit_once('@')
+            .map(|(_, domain)| domain.to_lowercase())
+            .is_some_and(|domain| self.allowed_domains.contains(&domain))
+    }
+}
+
+/// リクエストが対象とするテナントの決め方。X-Tenant ヘッダー、base_domain のサブドメインの順に参照し、
+/// どちらもなければ default_slug のテナントとする。default_slug が None の場合は指定を必須にする
+pub struct TenantConfig {
+    pub base_domain: Option<String>,
+    pub default_slug: Option<String>,
+}
+
+impl TenantConfig {
+    /// "central.library.example.com" のようなホスト名からテナントの識別子を取り出す
+    pub fn slug_from_host(&self, host: &str) -> Option<String> {
+        let base_domain = self.base_domain.as_deref()?;
+        let host = host.split(':').next()?.to_lowercase();
+        let slug = host.strip_suffix(base_domain)?.strip_suffix('.')?;
+        (!slug.is_empty() && !slug.contains('.')).then(|| slug.to_string())
+    }
+}
+
+/// メール送信の設定。SMTP_HOST が未設定の場合は送信せずにログへ出力する。
+/// log_body は開発用で、有効にすると本文（トークンを含む）を debug レベルで出力する
+pub struct MailConfig {
+    pub smtp: Option<SmtpConfig>,
+    pub log_body: bool,
+}
+
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub security: SmtpSecurity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    Tls,
+    StartTls,
+    None,
+}
+
+/// X-Forwarded-For を信頼するリバースプロキシの IP アドレス。
+/// 空の場合はヘッダーを無視し、接続元のアドレスをそのまま使う
+pub struct ProxyConfig {
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+impl ProxyConfig {
+    /// 接続元が信頼するプロキシの場合だけ X-Forwarded-For を末尾から辿り、
+    /// 信頼するプロキシ以外で最初に現れたアドレスを利用者のものとする
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        let mut client = peer;
+        let Some(forwarded_for) = forwarded_for else {
+            return client;
+        };
+        for addr in forwarded_for.rsplit(',') {
+            if !self.trusted_proxies.contains(&client) {
+                break;
+            }
+            match addr.trim().parse() {
+                Ok(addr) => client = addr,
+                Err(_) => break,
+            }
+        }
+        client
+    }
+}
+
+/// 環境変数が設定されていなければ既定値を返す
+fn env_or<T>(key: &str, default: T) -> Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match std::env::var(key) {
+        Ok(v) => Ok(v.parse::<T>()?),
+        Err(_) => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy(trusted: &[&str]) -> ProxyConfig {
+        ProxyConfig {
+            trusted_proxies: trusted.iter().map(|p| p.parse().unwrap()).collect(),
+        }
+    }
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().unwrap()
+    }
+
+    #[test]
+    fn test_client_ip_ignores_header_from_untrusted_peer() {
+        let config = proxy(&["10.0.0.1"]);
+        let client = config.client_ip(ip("203.0.113.5"), Some("198.51.100.7"));
+        assert_eq!(client, ip("203.0.113.5"));
+
+        // 信頼するプロキシがなければヘッダーは常に無視する
+        let client = proxy(&[]).client_ip(ip("10.0.0.1"), Some("198.51.100.7"));
+        assert_eq!(client, ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn test_client_ip_through_single_proxy() {
+        let config = proxy(&["10.0.0.1"]);
+        let client = config.client_ip(ip("10.0.0.1"), Some("203.0.113.5"));
+        assert_eq!(client, ip("203.0.113.5"));
+
+        // 利用者が先頭に付け足したアドレスは使わない
+        let client = config.client_ip(ip("10.0.0.1"), Some("198.51.100.7, 203.0.113.5"));
+        assert_eq!(client, ip("203.0.113.5"));
+
+        assert_eq!(config.client_ip(ip("10.0.0.1"), None), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn test_client_ip_through_proxy_chain() {
+        let config = proxy(&["10.0.0.1", "10.0.0.2"]);
+        let client = config.client_ip(ip("10.0.0.1"), Some("198.51.100.7, 203.0.113.5, 10.0.0.2"));
+        assert_eq!(client, ip("203.0.113.5"));
+    }
+
+    #[test]
+    fn test_client_ip_stops_at_invalid_entry() {
+        let config = proxy(&["10.0.0.1", "10.0.0.2"]);
+        let client = config.client_ip(ip("10.0.0.1"), Some("203.0.113.5, unknown, 10.0.0.2"));
+        assert_eq!(client, ip("10.0.0.2"));
+
+        let client = config.client_ip(ip("10.0.0.1"), Some("not-an-ip"));
+        assert_eq!(client, ip("10.0.0.1"));
+    }
 }
@@ -1,5 +1,10 @@
 use axum::response::Response;
-use axum::{http::StatusCode, response::IntoResponse};
+use axum::{
+    Json,
+    http::{StatusCode, header},
+    response::IntoResponse,
+};
+use serde::Serialize;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -9,7 +14,11 @@ pub enum AppError {
     #[error("{0}")]
     EntityNotFound(String),
     #[error("{0}")]
+    Conflict(String),
+    #[error("{0}")]
     ValidationError(#[from] garde::Report),
+    #[error("パスワードがポリシーを満たしていません。")]
+    PasswordPolicyViolation(Vec<PasswordRuleViolation>),
     #[error("トランザクションを実行できませんでした。")]
     TransactionError(#[source] sqlx::Error),
     #[error("データベース処理実行中にエラーが発生しました。")]
@@ -21,35 +30,101 @@ pub enum AppError {
     #[error("{0}")]
     BcryptError(#[from] bcrypt::BcryptError),
     #[error("{0}")]
+    PasswordHashError(String),
+    #[error("{0}")]
     ConvertToUuidError(#[from] uuid::Error),
     #[error("{0}")]
     ConvertToDateTimeError(#[from] chrono::ParseError),
     #[error("ログインに失敗しました。")]
     UnauthenticatedError,
+    #[error("メールアドレスの確認が完了していません。")]
+    EmailNotVerified,
+    #[error("このアカウントは無効化されています。")]
+    AccountDeactivated,
+    #[error("ログインの失敗が続いたため、アカウントを一時的にロックしています。")]
+    AccountLocked(u64),
+    #[error("試行回数が多すぎます。しばらくしてから再度お試しください。")]
+    TooManyRequests(u64),
     #[error("認可情報が誤っています。")]
     UnauthorizedError,
     #[error("認可されていない操作です。")]
     ForbiddenOperationError,
     #[error("{0}")]
+    PreconditionFailed(String),
+    #[error("If-Match ヘッダを指定してください。")]
+    PreconditionRequired,
+    #[error("{0}")]
     ConversionEntityError(String),
+    #[error("{0}")]
+    ExternalServiceError(String),
+}
+
+// パスワードポリシーのうち満たしていない規則
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordRuleViolation {
+    pub rule: PasswordRule,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PasswordRule {
+    MinLength,
+    CharacterClasses,
+    PersonalInfo,
+    CommonPassword,
+    Reuse,
+}
+
+#[derive(Serialize)]
+struct PasswordPolicyErrorBody<'a> {
+    message: String,
+    violations: &'a [PasswordRuleViolation],
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        // どの規則を満たしていないかをクライアントに示すため、本文に一覧を含める
+        if let AppError::PasswordPolicyViolation(violations) = &self {
+            let body = PasswordPolicyErrorBody {
+                message: self.to_string(),
+                violations,
+            };
+            return (StatusCode::BAD_REQUEST, Json(body)).into_response();
+        }
+        // 再試行できるまでの秒数を Retry-After ヘッダで返す
+        let retry_after = match self {
+            AppError::AccountLocked(secs) | AppError::TooManyRequests(secs) => Some(secs),
+            _ => None,
+        };
         let staus_code = match self {
             AppError::UnprocessableEntity(_) => StatusCode::UNPROCESSABLE_ENTITY,
             AppError::EntityNotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
             AppError::ValidationError(_) => StatusCode::BAD_REQUEST,
+            AppError::PasswordPolicyViolation(_) => StatusCode::BAD_REQUEST,
             AppError::ConvertToUuidError(_) => StatusCode::BAD_REQUEST,
             AppError::ConvertToDateTimeError(_) => StatusCode::BAD_REQUEST,
             AppError::UnauthenticatedError => StatusCode::FORBIDDEN,
+            AppError::EmailNotVerified => StatusCode::FORBIDDEN,
+            AppError::AccountDeactivated => StatusCode::FORBIDDEN,
+            AppError::AccountLocked(_) => StatusCode::LOCKED,
+            AppError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
             AppError::ForbiddenOperationError => StatusCode::FORBIDDEN,
             AppError::UnauthorizedError => StatusCode::UNAUTHORIZED,
+            AppError::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            AppError::PreconditionRequired => StatusCode::PRECONDITION_REQUIRED,
+            AppError::ExternalServiceError(ref message) => {
+                tracing::warn!(error.message = %message, "External service failed");
+                StatusCode::BAD_GATEWAY
+            }
             e @ (AppError::TransactionError(_)
             | AppError::SpecificOperationError(_)
             | AppError::NoRowsAffectedError(_)
             | AppError::KeyValueStoreError(_)
             | AppError::BcryptError(_)
+            | AppError::PasswordHashError(_)
             | AppError::ConversionEntityError(_)) => {
                 tracing::error!(
                     error.cause_chain = ?e,
@@ -59,7 +134,10 @@ impl IntoResponse for AppError {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
         };
-        staus_code.into_response()
+        match retry_after {
+            Some(secs) => (staus_code, [(header::RETRY_AFTER, secs.to_string())]).into_response(),
+            None => staus_code.into_response(),
+        }
     }
 }
 
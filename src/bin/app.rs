@@ -3,13 +3,14 @@ use anyhow::{Context, Result};
 use api::openapi::ApiDoc;
 use api::route::{auth, v1};
 use axum::Router;
-use axum::http::Method;
+use axum::http::{Method, header};
 use registry::AppRegistry;
-use shared::config::AppConfig;
+use shared::config::{AppConfig, PurgeConfig};
 use shared::env::{Environment, which};
 use std::{
     net::{Ipv4Addr, SocketAddr},
     sync::Arc,
+    time::Duration,
 };
 use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
@@ -31,7 +32,14 @@ async fn main() -> Result<()> {
 fn cors() -> CorsLayer {
     CorsLayer::new()
         .allow_headers(cors::Any)
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .expose_headers([header::ETAG])
         .allow_origin(cors::Any)
 }
 
@@ -39,9 +47,12 @@ async fn bootstrap() -> Result<()> {
     let app_config = AppConfig::new()?;
     let pool = connect_database_with(&app_config.database);
     let kv = Arc::new(RedisClient::new(&app_config.redis)?);
+    let purge_config = app_config.purge.clone();
 
     let registry = AppRegistry::new(pool, kv, app_config);
 
+    tokio::spawn(purge_deleted_entities(registry.clone(), purge_config));
+
     let router = Router::new().merge(v1::routes()).merge(auth::routes());
 
     #[cfg(debug_assertions)]
@@ -65,7 +76,11 @@ async fn bootstrap() -> Result<()> {
     let listener = TcpListener::bind(addr).await?;
 
     tracing::info!("Listening on {}", addr);
-    axum::serve(listener, app)
+    // セッションに接続元の IP アドレスを記録するため ConnectInfo を有効にする
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
         .await
         .context("Unexpected error while serving")
         .inspect_err(|e| {
@@ -75,6 +90,28 @@ async fn bootstrap() -> Result<()> {
         })
 }
 
+/// 保持期間を過ぎた論理削除済みの蔵書とユーザーを定期的に物理削除する
+async fn purge_deleted_entities(registry: AppRegistry, config: PurgeConfig) {
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    loop {
+        interval.tick().await;
+
+        let deleted_before = chrono::Utc::now() - chrono::Duration::days(config.retention_days);
+        let books = registry.book_repository().purge_deleted(deleted_before).await;
+        let users = registry.user_repository().purge_deleted(deleted_before).await;
+        match (books, users) {
+            (Ok(books), Ok(users)) => {
+                tracing::info!(books, users, "Purged soft-deleted entities");
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::error!(
+                    error.cause_chain = ?e, error.message = %e, "Failed to purge soft-deleted entities"
+                );
+            }
+        }
+    }
+}
+
 fn init_logger() -> Result<()> {
     let log_level = match which() {
         Environment::Development => "debug",